@@ -1,4 +1,7 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/marketstore.proto")?;
+    tonic_build::configure().compile(
+        &["proto/marketstore.proto", "proto/replication.proto"],
+        &["proto"],
+    )?;
     Ok(())
-} 
\ No newline at end of file
+}
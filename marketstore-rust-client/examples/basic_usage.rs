@@ -1,8 +1,8 @@
 use marketstore_rust_client::{
-    MarketStoreClient, OHLCVData, StreamSubscription, SymbolFormat, DataShape, StreamPayload,
+    MarketStoreClient, OHLCVData, StreamSubscription, SymbolFormat, DataShape, StreamEvent,
     error::Result,
 };
-use tokio::sync::oneshot;
+use futures::StreamExt;
 use tracing::info;
 
 #[tokio::main]
@@ -92,25 +92,42 @@ async fn main() -> Result<()> {
     let subscription = StreamSubscription::new()
         .add_stream("EXAMPLE/1Min/OHLCV");
 
-    let received_count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let received_count_clone = received_count.clone();
-    let handler = move |payload: StreamPayload| {
-        let count = received_count_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        info!("📡 Received #{}: {} = {:?}", count + 1, payload.key, payload.data);
-        Ok(())
-    };
-
-    let (tx, rx) = oneshot::channel();
-    let stream_handle = client.subscribe_realtime_with_cancel(subscription, handler, rx).await?;
+    let mut stream = client.subscribe_stream(subscription, 1024).await?;
     
-    // 等待10秒
-    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+    // 接收10秒
+    let mut received_count = 0;
+    let deadline = tokio::time::sleep(tokio::time::Duration::from_secs(10));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            event = stream.next() => match event {
+                Some(Ok(StreamEvent::Payload(payload))) => {
+                    received_count += 1;
+                    info!("📡 Received #{}: {} = {:?}", received_count, payload.key, payload.data);
+                }
+                Some(Ok(StreamEvent::Lagged { dropped })) => {
+                    info!("⚠️  Dropped {} messages", dropped);
+                }
+                Some(Ok(StreamEvent::ServerError { message })) => {
+                    info!("⚠️  Stream server error: {}", message);
+                }
+                Some(Ok(StreamEvent::Undecodable { error, .. })) => {
+                    info!("⚠️  Undecodable stream frame: {}", error);
+                }
+                Some(Err(e)) => {
+                    info!("❌ Subscription error: {}", e);
+                    break;
+                }
+                None => break,
+            },
+            _ = &mut deadline => break,
+        }
+    }
     
-    // 发送取消信号
-    let _ = tx.send(());
-    let _ = stream_handle.await;
+    // drop或close都会取消订阅，close会等待关闭握手完成
+    stream.close().await?;
     
-    info!("✅ Real-time subscription completed, received {} messages", received_count.load(std::sync::atomic::Ordering::SeqCst));
+    info!("✅ Real-time subscription completed, received {} messages", received_count);
 
     // 8. 清理测试数据
     client.destroy_bucket("EXAMPLE", "1Min", "OHLCV").await?;
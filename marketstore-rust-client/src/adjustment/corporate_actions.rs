@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{NumpyDataset, OHLCVData, QueryRequest},
+    client::GrpcClientTrait,
+    utils::{bucket_key, column_type_width, numpy_dataset_to_records},
+};
+
+/// ICE公司行动导入器写入的bucket后缀，完整key为 `<symbol>/1D/ACTIONS`
+pub const ACTIONS_BUCKET_SUFFIX: &str = "/1D/ACTIONS";
+
+/// 价格保留的小数位数，与服务器 `uda/adjust` 一致
+const PRICE_DECIMALS: f64 = 10_000.0;
+
+/// 公司行动类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    /// 拆股（含合股），`value` 为新股数/旧股数，如4拆1为4.0、10合1为0.1
+    Split,
+    /// 送股，`value` 为送股后/送股前的股数比例，如每10股送1股为1.1
+    StockDividend,
+    /// 现金分红，`value` 为每股派息金额
+    CashDividend,
+}
+
+/// 一次公司行动，`epoch` 为除权日，该时间及之后的bar为除权后价格
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CorporateAction {
+    pub epoch: i64,
+    pub kind: ActionKind,
+    pub value: f64,
+}
+
+/// 复权方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdjustMode {
+    /// 不复权，返回原始数据
+    Raw,
+    /// 以最新价格为基准调整除权日之前的历史数据（前复权），与服务器 `uda/adjust` 相同
+    #[default]
+    Backward,
+    /// 以最早价格为基准调整除权日及之后的数据（后复权）
+    Forward,
+}
+
+/// 按symbol组织的公司行动表
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorporateActions {
+    #[serde(flatten)]
+    actions: BTreeMap<String, Vec<CorporateAction>>,
+}
+
+impl CorporateActions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从 `{"AAPL": [{"epoch": ..., "kind": "split", "value": 4.0}], ...}` 形式的JSON读取
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn add(&mut self, symbol: &str, action: CorporateAction) -> &mut Self {
+        let actions = self.actions.entry(symbol.to_string()).or_default();
+        actions.push(action);
+        actions.sort_by_key(|action| action.epoch);
+        self
+    }
+
+    /// symbol的公司行动，按除权日升序
+    pub fn get(&self, symbol: &str) -> &[CorporateAction] {
+        self.actions.get(symbol).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// 解码 `<symbol>/1D/ACTIONS` bucket的查询结果，按服务器 `uda/adjust` 的规则合并公告
+    ///
+    /// 新公告（N）按TextNumber登记，更新公告（U）替换较早的同号公告，删除公告（D）移除同号公告；
+    /// 拆股/合股（`7`/`+`）与送股（`/`）以ExpirationDate为除权日、Rate为比例，其它类型忽略。
+    pub fn from_actions_dataset(symbol: &str, dataset: &NumpyDataset) -> Result<Self> {
+        let records = numpy_dataset_to_records(dataset)?;
+        let int = |record: &HashMap<String, serde_json::Value>, name: &str| {
+            record
+                .get(name)
+                .and_then(|value| value.as_i64())
+                .ok_or_else(|| MarketStoreError::InvalidData(format!("Missing column {} in corporate actions", name)))
+        };
+
+        // TextNumber -> (公告时间, 记录下标)
+        let mut effective: HashMap<i64, (i64, usize)> = HashMap::new();
+        for (index, record) in records.iter().enumerate() {
+            let entry_date = int(record, "Epoch")?;
+            match int(record, "Status")? as u8 {
+                b'N' => {
+                    effective.insert(int(record, "TextNumber")?, (entry_date, index));
+                }
+                b'U' => {
+                    let text_number = int(record, "UpdateTextNumber")?;
+                    if effective.get(&text_number).is_none_or(|&(previous, _)| entry_date > previous) {
+                        effective.insert(text_number, (entry_date, index));
+                    }
+                }
+                b'D' => {
+                    effective.remove(&int(record, "DeleteTextNumber")?);
+                }
+                _ => {}
+            }
+        }
+
+        let mut actions = Self::new();
+        for (_, index) in effective.into_values() {
+            let record = &records[index];
+            let kind = match int(record, "NotificationType")? as u8 {
+                b'7' | b'+' => ActionKind::Split,
+                b'/' => ActionKind::StockDividend,
+                _ => continue,
+            };
+            let value = record
+                .get("Rate")
+                .and_then(|value| value.as_f64())
+                .ok_or_else(|| MarketStoreError::InvalidData("Missing column Rate in corporate actions".to_string()))?;
+            actions.add(symbol, CorporateAction { epoch: int(record, "ExpirationDate")?, kind, value });
+        }
+        Ok(actions)
+    }
+
+    /// 查询 `<symbol>/1D/ACTIONS` bucket并解码；bucket不存在时返回查询错误
+    pub async fn query<C: GrpcClientTrait + Send>(client: &mut C, symbol: &str) -> Result<Self> {
+        let request = QueryRequest {
+            destination: format!("{}{}", symbol, ACTIONS_BUCKET_SUFFIX),
+            epoch_start: None,
+            epoch_end: None,
+            limit_record_count: Some(i32::MAX),
+            limit_from_start: true,
+            columns: Vec::new(),
+        };
+        match client.query(request).await?.data {
+            Some(dataset) => Self::from_actions_dataset(symbol, &dataset),
+            None => Ok(Self::new()),
+        }
+    }
+}
+
+/// 对解码后的OHLCV数据应用拆股与分红因子
+///
+/// 拆股与送股同时调整价格与成交量，现金分红只调整价格，因子为除权日前一个交易日收盘价的
+/// `Close / (Close - 派息)`。该收盘价不从查询窗口推断，需通过 `with_previous_close` 提供或由
+/// `resolve_previous_closes` 查询日线bucket，缺失时复权返回错误。价格保留4位小数。
+#[derive(Debug, Clone)]
+pub struct Adjuster {
+    actions: Vec<CorporateAction>,
+    mode: AdjustMode,
+    splits: bool,
+    dividends: bool,
+    /// 除权日 -> 前一个交易日的收盘价
+    previous_closes: BTreeMap<i64, f64>,
+}
+
+impl Adjuster {
+    pub fn new(actions: &[CorporateAction]) -> Self {
+        let mut actions = actions.to_vec();
+        actions.sort_by_key(|action| action.epoch);
+        Self {
+            actions,
+            mode: AdjustMode::default(),
+            splits: true,
+            dividends: true,
+            previous_closes: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: AdjustMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 是否应用拆股/合股，默认应用
+    pub fn with_splits(mut self, enabled: bool) -> Self {
+        self.splits = enabled;
+        self
+    }
+
+    /// 是否应用送股与现金分红，默认应用
+    pub fn with_dividends(mut self, enabled: bool) -> Self {
+        self.dividends = enabled;
+        self
+    }
+
+    /// 设置除权日为 `ex_date` 的现金分红使用的前一交易日收盘价
+    pub fn with_previous_close(mut self, ex_date: i64, close: f64) -> Self {
+        self.previous_closes.insert(ex_date, close);
+        self
+    }
+
+    /// 为尚未提供收盘价的现金分红查询 `<symbol>/1D/OHLCV` 中除权日之前的最后一根日线
+    pub async fn resolve_previous_closes<C: GrpcClientTrait + Send>(mut self, client: &mut C, symbol: &str) -> Result<Self> {
+        let ex_dates: Vec<i64> = self
+            .actions
+            .iter()
+            .filter(|action| action.kind == ActionKind::CashDividend && !self.previous_closes.contains_key(&action.epoch))
+            .map(|action| action.epoch)
+            .collect();
+
+        for ex_date in ex_dates {
+            let request = QueryRequest {
+                destination: bucket_key(symbol, "1D", "OHLCV"),
+                epoch_start: None,
+                epoch_end: Some(ex_date - 1),
+                limit_record_count: Some(1),
+                limit_from_start: false,
+                columns: Vec::new(),
+            };
+            let records = match client.query(request).await?.data {
+                Some(dataset) => numpy_dataset_to_records(&dataset)?,
+                None => Vec::new(),
+            };
+            let close = records
+                .last()
+                .and_then(|record| record.get("Close"))
+                .and_then(|value| value.as_f64())
+                .ok_or_else(|| {
+                    MarketStoreError::InvalidData(format!("No daily close for {} before ex-date {}", symbol, ex_date))
+                })?;
+            self.previous_closes.insert(ex_date, close);
+        }
+        Ok(self)
+    }
+
+    pub fn mode(&self) -> AdjustMode {
+        self.mode
+    }
+
+    pub fn adjust_bars(&self, bars: &[OHLCVData]) -> Result<Vec<OHLCVData>> {
+        let epochs: Vec<i64> = bars.iter().map(|bar| bar.epoch).collect();
+        let factors = self.factors(&epochs)?;
+
+        Ok(bars
+            .iter()
+            .zip(factors)
+            .map(|(bar, (price, volume))| OHLCVData {
+                epoch: bar.epoch,
+                open: round_price(bar.open as f64 * price) as f32,
+                high: round_price(bar.high as f64 * price) as f32,
+                low: round_price(bar.low as f64 * price) as f32,
+                close: round_price(bar.close as f64 * price) as f32,
+                volume: (bar.volume as f64 * volume) as f32,
+            })
+            .collect())
+    }
+
+    /// 调整查询结果中的Open/High/Low/Close与Volume列，其它列原样保留
+    pub fn adjust_dataset(&self, dataset: &NumpyDataset) -> Result<NumpyDataset> {
+        let length = dataset.length.max(0) as usize;
+        let column = |name: &str| -> Result<Option<Vec<f64>>> {
+            match dataset.column_names.iter().position(|column| column == name) {
+                Some(index) => Ok(Some(read_numbers(&dataset.column_types[index], &dataset.column_data[index], length)?)),
+                None => Ok(None),
+            }
+        };
+        let epochs: Vec<i64> = column("Epoch")?
+            .ok_or_else(|| MarketStoreError::InvalidData("Missing column Epoch".to_string()))?
+            .into_iter()
+            .map(|epoch| epoch as i64)
+            .collect();
+        let factors = self.factors(&epochs)?;
+
+        let mut adjusted = dataset.clone();
+        for (index, name) in dataset.column_names.iter().enumerate() {
+            let is_price = matches!(name.as_str(), "Open" | "High" | "Low" | "Close");
+            if !is_price && name != "Volume" {
+                continue;
+            }
+            let column_type = &dataset.column_types[index];
+            let values = read_numbers(column_type, &dataset.column_data[index], length)?;
+            let values: Vec<f64> = values
+                .into_iter()
+                .zip(&factors)
+                .map(|(value, &(price, volume))| if is_price { round_price(value * price) } else { value * volume })
+                .collect();
+            adjusted.column_data[index] = write_numbers(column_type, &values)?;
+        }
+        Ok(adjusted)
+    }
+
+    /// 每行的 (价格因子, 成交量因子)
+    fn factors(&self, epochs: &[i64]) -> Result<Vec<(f64, f64)>> {
+        if self.mode == AdjustMode::Raw {
+            return Ok(vec![(1.0, 1.0); epochs.len()]);
+        }
+
+        // 每次行动的 (除权日, 价格比例, 成交量比例)，比例为除权前价格/除权后价格
+        let mut ratios = Vec::with_capacity(self.actions.len());
+        for action in &self.actions {
+            let ratio = match action.kind {
+                ActionKind::Split if self.splits => Some((action.value, action.value)),
+                ActionKind::StockDividend if self.dividends => Some((action.value, action.value)),
+                ActionKind::CashDividend if self.dividends => {
+                    let close = *self.previous_closes.get(&action.epoch).ok_or_else(|| {
+                        MarketStoreError::InvalidData(format!(
+                            "Missing previous close for cash dividend at {}",
+                            action.epoch
+                        ))
+                    })?;
+                    if close > action.value && action.value > 0.0 {
+                        Some((close / (close - action.value), 1.0))
+                    } else {
+                        tracing::warn!("Skipping cash dividend of {} at {} with previous close {}", action.value, action.epoch, close);
+                        None
+                    }
+                }
+                _ => None,
+            };
+            if let Some((price, volume)) = ratio {
+                if !(price > 0.0 && volume > 0.0) {
+                    return Err(MarketStoreError::InvalidData(format!(
+                        "Invalid corporate action value {} at {}",
+                        action.value, action.epoch
+                    )));
+                }
+                ratios.push((action.epoch, price, volume));
+            }
+        }
+
+        // prefix[k]为前k次行动的累计比例
+        let mut prefix = vec![(1.0, 1.0)];
+        for &(_, price, volume) in &ratios {
+            let (total_price, total_volume) = prefix[prefix.len() - 1];
+            prefix.push((total_price * price, total_volume * volume));
+        }
+        let (total_price, total_volume) = prefix[prefix.len() - 1];
+
+        Ok(epochs
+            .iter()
+            .map(|&epoch| {
+                let applied = ratios.partition_point(|&(action_epoch, _, _)| action_epoch <= epoch);
+                let (price, volume) = prefix[applied];
+                match self.mode {
+                    AdjustMode::Backward => (price / total_price, total_volume / volume),
+                    _ => (price, 1.0 / volume),
+                }
+            })
+            .collect())
+    }
+}
+
+fn round_price(price: f64) -> f64 {
+    (price * PRICE_DECIMALS).round() / PRICE_DECIMALS
+}
+
+fn read_numbers(column_type: &str, bytes: &[u8], length: usize) -> Result<Vec<f64>> {
+    let width = column_type_width(column_type)?;
+    let bytes = bytes
+        .get(..length * width)
+        .ok_or_else(|| MarketStoreError::InvalidData(format!("Column of type {} is truncated", column_type)))?;
+    bytes
+        .chunks_exact(width)
+        .map(|chunk| match column_type {
+            "f4" => Ok(f32::from_le_bytes(chunk.try_into().unwrap()) as f64),
+            "f8" => Ok(f64::from_le_bytes(chunk.try_into().unwrap())),
+            "i4" => Ok(i32::from_le_bytes(chunk.try_into().unwrap()) as f64),
+            "i8" => Ok(i64::from_le_bytes(chunk.try_into().unwrap()) as f64),
+            "u4" => Ok(u32::from_le_bytes(chunk.try_into().unwrap()) as f64),
+            "u8" => Ok(u64::from_le_bytes(chunk.try_into().unwrap()) as f64),
+            other => Err(MarketStoreError::InvalidData(format!("Cannot adjust column of type {}", other))),
+        })
+        .collect()
+}
+
+/// 整数列四舍五入
+fn write_numbers(column_type: &str, values: &[f64]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(values.len() * column_type_width(column_type)?);
+    for &value in values {
+        match column_type {
+            "f4" => bytes.extend_from_slice(&(value as f32).to_le_bytes()),
+            "f8" => bytes.extend_from_slice(&value.to_le_bytes()),
+            "i4" => bytes.extend_from_slice(&(value.round() as i32).to_le_bytes()),
+            "i8" => bytes.extend_from_slice(&(value.round() as i64).to_le_bytes()),
+            "u4" => bytes.extend_from_slice(&(value.round() as u32).to_le_bytes()),
+            "u8" => bytes.extend_from_slice(&(value.round() as u64).to_le_bytes()),
+            other => return Err(MarketStoreError::InvalidData(format!("Cannot adjust column of type {}", other))),
+        }
+    }
+    Ok(bytes)
+}
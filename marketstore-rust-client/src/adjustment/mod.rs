@@ -0,0 +1,3 @@
+pub mod corporate_actions;
+
+pub use corporate_actions::*;
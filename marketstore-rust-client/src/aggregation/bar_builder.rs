@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{OHLCVData, WriteRequest},
+    utils::Timeframe,
+};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// 一笔成交
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub symbol: String,
+    pub price: f64,
+    pub size: f64,
+    pub epoch: i64,
+    /// 秒内纳秒，0 ~ 999_999_999
+    pub nanoseconds: i32,
+}
+
+impl Trade {
+    fn timestamp(&self) -> i128 {
+        self.epoch as i128 * NANOS_PER_SECOND as i128 + self.nanoseconds as i128
+    }
+}
+
+/// 已收盘的bar，`bar.epoch` 为bar的起始时间
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedBar {
+    pub symbol: String,
+    pub bar: OHLCVData,
+    /// 参与聚合的成交笔数
+    pub trades: usize,
+}
+
+#[derive(Debug)]
+struct OpenBar {
+    bar: OHLCVData,
+    first: i128,
+    last: i128,
+    trades: usize,
+}
+
+/// 将成交流聚合为固定周期的OHLCV bar，与服务器 `contrib/candler/tickcandler` 的聚合方式一致
+///
+/// bar按事件时间划分：水位为已见到的最大成交时间（或 `advance` 推进的时间），
+/// 水位到达 `bar结束 + 宽限期` 时bar收盘并输出。宽限期内迟到的成交仍计入对应bar，
+/// 之后到达的成交被丢弃并计入 `late_trades`。同一bar内的成交可以乱序，Open/Close按成交时间取首尾。
+#[derive(Debug)]
+pub struct BarBuilder {
+    timeframe: Timeframe,
+    attr_group: String,
+    grace: i128,
+    watermark: Option<i128>,
+    /// (bar起始时间, symbol) -> 未收盘的bar
+    open_bars: BTreeMap<(i64, String), OpenBar>,
+    late_trades: u64,
+}
+
+impl BarBuilder {
+    pub fn new(timeframe: Timeframe) -> Self {
+        Self {
+            timeframe,
+            attr_group: "OHLCV".to_string(),
+            grace: 0,
+            watermark: None,
+            open_bars: BTreeMap::new(),
+            late_trades: 0,
+        }
+    }
+
+    /// 收盘前等待迟到成交的时长，默认为0
+    pub fn with_grace_period(mut self, grace: Duration) -> Self {
+        self.grace = grace.as_nanos() as i128;
+        self
+    }
+
+    /// 写入时使用的AttributeGroup，默认为 `OHLCV`
+    pub fn with_attr_group(mut self, attr_group: &str) -> Self {
+        self.attr_group = attr_group.to_string();
+        self
+    }
+
+    pub fn timeframe(&self) -> &Timeframe {
+        &self.timeframe
+    }
+
+    /// 因超过宽限期而被丢弃的成交笔数
+    pub fn late_trades(&self) -> u64 {
+        self.late_trades
+    }
+
+    /// 未收盘的bar数量
+    pub fn open_bars(&self) -> usize {
+        self.open_bars.len()
+    }
+
+    /// 计入一笔成交，返回因水位推进而收盘的bar
+    pub fn push(&mut self, trade: &Trade) -> Result<Vec<ClosedBar>> {
+        if !(0..NANOS_PER_SECOND as i32).contains(&trade.nanoseconds) {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Invalid nanoseconds {} in trade of {}",
+                trade.nanoseconds, trade.symbol
+            )));
+        }
+
+        let period = self.period();
+        let start = trade.epoch.div_euclid(period) * period;
+        let timestamp = trade.timestamp();
+        if self.watermark.is_some_and(|watermark| close_time(start, period, self.grace) <= watermark) {
+            self.late_trades += 1;
+            tracing::debug!("Dropped late trade of {} at {}", trade.symbol, trade.epoch);
+            return Ok(Vec::new());
+        }
+
+        let price = trade.price as f32;
+        let open_bar = self.open_bars.entry((start, trade.symbol.clone())).or_insert_with(|| OpenBar {
+            bar: OHLCVData { epoch: start, open: price, high: price, low: price, close: price, volume: 0.0 },
+            first: timestamp,
+            last: timestamp,
+            trades: 0,
+        });
+        open_bar.bar.high = open_bar.bar.high.max(price);
+        open_bar.bar.low = open_bar.bar.low.min(price);
+        open_bar.bar.volume += trade.size as f32;
+        if timestamp < open_bar.first {
+            open_bar.first = timestamp;
+            open_bar.bar.open = price;
+        }
+        if timestamp >= open_bar.last {
+            open_bar.last = timestamp;
+            open_bar.bar.close = price;
+        }
+        open_bar.trades += 1;
+
+        Ok(self.advance_to(timestamp))
+    }
+
+    /// 将水位推进到 `epoch`，返回收盘的bar；用于没有成交时按时钟收盘
+    pub fn advance(&mut self, epoch: i64) -> Vec<ClosedBar> {
+        self.advance_to(epoch as i128 * NANOS_PER_SECOND as i128)
+    }
+
+    /// 输出所有未收盘的bar（含未完成的），用于停止时
+    pub fn flush(&mut self) -> Vec<ClosedBar> {
+        std::mem::take(&mut self.open_bars)
+            .into_iter()
+            .map(|((_, symbol), open_bar)| ClosedBar { symbol, bar: open_bar.bar, trades: open_bar.trades })
+            .collect()
+    }
+
+    /// 按symbol将收盘的bar组织为写入请求，key为 `symbol/周期/AttributeGroup`
+    pub fn write_requests(&self, bars: Vec<ClosedBar>) -> Vec<WriteRequest> {
+        let mut by_symbol: HashMap<String, Vec<OHLCVData>> = HashMap::new();
+        let mut order = Vec::new();
+        for closed in bars {
+            if !by_symbol.contains_key(&closed.symbol) {
+                order.push(closed.symbol.clone());
+            }
+            by_symbol.entry(closed.symbol).or_default().push(closed.bar);
+        }
+
+        order
+            .into_iter()
+            .map(|symbol| {
+                let data = by_symbol.remove(&symbol).unwrap_or_default();
+                WriteRequest::new(&symbol, &self.timeframe.name, &self.attr_group, data)
+            })
+            .collect()
+    }
+
+    fn advance_to(&mut self, timestamp: i128) -> Vec<ClosedBar> {
+        let watermark = self.watermark.map_or(timestamp, |watermark| watermark.max(timestamp));
+        self.watermark = Some(watermark);
+
+        let (period, grace) = (self.period(), self.grace);
+        let mut closed = Vec::new();
+        while let Some(entry) = self.open_bars.first_entry() {
+            if close_time(entry.key().0, period, grace) > watermark {
+                break;
+            }
+            let ((_, symbol), open_bar) = entry.remove_entry();
+            closed.push(ClosedBar { symbol, bar: open_bar.bar, trades: open_bar.trades });
+        }
+        closed
+    }
+
+    fn period(&self) -> i64 {
+        (self.timeframe.duration.as_secs() as i64).max(1)
+    }
+}
+
+/// 起始于 `start` 的bar的收盘水位
+fn close_time(start: i64, period: i64, grace: i128) -> i128 {
+    (start + period) as i128 * NANOS_PER_SECOND as i128 + grace
+}
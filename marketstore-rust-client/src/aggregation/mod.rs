@@ -0,0 +1,5 @@
+pub mod bar_builder;
+pub mod resample;
+
+pub use bar_builder::*;
+pub use resample::*;
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use crate::{
+    calendar::{local_midnight, TimeZoneRule},
+    error::{MarketStoreError, Result},
+    models::OHLCVData,
+    utils::{Timeframe, SECONDS_PER_DAY},
+};
+
+/// 每日交易时段，以当地时间距零点的时长表示；收盘早于开盘时表示跨越午夜的时段
+///
+/// 开收盘按 `timezone` 换算为UTC，夏令时切换前后的时段都对齐到当地的开盘时间。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub open: Duration,
+    pub close: Duration,
+    /// 交易所所在时区，如 `chrono_tz::America::New_York`
+    pub timezone: Tz,
+}
+
+impl Session {
+    pub fn new(open: Duration, close: Duration, timezone: Tz) -> Self {
+        Self { open, close, timezone }
+    }
+
+    /// 当地日期 `date` 开盘的时段 `[开盘, 收盘)`（UTC秒）
+    pub fn on(&self, date: NaiveDate) -> (i64, i64) {
+        let open = self.open.as_secs() as i64 % SECONDS_PER_DAY;
+        let mut length = (self.close.as_secs() as i64 - open).rem_euclid(SECONDS_PER_DAY);
+        if length == 0 {
+            length = SECONDS_PER_DAY;
+        }
+
+        let timezone = TimeZoneRule::Named(self.timezone);
+        let local_open = local_midnight(date) + open;
+        (timezone.local_to_utc(local_open), timezone.local_to_utc(local_open + length))
+    }
+
+    /// `epoch` 所在时段的 `[开盘, 收盘)`，不在任何时段内时返回None
+    pub fn bounds(&self, epoch: i64) -> Option<(i64, i64)> {
+        let date = self.local_date(epoch);
+        // 跨越午夜的时段可能在前一个当地日期开盘
+        [date.pred_opt(), Some(date)]
+            .into_iter()
+            .flatten()
+            .map(|date| self.on(date))
+            .find(|(open, close)| (*open..*close).contains(&epoch))
+    }
+
+    /// UTC时间 `epoch` 所在的当地日期
+    pub fn local_date(&self, epoch: i64) -> NaiveDate {
+        TimeZoneRule::Named(self.timezone).local_date(epoch)
+    }
+}
+
+/// 结尾不完整的bar的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartialBars {
+    /// 保留并标记为未完成
+    #[default]
+    Include,
+    /// 丢弃
+    Exclude,
+}
+
+/// 聚合后的K线，`bar.epoch` 为bar的起始时间
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub bar: OHLCVData,
+    /// 参与聚合的源bar数量
+    pub count: usize,
+    /// 源数据是否已覆盖到bar结束；只有最后一根可能未完成
+    pub complete: bool,
+}
+
+/// 客户端K线重采样：Open取第一根、High取最大、Low取最小、Close取最后一根、Volume求和
+///
+/// 无交易时段时bar按 `epoch - offset` 对目标周期取整对齐（与服务器的对齐方式一致）；
+/// 指定交易时段时bar从每个时段开盘起算，在收盘处截断，时段外的源bar被忽略。
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    timeframe: Timeframe,
+    offset: i64,
+    session: Option<Session>,
+    partial: PartialBars,
+}
+
+impl Resampler {
+    pub fn new(timeframe: Timeframe) -> Self {
+        Self {
+            timeframe,
+            offset: 0,
+            session: None,
+            partial: PartialBars::default(),
+        }
+    }
+
+    /// 对齐偏移，如1H周期的bar从每小时30分开始时为30分钟
+    pub fn with_offset(mut self, offset: Duration) -> Self {
+        self.offset = offset.as_secs() as i64;
+        self
+    }
+
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    pub fn with_partial_bars(mut self, partial: PartialBars) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    /// `epoch` 所属bar的 `[开始, 结束)`；有交易时段且不在时段内时返回None
+    pub fn bar_bounds(&self, epoch: i64) -> Option<(i64, i64)> {
+        let period = self.period();
+        match self.session {
+            Some(session) => {
+                let (open, close) = session.bounds(epoch)?;
+                let start = open + (epoch - open) / period * period;
+                Some((start, (start + period).min(close)))
+            }
+            None => {
+                let start = (epoch - self.offset).div_euclid(period) * period + self.offset;
+                Some((start, start + period))
+            }
+        }
+    }
+
+    /// 将 `source` 周期的bar聚合为目标周期，输入无需有序
+    ///
+    /// 目标周期须为源周期的整数倍。最后一根bar在源数据未覆盖到其结束时间时为未完成。
+    pub fn resample(&self, source: &Timeframe, bars: &[OHLCVData]) -> Result<Vec<Candle>> {
+        let source_period = source.duration.as_secs() as i64;
+        if source_period == 0 || self.period() % source_period != 0 {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Cannot resample {} into {}",
+                source.name, self.timeframe.name
+            )));
+        }
+
+        let mut sorted: Vec<&OHLCVData> = bars.iter().collect();
+        sorted.sort_by_key(|bar| bar.epoch);
+
+        let mut candles: BTreeMap<i64, (i64, Candle)> = BTreeMap::new();
+        for bar in sorted {
+            let Some((start, end)) = self.bar_bounds(bar.epoch) else {
+                continue;
+            };
+            candles
+                .entry(start)
+                .and_modify(|(_, candle)| {
+                    candle.bar.high = candle.bar.high.max(bar.high);
+                    candle.bar.low = candle.bar.low.min(bar.low);
+                    candle.bar.close = bar.close;
+                    candle.bar.volume += bar.volume;
+                    candle.count += 1;
+                })
+                .or_insert_with(|| {
+                    let candle = Candle {
+                        bar: OHLCVData { epoch: start, ..bar.clone() },
+                        count: 1,
+                        complete: true,
+                    };
+                    (end, candle)
+                });
+        }
+
+        let last_covered = bars.iter().map(|bar| bar.epoch + source_period).max();
+        let mut result: Vec<Candle> = candles.into_values().map(|(_, candle)| candle).collect();
+        if let (Some(last), Some(covered)) = (result.last_mut(), last_covered) {
+            let (_, end) = self.bar_bounds(last.bar.epoch).unwrap_or((last.bar.epoch, last.bar.epoch));
+            last.complete = covered >= end;
+            if !last.complete && self.partial == PartialBars::Exclude {
+                result.pop();
+            }
+        }
+        Ok(result)
+    }
+
+    fn period(&self) -> i64 {
+        (self.timeframe.duration.as_secs() as i64).max(1)
+    }
+}
@@ -1,8 +1,10 @@
 use marketstore_rust_client::{
-    MarketStoreClient, OHLCVData, StreamSubscription, SymbolFormat, DataShape, StreamPayload,
-    error::Result,
+    MarketStoreClient, OHLCVData, StreamSubscription, SymbolFormat, DataShape, StreamEvent,
+    client::{GrpcClient, Spool, SpoolConfig},
+    error::{MarketStoreError, Result},
+    storage::{Catalog, IntegrityChecker, WalFile, DEFAULT_CHECKSUM_CHUNKS},
 };
-use tokio::sync::oneshot;
+use futures::StreamExt;
 use tracing::{info, error, warn};
 use clap::{App, Arg, SubCommand};
 use std::time::{Duration, Instant};
@@ -170,9 +172,99 @@ async fn main() -> Result<()> {
                         .default_value("100")
                 )
         )
+        .subcommand(
+            SubCommand::with_name("wal")
+                .about("Inspect a WAL file offline")
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .short('f')
+                        .value_name("PATH")
+                        .required(true)
+                        .help("Path to the WAL file")
+                )
+                .arg(
+                    Arg::with_name("dump")
+                        .long("dump")
+                        .help("Dump transaction info records and rows per key")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("integrity")
+                .about("Check data files of a data directory offline and print a JSON report")
+                .arg(
+                    Arg::with_name("dir")
+                        .long("dir")
+                        .short('d')
+                        .value_name("PATH")
+                        .required(true)
+                        .help("Path to the data directory")
+                )
+                .arg(
+                    Arg::with_name("checksums")
+                        .long("checksums")
+                        .help("Compute chunk checksums per file")
+                )
+                .arg(
+                    Arg::with_name("chunks")
+                        .long("chunks")
+                        .value_name("N")
+                        .default_value("12")
+                        .help("Number of checksum chunks per file, excluding the header")
+                )
+                .arg(
+                    Arg::with_name("uncompressed")
+                        .long("uncompressed")
+                        .help("Variable length data was written with disable_variable_compression")
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("PATH")
+                        .help("Write the report to a file instead of stdout")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("spool")
+                .about("Inspect or drain a local write spool")
+                .arg(
+                    Arg::with_name("dir")
+                        .long("dir")
+                        .short('d')
+                        .value_name("PATH")
+                        .required(true)
+                        .help("Path to the spool directory")
+                )
+                .arg(
+                    Arg::with_name("dump")
+                        .long("dump")
+                        .help("Print every spooled write as a JSON line")
+                )
+                .arg(
+                    Arg::with_name("drain")
+                        .long("drain")
+                        .help("Replay spooled writes to the server at --grpc-url and remove them")
+                )
+        )
         .get_matches();
 
+    // 离线子命令不需要连接服务器
+    if let Some(("wal", args)) = matches.subcommand() {
+        return inspect_wal(args.value_of("file").unwrap(), args.is_present("dump"));
+    }
+    if let Some(("integrity", args)) = matches.subcommand() {
+        let chunks = args
+            .is_present("checksums")
+            .then(|| args.value_of("chunks").unwrap().parse().unwrap_or(DEFAULT_CHECKSUM_CHUNKS));
+        return check_integrity(args.value_of("dir").unwrap(), chunks, !args.is_present("uncompressed"), args.value_of("output"));
+    }
+
     let grpc_url = matches.value_of("grpc-url").unwrap().to_string();
+    if let Some(("spool", args)) = matches.subcommand() {
+        let drain_url = args.is_present("drain").then_some(grpc_url);
+        return inspect_spool(args.value_of("dir").unwrap(), args.is_present("dump"), drain_url).await;
+    }
     let websocket_url = matches.value_of("websocket-url").unwrap().to_string();
 
     info!("Connecting to MarketStore...");
@@ -391,30 +483,43 @@ async fn test_subscribe_realtime(client: &MarketStoreClient, streams: Vec<String
     
     let subscription = StreamSubscription::new().add_streams(streams);
     
-    let received_count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let received_count_clone = received_count.clone();
-    let handler = move |payload: StreamPayload| {
-        let count = received_count_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        info!("📡 Received #{}: {} = {:?}", count + 1, payload.key, payload.data);
-        Ok(())
-    };
-
-    let (tx, rx) = oneshot::channel();
-    
     let start = Instant::now();
-    let stream_handle = client.subscribe_realtime_with_cancel(subscription, handler, rx).await?;
-    
-    // 等待指定时间
-    tokio::time::sleep(Duration::from_secs(duration)).await;
-    
-    // 发送取消信号
-    let _ = tx.send(());
+    let mut stream = client.subscribe_stream(subscription, 1024).await?;
+    
+    // 在指定时间内接收数据
+    let mut final_count = 0;
+    let deadline = tokio::time::sleep(Duration::from_secs(duration));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            event = stream.next() => match event {
+                Some(Ok(StreamEvent::Payload(payload))) => {
+                    final_count += 1;
+                    info!("📡 Received #{}: {} = {:?}", final_count, payload.key, payload.data);
+                }
+                Some(Ok(StreamEvent::Lagged { dropped })) => {
+                    warn!("⚠️  Dropped {} messages (consumer lagging)", dropped);
+                }
+                Some(Ok(StreamEvent::ServerError { message })) => {
+                    warn!("⚠️  Stream server error: {}", message);
+                }
+                Some(Ok(StreamEvent::Undecodable { error, .. })) => {
+                    warn!("⚠️  Undecodable stream frame: {}", error);
+                }
+                Some(Err(e)) => {
+                    error!("Subscription error: {}", e);
+                    break;
+                }
+                None => break,
+            },
+            _ = &mut deadline => break,
+        }
+    }
     
-    // 等待流处理完成
-    let _ = stream_handle.await;
+    // 取消订阅并等待关闭握手完成
+    stream.close().await?;
     let total_duration = start.elapsed();
     
-    let final_count = received_count.load(std::sync::atomic::Ordering::SeqCst);
     info!("✅ Subscription completed (took {:?})", total_duration);
     info!("  Received {} messages", final_count);
     if duration > 0 {
@@ -539,4 +644,114 @@ async fn test_performance(client: &mut MarketStoreClient, iterations: usize) ->
           iterations, write_duration, iterations as f64 / write_duration.as_secs_f64());
     
     Ok(())
-} 
\ No newline at end of file
+} 
+
+fn inspect_wal(path: &str, dump: bool) -> Result<()> {
+    info!("Inspecting WAL file: {}", path);
+    let wal = WalFile::open(path)?;
+
+    info!(
+        "File status: {:?}, replay state: {:?}, owning PID: {}, needs replay: {}",
+        wal.header.file_status, wal.header.replay_state, wal.header.owning_instance_id, wal.header.needs_replay()
+    );
+
+    let groups: Vec<_> = wal.transaction_groups().collect();
+    info!(
+        "Transaction groups: {}, transaction info records: {}",
+        groups.len(), wal.transaction_infos().count()
+    );
+    for group in &groups {
+        let bytes: usize = group.write_sets.iter().map(|set| set.payload.len()).sum();
+        info!("  TG {}: {} write sets, {} bytes", group.transaction_group_id, group.write_sets.len(), bytes);
+    }
+
+    let pending: Vec<i64> = wal.pending_transaction_groups().iter().map(|group| group.transaction_group_id).collect();
+    info!("Pending transaction groups: {:?}", pending);
+
+    for issue in &wal.issues {
+        warn!("❌ Offset {}: {}", issue.offset, issue.message);
+    }
+
+    if dump {
+        for txn in wal.transaction_infos() {
+            info!("  TXNINFO TG {} {:?} {:?}", txn.transaction_group_id, txn.destination, txn.status);
+        }
+        for group in &groups {
+            match group.rows_by_key() {
+                Ok(rows) => {
+                    for (key, rows) in rows {
+                        info!("TG {} {} ({} rows)", group.transaction_group_id, key, rows.len());
+                        for row in rows {
+                            info!("    {}", serde_json::to_string(&row)?);
+                        }
+                    }
+                }
+                Err(e) => warn!("❌ Failed to decode rows of TG {}: {}", group.transaction_group_id, e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_integrity(dir: &str, checksum_chunks: Option<usize>, compressed: bool, output: Option<&str>) -> Result<()> {
+    let catalog = Catalog::open(dir)?.with_variable_compression(compressed);
+    let mut checker = IntegrityChecker::new();
+    if let Some(chunks) = checksum_chunks {
+        checker = checker.with_checksums(chunks);
+    }
+    let report = checker.check(&catalog);
+    let json = serde_json::to_string_pretty(&report)?;
+
+    // 未指定输出文件时stdout只输出报告，便于管道处理
+    match output {
+        Some(path) => {
+            std::fs::write(path, json)?;
+            info!("Checked {} data files under {}, report written to {}", report.files.len(), dir, path);
+        }
+        None => println!("{}", json),
+    }
+
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err(MarketStoreError::InvalidData(format!("{} integrity issues found", report.issue_count())))
+    }
+}
+
+async fn inspect_spool(dir: &str, dump: bool, drain_url: Option<String>) -> Result<()> {
+    let mut spool = Spool::open(SpoolConfig::new(dir))?;
+    let segments = spool.segments()?;
+    info!(
+        "Spool {}: {} segments, {} bytes, {} rows",
+        dir, segments.len(), spool.total_bytes(), segments.iter().map(|segment| segment.rows).sum::<usize>()
+    );
+    for segment in &segments {
+        info!("  {}: {} bytes, {} writes, {} rows", segment.path.display(), segment.bytes, segment.records, segment.rows);
+        if segment.torn_bytes > 0 {
+            warn!("❌ {} torn bytes at the end of {}", segment.torn_bytes, segment.path.display());
+        }
+    }
+
+    let dead_letters = spool.dead_letters()?;
+    if !dead_letters.is_empty() {
+        warn!("❌ {} writes rejected by the server in dead letters", dead_letters.len());
+    }
+    for path in spool.quarantined()? {
+        warn!("❌ Quarantined corrupt segment {}", path.display());
+    }
+
+    if dump {
+        for write in spool.read_all()? {
+            println!("{}", serde_json::to_string(&write)?);
+        }
+    }
+
+    if let Some(url) = drain_url {
+        info!("Draining spool to {}", url);
+        let mut client = GrpcClient::connect(url).await?;
+        let rows = spool.drain(&mut client).await?;
+        info!("✅ Replayed {} rows", rows);
+    }
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use marketstore_rust_client::{
-    MarketStoreClient, StreamSubscription, StreamPayload,
+    MarketStoreClient, StreamSubscription, StreamPayload, StreamEvent,
     error::Result,
 };
-use tokio::sync::oneshot;
+use futures::StreamExt;
 use tracing::info;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -35,7 +35,7 @@ async fn main() -> Result<()> {
     let received_count = Arc::new(AtomicUsize::new(0));
     let received_count_clone = received_count.clone();
     
-    let handler = move |payload: StreamPayload| {
+    let handler = move |payload: StreamPayload| -> Result<()> {
         let count = received_count_clone.fetch_add(1, Ordering::SeqCst);
         info!("📡 Received message #{}:", count + 1);
         info!("  Key: {}", payload.key);
@@ -64,19 +64,37 @@ async fn main() -> Result<()> {
         Ok(())
     };
 
-    let (tx, rx) = oneshot::channel();
-    
     info!("Starting WebSocket subscription...");
-    let stream_handle = client.subscribe_realtime_with_cancel(subscription, handler, rx).await?;
-    
-    // 等待10秒接收消息
-    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+    let mut stream = client.subscribe_stream(subscription, 1024).await?;
     
-    // 发送取消信号
-    let _ = tx.send(());
+    // 在10秒内接收消息
+    let deadline = tokio::time::sleep(tokio::time::Duration::from_secs(10));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            event = stream.next() => match event {
+                Some(Ok(StreamEvent::Payload(payload))) => handler(payload)?,
+                Some(Ok(StreamEvent::Lagged { dropped })) => {
+                    info!("⚠️  Dropped {} messages", dropped);
+                }
+                Some(Ok(StreamEvent::ServerError { message })) => {
+                    info!("⚠️  Stream server error: {}", message);
+                }
+                Some(Ok(StreamEvent::Undecodable { error, .. })) => {
+                    info!("⚠️  Undecodable stream frame: {}", error);
+                }
+                Some(Err(e)) => {
+                    info!("❌ Subscription error: {}", e);
+                    break;
+                }
+                None => break,
+            },
+            _ = &mut deadline => break,
+        }
+    }
     
-    // 等待流处理完成
-    let _ = stream_handle.await;
+    // 取消订阅并等待关闭握手完成
+    stream.close().await?;
     
     let final_count = received_count.load(Ordering::SeqCst);
     info!("✅ WebSocket test completed");
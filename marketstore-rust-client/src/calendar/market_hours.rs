@@ -0,0 +1,29 @@
+use crate::aggregation::Session;
+
+/// 判断某根bar是否处于开市时间，闭市时间的bar不算缺失；由 `Session` 与 `TradingCalendar` 实现
+pub trait MarketHours: Send + Sync {
+    fn is_open(&self, epoch: i64) -> bool;
+
+    /// `[start, end)` 是否与某个交易时段重叠，用于判断整根bar而不只是其起始时间
+    fn is_open_between(&self, start: i64, end: i64) -> bool;
+}
+
+impl MarketHours for Session {
+    fn is_open(&self, epoch: i64) -> bool {
+        self.bounds(epoch).is_some()
+    }
+
+    fn is_open_between(&self, start: i64, end: i64) -> bool {
+        // 跨越午夜的时段可能在前一个当地日期开盘
+        let mut date = self.local_date(start).pred_opt();
+        let last = self.local_date(end - 1);
+        while let Some(current) = date.filter(|date| *date <= last) {
+            let (open, close) = self.on(current);
+            if open < end && start < close {
+                return true;
+            }
+            date = current.succ_opt();
+        }
+        false
+    }
+}
@@ -0,0 +1,5 @@
+pub mod market_hours;
+pub mod trading_calendar;
+
+pub use market_hours::*;
+pub use trading_calendar::*;
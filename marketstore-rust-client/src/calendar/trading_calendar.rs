@@ -0,0 +1,344 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use chrono::{DateTime, Datelike, NaiveDate, Offset, TimeZone};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use crate::{
+    calendar::MarketHours,
+    error::{MarketStoreError, Result},
+    models::{NumpyDataset, OHLCVData},
+    utils::{column_type_width, Timeframe, SECONDS_PER_DAY},
+};
+
+/// 内置的NASDAQ日历，与服务器 `contrib/calendar/nasdaq.go` 相同
+const NASDAQ_JSON: &str = include_str!("nasdaq.json");
+
+const SECONDS_PER_HOUR: i64 = 60 * 60;
+
+/// 日历JSON定义，字段与服务器 `contrib/calendar` 一致
+///
+/// `trading_weekdays` 为扩展字段，ISO星期（1为周一，7为周日），缺省为周一至周五。
+#[derive(Debug, Deserialize)]
+struct CalendarDefinition {
+    timezone: String,
+    open_time: String,
+    close_time: String,
+    #[serde(default)]
+    early_close_time: Option<String>,
+    #[serde(default)]
+    non_trading_days: Vec<String>,
+    #[serde(default)]
+    early_closes: Vec<String>,
+    #[serde(default)]
+    trading_weekdays: Option<Vec<u32>>,
+}
+
+/// 日历使用的时区规则：IANA时区名（如 `America/New_York`，夏令时由 `chrono-tz` 时区数据库处理）
+/// 或固定偏移（如 `+08:00`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TimeZoneRule {
+    Fixed(i64),
+    Named(Tz),
+}
+
+impl TimeZoneRule {
+    fn parse(name: &str) -> Result<Self> {
+        if let Some(offset) = parse_utc_offset(name) {
+            return Ok(Self::Fixed(offset));
+        }
+        name.parse::<Tz>()
+            .map(Self::Named)
+            .map_err(|_| MarketStoreError::InvalidData(format!("Unsupported calendar timezone: {}", name)))
+    }
+
+    /// UTC时间 `epoch` 处相对UTC的偏移秒数
+    pub(crate) fn offset_at(&self, epoch: i64) -> i64 {
+        match *self {
+            Self::Fixed(offset) => offset,
+            Self::Named(tz) => DateTime::from_timestamp(epoch, 0).map_or(0, |time| {
+                tz.offset_from_utc_datetime(&time.naive_utc()).fix().local_minus_utc() as i64
+            }),
+        }
+    }
+
+    /// 当地时间（以距1970-01-01当地零点的秒数表示）对应的UTC时间
+    pub(crate) fn local_to_utc(&self, local: i64) -> i64 {
+        let guess = local - self.offset_at(local);
+        local - self.offset_at(guess)
+    }
+
+    /// UTC时间 `epoch` 所在的当地日期
+    pub(crate) fn local_date(&self, epoch: i64) -> NaiveDate {
+        let local = epoch + self.offset_at(epoch);
+        DateTime::from_timestamp(local, 0).map_or(NaiveDate::MIN, |time| time.date_naive())
+    }
+}
+
+/// 交易日历：交易日、开收盘时间、休市日与提前收盘日
+///
+/// 与服务器 `contrib/calendar` 的JSON格式兼容，内置NASDAQ、NYSE（与NASDAQ的休市安排相同）
+/// 与全天候交易的24x7日历。时段为 `[开盘, 收盘)`。
+#[derive(Debug, Clone)]
+pub struct TradingCalendar {
+    name: String,
+    timezone: TimeZoneRule,
+    /// 距当地零点的秒数
+    open: i64,
+    close: i64,
+    early_close: i64,
+    non_trading_days: BTreeSet<NaiveDate>,
+    early_closes: BTreeSet<NaiveDate>,
+    /// 下标为 `Weekday::num_days_from_monday`
+    weekdays: [bool; 7],
+}
+
+impl TradingCalendar {
+    pub fn from_json(name: &str, json: &str) -> Result<Self> {
+        let definition: CalendarDefinition = serde_json::from_str(json)?;
+        let mut weekdays = [false; 7];
+        for day in definition.trading_weekdays.unwrap_or_else(|| vec![1, 2, 3, 4, 5]) {
+            *weekdays.get_mut((day as usize).wrapping_sub(1)).ok_or_else(|| {
+                MarketStoreError::InvalidData(format!("Invalid weekday {} in calendar {}", day, name))
+            })? = true;
+        }
+        let close = parse_time(&definition.close_time)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            timezone: TimeZoneRule::parse(&definition.timezone)?,
+            open: parse_time(&definition.open_time)?,
+            close,
+            early_close: definition.early_close_time.as_deref().map(parse_time).transpose()?.unwrap_or(close),
+            non_trading_days: parse_dates(&definition.non_trading_days)?,
+            early_closes: parse_dates(&definition.early_closes)?,
+            weekdays,
+        })
+    }
+
+    /// 从JSON文件加载，文件名（不含扩展名）作为日历名
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        Self::from_json(&name, &std::fs::read_to_string(path)?)
+    }
+
+    pub fn nasdaq() -> Self {
+        Self::from_json("NASDAQ", NASDAQ_JSON).expect("built-in NASDAQ calendar is valid")
+    }
+
+    pub fn nyse() -> Self {
+        Self { name: "NYSE".to_string(), ..Self::nasdaq() }
+    }
+
+    /// 全天候交易，用于加密货币
+    pub fn always_open() -> Self {
+        Self {
+            name: "24x7".to_string(),
+            timezone: TimeZoneRule::Fixed(0),
+            open: 0,
+            close: SECONDS_PER_DAY,
+            early_close: SECONDS_PER_DAY,
+            non_trading_days: BTreeSet::new(),
+            early_closes: BTreeSet::new(),
+            weekdays: [true; 7],
+        }
+    }
+
+    /// 按名称取内置日历：`NASDAQ`、`NYSE`、`24x7`（或 `crypto`），不区分大小写
+    pub fn builtin(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "nasdaq" => Ok(Self::nasdaq()),
+            "nyse" => Ok(Self::nyse()),
+            "24x7" | "crypto" => Ok(Self::always_open()),
+            _ => Err(MarketStoreError::InvalidData(format!("Unknown calendar: {}", name))),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.weekdays[date.weekday().num_days_from_monday() as usize] && !self.non_trading_days.contains(&date)
+    }
+
+    /// 当地日期 `date` 的交易时段 `[开盘, 收盘)`（UTC秒），非交易日返回None
+    pub fn session(&self, date: NaiveDate) -> Option<(i64, i64)> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        let close = if self.early_closes.contains(&date) { self.early_close } else { self.close };
+        let midnight = local_midnight(date);
+        Some((self.timezone.local_to_utc(midnight + self.open), self.timezone.local_to_utc(midnight + close)))
+    }
+
+    /// UTC时间 `epoch` 所在的当地日期
+    pub fn local_date(&self, epoch: i64) -> NaiveDate {
+        self.timezone.local_date(epoch)
+    }
+
+    /// `epoch` 时刻是否处于交易时段；判断整根bar应使用 `is_open_between`
+    pub fn is_open(&self, epoch: i64) -> bool {
+        self.session(self.local_date(epoch)).is_some_and(|(open, close)| (open..close).contains(&epoch))
+    }
+
+    /// `[start, end)` 是否与某个交易时段重叠
+    ///
+    /// 一天及以上周期的bar以UTC零点为起点、以日期标识交易日，按覆盖的日期中是否有交易日判断，
+    /// 不比较具体时刻。
+    pub fn is_open_between(&self, start: i64, end: i64) -> bool {
+        let (mut date, last, daily) = if end - start >= SECONDS_PER_DAY {
+            (utc_date(start), utc_date(end - 1), true)
+        } else {
+            (self.local_date(start), self.local_date(end - 1), false)
+        };
+        while date <= last {
+            if daily {
+                if self.is_trading_day(date) {
+                    return true;
+                }
+            } else if self.session(date).is_some_and(|(open, close)| open < end && start < close) {
+                return true;
+            }
+            let Some(next) = date.succ_opt() else {
+                break;
+            };
+            date = next;
+        }
+        false
+    }
+
+    /// 截至 `now` 的最近 `days` 个交易日的时间范围 `[首日开盘, min(now, 末日收盘 - 1)]`
+    ///
+    /// 当日尚未开盘时不计入当日。`days` 为0或在日历范围内找不到足够的交易日时返回None。
+    pub fn last_trading_days(&self, days: usize, now: i64) -> Option<(i64, i64)> {
+        let mut date = self.local_date(now);
+        let mut sessions = Vec::with_capacity(days);
+        // 最多向前查找 days * 7 + 14 天，足以跨过任意长度的节假日
+        for _ in 0..days * 7 + 14 {
+            if sessions.len() == days {
+                break;
+            }
+            if let Some((open, close)) = self.session(date) {
+                if open <= now {
+                    sessions.push((open, close));
+                }
+            }
+            date = date.pred_opt()?;
+        }
+
+        let (first, _) = *sessions.last().filter(|_| sessions.len() == days && days > 0)?;
+        let (_, latest_close) = sessions[0];
+        Some((first, now.min(latest_close - 1)))
+    }
+
+    /// 只保留与交易时段重叠的bar，`timeframe` 为bar的周期
+    pub fn filter_bars(&self, bars: &[OHLCVData], timeframe: &Timeframe) -> Vec<OHLCVData> {
+        let period = timeframe_period(timeframe);
+        bars.iter().filter(|bar| self.is_open_between(bar.epoch, bar.epoch + period)).cloned().collect()
+    }
+
+    /// 只保留查询结果中与交易时段重叠的行，`timeframe` 为查询key的周期
+    pub fn filter_dataset(&self, dataset: &NumpyDataset, timeframe: &Timeframe) -> Result<NumpyDataset> {
+        let period = timeframe_period(timeframe);
+        let epoch_index = dataset
+            .column_names
+            .iter()
+            .position(|name| name == "Epoch")
+            .ok_or_else(|| MarketStoreError::InvalidData("Missing column Epoch".to_string()))?;
+        let epochs = dataset
+            .column_data
+            .get(epoch_index)
+            .ok_or_else(|| MarketStoreError::InvalidData("Missing data for column Epoch".to_string()))?;
+        let length = dataset.length.max(0) as usize;
+        if epochs.len() < length * 8 {
+            return Err(MarketStoreError::InvalidData("Epoch column is truncated".to_string()));
+        }
+        let rows: Vec<usize> = (0..length)
+            .filter(|&row| {
+                let epoch = i64::from_le_bytes(epochs[row * 8..row * 8 + 8].try_into().unwrap());
+                self.is_open_between(epoch, epoch + period)
+            })
+            .collect();
+
+        let column_data = dataset
+            .column_types
+            .iter()
+            .zip(&dataset.column_data)
+            .map(|(column_type, data)| {
+                let width = column_type_width(column_type)?;
+                if data.len() < length * width {
+                    return Err(MarketStoreError::InvalidData(format!("Column of type {} is truncated", column_type)));
+                }
+                Ok(rows.iter().flat_map(|&row| data[row * width..(row + 1) * width].iter().copied()).collect())
+            })
+            .collect::<Result<Vec<Vec<u8>>>>()?;
+
+        Ok(NumpyDataset {
+            column_types: dataset.column_types.clone(),
+            column_names: dataset.column_names.clone(),
+            column_data,
+            length: rows.len() as i32,
+        })
+    }
+}
+
+impl MarketHours for TradingCalendar {
+    fn is_open(&self, epoch: i64) -> bool {
+        TradingCalendar::is_open(self, epoch)
+    }
+
+    fn is_open_between(&self, start: i64, end: i64) -> bool {
+        TradingCalendar::is_open_between(self, start, end)
+    }
+}
+
+/// `HH:MM:SS` 转为距零点的秒数，允许 `24:00:00`
+fn parse_time(time: &str) -> Result<i64> {
+    let parts: Vec<i64> = time.split(':').map(|part| part.parse().ok()).collect::<Option<_>>().unwrap_or_default();
+    match parts.as_slice() {
+        [hour, minute, second] if (0..60).contains(minute) && (0..60).contains(second) => {
+            let seconds = hour * SECONDS_PER_HOUR + minute * 60 + second;
+            if (0..=SECONDS_PER_DAY).contains(&seconds) {
+                return Ok(seconds);
+            }
+        }
+        _ => {}
+    }
+    Err(MarketStoreError::InvalidData(format!("Invalid calendar time: {}", time)))
+}
+
+fn parse_dates(dates: &[String]) -> Result<BTreeSet<NaiveDate>> {
+    dates
+        .iter()
+        .map(|date| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| MarketStoreError::InvalidData(format!("Invalid calendar date: {}", date)))
+        })
+        .collect()
+}
+
+/// `+08:00` / `-05:00` 形式的固定偏移
+fn parse_utc_offset(name: &str) -> Option<i64> {
+    let sign = match name.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let (hour, minute) = name[1..].split_once(':')?;
+    Some(sign * (hour.parse::<i64>().ok()? * SECONDS_PER_HOUR + minute.parse::<i64>().ok()? * 60))
+}
+
+fn timeframe_period(timeframe: &Timeframe) -> i64 {
+    (timeframe.duration.as_secs() as i64).max(1)
+}
+
+/// UTC时间 `epoch` 所在的UTC日期
+fn utc_date(epoch: i64) -> NaiveDate {
+    DateTime::from_timestamp(epoch, 0).map_or(NaiveDate::MIN, |time| time.date_naive())
+}
+
+/// `date` 当地零点距1970-01-01当地零点的秒数
+pub(crate) fn local_midnight(date: NaiveDate) -> i64 {
+    date.signed_duration_since(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() * SECONDS_PER_DAY
+}
@@ -0,0 +1,309 @@
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::{
+    calendar::MarketHours,
+    error::{MarketStoreError, Result},
+    models::{NumpyDataset, OHLCVData, QueryRequest},
+    client::GrpcClientTrait,
+    utils::{split_bucket_key, write_atomic, Timeframe},
+};
+
+/// 每页查询的默认行数
+pub const DEFAULT_BACKFILL_PAGE_SIZE: i32 = 10_000;
+
+/// 一段连续缺失的bar，`start` 与 `end` 均为缺失bar的起始时间（含两端）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gap {
+    pub start: i64,
+    pub end: i64,
+    /// 区间内应有（开市时段内）的bar数量
+    pub missing: i64,
+}
+
+/// 外部历史数据源，按bucket key与时间范围（含两端）返回bar
+#[async_trait]
+pub trait HistoricalProvider: Send + Sync {
+    async fn fetch(&self, key: &str, start: i64, end: i64) -> Result<Vec<OHLCVData>>;
+}
+
+/// 已补齐的缺口
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilledGap {
+    pub gap: Gap,
+    /// 数据源返回并写入的行数，为0表示数据源也没有数据
+    pub rows: usize,
+}
+
+/// 补数进度，每补齐一个缺口后保存，中断后从未完成的缺口继续
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackfillState {
+    pub key: String,
+    pub start: i64,
+    pub end: i64,
+    pub pending: Vec<Gap>,
+    pub filled: Vec<FilledGap>,
+}
+
+/// 一次补数（或dry-run）的结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackfillReport {
+    pub key: String,
+    pub start: i64,
+    pub end: i64,
+    pub dry_run: bool,
+    /// 是否从进度文件恢复，未重新扫描
+    pub resumed: bool,
+    pub gaps: Vec<Gap>,
+    pub missing_bars: i64,
+    pub filled: Vec<FilledGap>,
+}
+
+/// 扫描bucket中的缺口并从 `HistoricalProvider` 补齐
+///
+/// 扫描按Epoch分页查询 `[start, end]`，期望的bar为按周期对齐的时间点，设置 `MarketHours` 时只检查与开市时间重叠的bar。
+/// 设置进度文件时，扫描结果与每个缺口的完成情况写入该文件，中断后以相同key与范围再次运行会跳过扫描
+/// 与已完成的缺口；全部完成后删除进度文件。
+pub struct Backfill<C, P> {
+    client: C,
+    provider: P,
+    page_size: i32,
+    market_hours: Option<Box<dyn MarketHours>>,
+    state_path: Option<PathBuf>,
+    dry_run: bool,
+}
+
+impl<C, P> Backfill<C, P>
+where
+    C: GrpcClientTrait + Send,
+    P: HistoricalProvider,
+{
+    pub fn new(client: C, provider: P) -> Self {
+        Self {
+            client,
+            provider,
+            page_size: DEFAULT_BACKFILL_PAGE_SIZE,
+            market_hours: None,
+            state_path: None,
+            dry_run: false,
+        }
+    }
+
+    pub fn with_page_size(mut self, page_size: i32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn with_market_hours<M: MarketHours + 'static>(mut self, market_hours: M) -> Self {
+        self.market_hours = Some(Box::new(market_hours));
+        self
+    }
+
+    pub fn with_state_file<T: AsRef<Path>>(mut self, path: T) -> Self {
+        self.state_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// 只扫描并报告缺口，不调用数据源也不写入
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// 扫描key在 `[start, end]` 内的缺口
+    pub async fn scan(&mut self, key: &str, start: i64, end: i64) -> Result<Vec<Gap>> {
+        let period = key_period(key)?;
+        let mut collector = GapCollector::new(period, start, self.market_hours.as_deref());
+
+        let mut cursor = start;
+        while cursor <= end {
+            let request = QueryRequest {
+                destination: key.to_string(),
+                epoch_start: Some(cursor),
+                epoch_end: Some(end),
+                limit_record_count: Some(self.page_size),
+                limit_from_start: true,
+                columns: Vec::new(),
+            };
+            let epochs = match self.client.query(request).await?.data {
+                Some(dataset) => epoch_column(&dataset)?,
+                None => Vec::new(),
+            };
+
+            for &epoch in &epochs {
+                collector.present(epoch);
+            }
+            match epochs.last() {
+                Some(&last) if epochs.len() >= self.page_size as usize => cursor = last + 1,
+                _ => break,
+            }
+        }
+
+        let gaps = collector.finish(end);
+        tracing::debug!("Found {} gaps in {} between {} and {}", gaps.len(), key, start, end);
+        Ok(gaps)
+    }
+
+    /// 扫描并补齐缺口；dry-run时只返回扫描结果
+    pub async fn run(&mut self, key: &str, start: i64, end: i64) -> Result<BackfillReport> {
+        let (mut state, resumed) = match self.load_state(key, start, end)? {
+            Some(state) => (state, true),
+            None => {
+                let gaps = self.scan(key, start, end).await?;
+                let state = BackfillState { key: key.to_string(), start, end, pending: gaps, filled: Vec::new() };
+                (state, false)
+            }
+        };
+
+        let gaps: Vec<Gap> = state.filled.iter().map(|filled| filled.gap).chain(state.pending.iter().copied()).collect();
+        let missing_bars = gaps.iter().map(|gap| gap.missing).sum();
+        if self.dry_run {
+            return Ok(BackfillReport {
+                key: key.to_string(),
+                start,
+                end,
+                dry_run: true,
+                resumed,
+                gaps,
+                missing_bars,
+                filled: state.filled,
+            });
+        }
+
+        let (symbol, timeframe, attr_group) = split_bucket_key(key)?;
+        self.save_state(&state)?;
+        while let Some(&gap) = state.pending.first() {
+            let mut rows = self.provider.fetch(key, gap.start, gap.end).await?;
+            rows.retain(|row| row.epoch >= gap.start && row.epoch <= gap.end);
+            rows.sort_by_key(|row| row.epoch);
+
+            let count = rows.len();
+            if count > 0 {
+                self.client.write(symbol, timeframe, attr_group, rows).await?;
+            } else {
+                tracing::warn!("Provider returned no data for {} between {} and {}", key, gap.start, gap.end);
+            }
+            state.pending.remove(0);
+            state.filled.push(FilledGap { gap, rows: count });
+            self.save_state(&state)?;
+        }
+
+        if let Some(path) = &self.state_path {
+            std::fs::remove_file(path)?;
+        }
+        Ok(BackfillReport {
+            key: key.to_string(),
+            start,
+            end,
+            dry_run: false,
+            resumed,
+            gaps,
+            missing_bars,
+            filled: state.filled,
+        })
+    }
+
+    /// 读取与本次key和范围相同的进度
+    fn load_state(&self, key: &str, start: i64, end: i64) -> Result<Option<BackfillState>> {
+        let Some(path) = &self.state_path else {
+            return Ok(None);
+        };
+        let state: BackfillState = match std::fs::read(path) {
+            Ok(buf) => serde_json::from_slice(&buf)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if state.key != key || state.start != start || state.end != end {
+            tracing::info!("Ignoring backfill state of {} for a different range", state.key);
+            return Ok(None);
+        }
+        tracing::info!("Resuming backfill of {} with {} pending gaps", key, state.pending.len());
+        Ok(Some(state))
+    }
+
+    /// 先写临时文件再改名，中断时不会留下不完整的进度
+    fn save_state(&self, state: &BackfillState) -> Result<()> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        write_atomic(path, &serde_json::to_vec_pretty(state)?)
+    }
+}
+
+/// 按期望的bar时间点依次比对已有数据，合并连续缺失的bar
+struct GapCollector<'a> {
+    period: i64,
+    market_hours: Option<&'a dyn MarketHours>,
+    /// 下一个待检查的bar时间点
+    next: i64,
+    current: Option<Gap>,
+    gaps: Vec<Gap>,
+}
+
+impl<'a> GapCollector<'a> {
+    fn new(period: i64, start: i64, market_hours: Option<&'a dyn MarketHours>) -> Self {
+        Self {
+            period,
+            market_hours,
+            next: start.div_euclid(period) * period + if start.rem_euclid(period) == 0 { 0 } else { period },
+            current: None,
+            gaps: Vec::new(),
+        }
+    }
+
+    fn present(&mut self, epoch: i64) {
+        let bar = epoch.div_euclid(self.period) * self.period;
+        self.missing_until(bar - 1);
+        if let Some(gap) = self.current.take() {
+            self.gaps.push(gap);
+        }
+        self.next = self.next.max(bar + self.period);
+    }
+
+    fn finish(mut self, end: i64) -> Vec<Gap> {
+        self.missing_until(end);
+        self.gaps.extend(self.current.take());
+        self.gaps
+    }
+
+    /// 将 `next` 到 `until`（含）之间与开市时间重叠的bar记为缺失；闭市时间不打断当前缺口
+    fn missing_until(&mut self, until: i64) {
+        while self.next <= until {
+            let bar = self.next;
+            self.next += self.period;
+            if self.market_hours.is_some_and(|hours| !hours.is_open_between(bar, bar + self.period)) {
+                continue;
+            }
+            match &mut self.current {
+                Some(gap) => {
+                    gap.end = bar;
+                    gap.missing += 1;
+                }
+                None => self.current = Some(Gap { start: bar, end: bar, missing: 1 }),
+            }
+        }
+    }
+}
+
+fn key_period(key: &str) -> Result<i64> {
+    let (_, timeframe, _) = split_bucket_key(key)?;
+    Ok((Timeframe::parse(timeframe)?.duration.as_secs() as i64).max(1))
+}
+
+/// 取出数据集的Epoch列
+fn epoch_column(dataset: &NumpyDataset) -> Result<Vec<i64>> {
+    let index = dataset
+        .column_names
+        .iter()
+        .position(|name| name == "Epoch")
+        .ok_or_else(|| MarketStoreError::InvalidData("Missing column Epoch".to_string()))?;
+    let bytes = dataset
+        .column_data
+        .get(index)
+        .ok_or_else(|| MarketStoreError::InvalidData("Missing data for column Epoch".to_string()))?;
+    Ok(bytes
+        .chunks_exact(8)
+        .take(dataset.length.max(0) as usize)
+        .map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{OHLCVData, WriteRequest},
+    client::{routed_client::is_node_failure, GrpcClient, GrpcClientTrait},
+    utils::split_bucket_key,
+};
+
+/// 批量写入配置
+#[derive(Debug, Clone)]
+pub struct BufferedWriterConfig {
+    /// 缓冲行数达到该值时立即刷新
+    pub max_batch_rows: usize,
+    /// 距上次刷新超过该时长时刷新缓冲中的数据
+    pub flush_interval: Duration,
+    /// 已提交但尚未写入的行数上限，超过时 `write` 直接返回错误
+    pub max_buffered_rows: usize,
+    /// 节点不可用导致刷新失败后，首次重试前的等待时间，之后每次失败翻倍
+    pub retry_backoff: Duration,
+    pub max_retry_backoff: Duration,
+}
+
+impl Default for BufferedWriterConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_rows: 10_000,
+            flush_interval: Duration::from_secs(1),
+            max_buffered_rows: 1_000_000,
+            retry_backoff: Duration::from_millis(100),
+            max_retry_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// 批量写入的运行指标快照
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriterMetrics {
+    /// 已提交但尚未成功写入的行数
+    pub queued_rows: usize,
+    /// 缓冲中的key数量
+    pub queued_keys: usize,
+    /// 成功的刷新次数
+    pub flushes: u64,
+    pub failed_flushes: u64,
+    /// 已成功写入的行数
+    pub rows_written: u64,
+    /// 被服务器拒绝（非节点故障）而丢弃的行数
+    pub dropped_rows: u64,
+    pub last_flush_latency: Option<Duration>,
+    pub max_flush_latency: Option<Duration>,
+}
+
+enum WriterCommand {
+    Write { key: String, rows: Vec<OHLCVData> },
+    Flush(oneshot::Sender<Result<()>>),
+    Close(oneshot::Sender<Result<()>>),
+}
+
+/// 合并多次写入、按key聚合后批量发送的后台写入器
+///
+/// 缓冲行数达到 `max_batch_rows` 或距上次刷新超过 `flush_interval` 时刷新；每次刷新前
+/// 各key的行按Epoch稳定排序，同一key的行总是按提交顺序进入同一批或更早的批。
+/// 每次刷新将全部key合并为一个批量写请求：节点不可用时数据留在缓冲中，按退避间隔重试；
+/// 服务器返回的错误不带key，批量请求被拒绝时逐个key重发，被拒绝的key丢弃并计入
+/// `dropped_rows`，不会阻塞其它key。writer被drop时后台任务会尝试最后一次刷新。
+pub struct BufferedWriter {
+    commands: mpsc::UnboundedSender<WriterCommand>,
+    metrics: Arc<StdMutex<WriterMetrics>>,
+    max_buffered_rows: usize,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl BufferedWriter {
+    pub fn new(client: GrpcClient, config: BufferedWriterConfig) -> Self {
+        Self::with_client(client, config)
+    }
+
+    /// 使用任意gRPC客户端实现，如 `RoutedClient`
+    pub fn with_client<C>(client: C, config: BufferedWriterConfig) -> Self
+    where
+        C: GrpcClientTrait + Send + 'static,
+    {
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let metrics = Arc::new(StdMutex::new(WriterMetrics::default()));
+        let max_buffered_rows = config.max_buffered_rows;
+        let task = tokio::spawn(run_writer(client, config, command_rx, metrics.clone()));
+        Self {
+            commands: command_tx,
+            metrics,
+            max_buffered_rows,
+            task: Some(task),
+        }
+    }
+
+    /// 提交一个key的行，key形如 `AAPL/1Min/OHLCV`；缓冲超过 `max_buffered_rows` 时返回错误
+    pub fn write(&self, key: &str, rows: Vec<OHLCVData>) -> Result<()> {
+        split_bucket_key(key)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        // 先计入队列深度，避免后台任务在计数前就已写出这些行
+        let count = rows.len();
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+            if metrics.queued_rows + count > self.max_buffered_rows {
+                return Err(MarketStoreError::InvalidData(format!(
+                    "Buffered writer is full: {} of {} rows queued",
+                    metrics.queued_rows, self.max_buffered_rows
+                )));
+            }
+            metrics.queued_rows += count;
+        }
+        self.commands.send(WriterCommand::Write { key: key.to_string(), rows }).map_err(|_| {
+            self.metrics.lock().unwrap().queued_rows -= count;
+            MarketStoreError::Connection("Buffered writer is closed".to_string())
+        })
+    }
+
+    /// 立即刷新此前提交的全部行，返回本次写入的结果
+    pub async fn flush(&self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(WriterCommand::Flush(tx))
+            .map_err(|_| MarketStoreError::Connection("Buffered writer is closed".to_string()))?;
+        rx.await
+            .map_err(|_| MarketStoreError::Connection("Buffered writer task stopped".to_string()))?
+    }
+
+    /// 刷新剩余数据并停止后台任务
+    pub async fn close(mut self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(WriterCommand::Close(tx))
+            .map_err(|_| MarketStoreError::Connection("Buffered writer is closed".to_string()))?;
+        let result = rx
+            .await
+            .map_err(|_| MarketStoreError::Connection("Buffered writer task stopped".to_string()))?;
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        result
+    }
+
+    pub fn metrics(&self) -> WriterMetrics {
+        self.metrics.lock().unwrap().clone()
+    }
+}
+
+async fn run_writer<C: GrpcClientTrait + Send>(
+    mut client: C,
+    config: BufferedWriterConfig,
+    mut commands: mpsc::UnboundedReceiver<WriterCommand>,
+    metrics: Arc<StdMutex<WriterMetrics>>,
+) {
+    let mut buffer: BTreeMap<String, Vec<OHLCVData>> = BTreeMap::new();
+    let mut buffered_rows = 0;
+    let mut retry = Retry::new(&config);
+    let mut ticker = tokio::time::interval(config.flush_interval.max(Duration::from_millis(1)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(WriterCommand::Write { key, rows }) => {
+                    buffered_rows += rows.len();
+                    buffer.entry(key).or_default().extend(rows);
+                    metrics.lock().unwrap().queued_keys = buffer.len();
+                    if buffered_rows >= config.max_batch_rows && retry.ready() {
+                        let result = flush(&mut client, &mut buffer, &mut buffered_rows, &metrics).await;
+                        retry.update(&result);
+                        ticker.reset();
+                    }
+                }
+                Some(WriterCommand::Flush(reply)) => {
+                    let result = flush(&mut client, &mut buffer, &mut buffered_rows, &metrics).await;
+                    retry.update(&result);
+                    let _ = reply.send(result);
+                    ticker.reset();
+                }
+                Some(WriterCommand::Close(reply)) => {
+                    let _ = reply.send(flush(&mut client, &mut buffer, &mut buffered_rows, &metrics).await);
+                    break;
+                }
+                None => {
+                    if let Err(e) = flush(&mut client, &mut buffer, &mut buffered_rows, &metrics).await {
+                        tracing::warn!("Final flush of dropped buffered writer failed: {}", e);
+                    }
+                    break;
+                }
+            },
+            _ = ticker.tick() => {
+                if !retry.ready() {
+                    continue;
+                }
+                let result = flush(&mut client, &mut buffer, &mut buffered_rows, &metrics).await;
+                if let Err(e) = &result {
+                    tracing::warn!("Periodic flush failed, {} rows kept for retry: {}", buffered_rows, e);
+                }
+                retry.update(&result);
+            }
+        }
+    }
+}
+
+/// 节点不可用时的刷新退避
+struct Retry {
+    initial: Duration,
+    max: Duration,
+    delay: Duration,
+    next_attempt: Option<Instant>,
+}
+
+impl Retry {
+    fn new(config: &BufferedWriterConfig) -> Self {
+        Self {
+            initial: config.retry_backoff,
+            max: config.max_retry_backoff.max(config.retry_backoff),
+            delay: config.retry_backoff,
+            next_attempt: None,
+        }
+    }
+
+    fn ready(&self) -> bool {
+        self.next_attempt.is_none_or(|at| Instant::now() >= at)
+    }
+
+    fn update(&mut self, result: &Result<()>) {
+        match result {
+            Err(e) if is_node_failure(e) => {
+                tracing::debug!("Retrying buffered writes in {:?}", self.delay);
+                self.next_attempt = Some(Instant::now() + self.delay);
+                self.delay = (self.delay * 2).min(self.max);
+            }
+            _ => {
+                self.next_attempt = None;
+                self.delay = self.initial;
+            }
+        }
+    }
+}
+
+/// 将整个缓冲作为一个批量写请求发送；节点不可用时保留全部数据，
+/// 批量请求被服务器拒绝时逐个key重发以找出被拒绝的key并丢弃
+async fn flush<C: GrpcClientTrait + Send>(
+    client: &mut C,
+    buffer: &mut BTreeMap<String, Vec<OHLCVData>>,
+    buffered_rows: &mut usize,
+    metrics: &StdMutex<WriterMetrics>,
+) -> Result<()> {
+    if buffer.is_empty() {
+        return Ok(());
+    }
+
+    let start = Instant::now();
+    let (mut written, mut dropped) = (0, 0);
+    let mut node_failure = None;
+    let mut rejected = None;
+    match client.write_batch(buffer.iter_mut().map(|(key, rows)| write_request(key, rows)).collect()).await {
+        Ok(()) => {
+            written = *buffered_rows;
+            buffer.clear();
+        }
+        Err(e) if is_node_failure(&e) => node_failure = Some(e),
+        Err(e) if buffer.len() == 1 => {
+            dropped = *buffered_rows;
+            for (key, rows) in std::mem::take(buffer) {
+                tracing::error!("Dropping {} rows for {} rejected by server: {}", rows.len(), key, e);
+            }
+            rejected = Some(e);
+        }
+        Err(e) => {
+            // 服务器返回的错误不带key；同一Epoch的定长记录重写是幂等的，已写入的key可以安全重发
+            tracing::warn!("Batch write of {} keys rejected, retrying key by key: {}", buffer.len(), e);
+            let keys: Vec<String> = buffer.keys().cloned().collect();
+            for key in keys {
+                let request = write_request(&key, buffer.get_mut(&key).expect("key taken from buffer"));
+                match client.write_batch(vec![request]).await {
+                    Ok(()) => written += buffer.remove(&key).map_or(0, |rows| rows.len()),
+                    Err(e) if is_node_failure(&e) => {
+                        node_failure = Some(e);
+                        break;
+                    }
+                    Err(e) => {
+                        let count = buffer.remove(&key).map_or(0, |rows| rows.len());
+                        tracing::error!("Dropping {} rows for {} rejected by server: {}", count, key, e);
+                        dropped += count;
+                        rejected = Some(e);
+                    }
+                }
+            }
+        }
+    }
+    let latency = start.elapsed();
+    *buffered_rows -= written + dropped;
+
+    let mut metrics = metrics.lock().unwrap();
+    metrics.queued_rows = metrics.queued_rows.saturating_sub(written + dropped);
+    metrics.queued_keys = buffer.len();
+    metrics.rows_written += written as u64;
+    metrics.dropped_rows += dropped as u64;
+    if let Some(e) = node_failure {
+        metrics.failed_flushes += 1;
+        return Err(e);
+    }
+
+    metrics.flushes += 1;
+    metrics.last_flush_latency = Some(latency);
+    metrics.max_flush_latency = Some(metrics.max_flush_latency.map_or(latency, |max| max.max(latency)));
+    tracing::debug!("Flushed {} rows in {:?}, dropped {}", written, latency, dropped);
+    rejected.map_or(Ok(()), Err)
+}
+
+/// 按Epoch稳定排序后构造一个key的写请求
+fn write_request(key: &str, rows: &mut [OHLCVData]) -> WriteRequest {
+    rows.sort_by_key(|row| row.epoch);
+    // key在 `write` 时已校验
+    let (symbol, timeframe, attr_group) = split_bucket_key(key).unwrap_or_default();
+    WriteRequest::new(symbol, timeframe, attr_group, rows.to_vec())
+}
@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use async_trait::async_trait;
+use futures::StreamExt;
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{DataShape, QueryRequest, StreamData},
+    client::{GrpcClient, GrpcClientTrait, ReconnectPolicy, ReplicationClient, ReplicationEvent},
+    storage::{RecordType, TransactionGroup},
+    utils::write_atomic,
+};
+
+/// 复制流缓冲的事务组数量
+const CDC_BUFFER: usize = 64;
+
+/// 某个bucket在一个事务组中写入的行
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowsWritten {
+    /// bucket key，如 `AAPL/1Min/OHLCV`
+    pub key: String,
+    /// 与行中各列对应的数据结构，含 `Epoch`，可变长bucket另含 `Nanoseconds`
+    pub schema: Vec<DataShape>,
+    pub rows: Vec<StreamData>,
+}
+
+/// 一个事务组中的全部写入，按key排序
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeBatch {
+    pub transaction_group_id: i64,
+    pub changes: Vec<RowsWritten>,
+}
+
+/// 事务组未携带数据结构时，按key查询bucket结构
+#[async_trait]
+pub trait SchemaResolver: Send + Sync {
+    async fn resolve(&self, key: &str) -> Result<Vec<DataShape>>;
+}
+
+/// 通过查询bucket最后一行获得列名与类型
+pub struct GrpcSchemaResolver {
+    client: GrpcClient,
+}
+
+impl GrpcSchemaResolver {
+    pub fn new(client: GrpcClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SchemaResolver for GrpcSchemaResolver {
+    async fn resolve(&self, key: &str) -> Result<Vec<DataShape>> {
+        let request = QueryRequest {
+            destination: key.to_string(),
+            epoch_start: None,
+            epoch_end: None,
+            limit_record_count: Some(1),
+            limit_from_start: false,
+            columns: Vec::new(),
+        };
+        let dataset = self.client.clone().query(request).await?;
+        let dataset = dataset
+            .data
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("No schema returned for {}", key)))?;
+
+        Ok(dataset
+            .column_names
+            .into_iter()
+            .zip(dataset.column_types)
+            // 查询结果中的Nanoseconds列由服务器生成，不属于写入数据
+            .filter(|(name, _)| name != "Nanoseconds")
+            .map(|(name, data_type)| DataShape { name, data_type })
+            .collect())
+    }
+}
+
+/// 持久化最后一个已处理的事务组ID
+pub trait CheckpointStore: Send + Sync {
+    fn load(&self) -> Result<Option<i64>>;
+    fn save(&self, transaction_group_id: i64) -> Result<()>;
+}
+
+/// 将检查点以文本形式保存在文件中，经 `write_atomic` 写入，避免崩溃或断电时留下半个检查点
+pub struct FileCheckpoint {
+    path: PathBuf,
+}
+
+impl FileCheckpoint {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CheckpointStore for FileCheckpoint {
+    fn load(&self) -> Result<Option<i64>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => content
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| MarketStoreError::InvalidData(format!("Invalid checkpoint in {}", self.path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, transaction_group_id: i64) -> Result<()> {
+        write_atomic(&self.path, transaction_group_id.to_string().as_bytes())
+    }
+}
+
+/// 基于复制流的变更数据捕获（CDC）
+///
+/// 每个事务组转换为一个 `ChangeBatch` 交给handler；handler返回错误时按 `retry` 退避重试，
+/// 成功后才保存检查点，因此每个收到的事务组至少被处理一次。重启后从检查点之后继续，
+/// 但服务器不支持断点续传，停机期间提交的事务组不会重发。
+pub struct ChangeDataCapture {
+    addr: String,
+    policy: ReconnectPolicy,
+    retry: ReconnectPolicy,
+    checkpoint: Arc<dyn CheckpointStore>,
+    resolver: Option<Arc<dyn SchemaResolver>>,
+}
+
+impl ChangeDataCapture {
+    pub fn new<C: CheckpointStore + 'static>(addr: String, policy: ReconnectPolicy, checkpoint: C) -> Self {
+        Self {
+            addr,
+            retry: policy.clone(),
+            policy,
+            checkpoint: Arc::new(checkpoint),
+            resolver: None,
+        }
+    }
+
+    /// 事务组未携带数据结构时使用的解析器
+    pub fn with_schema_resolver<R: SchemaResolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// handler失败时的重试策略，默认与重连策略相同
+    pub fn with_retry_policy(mut self, retry: ReconnectPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 持续消费复制流，直到放弃重连、事务组无法解码或handler重试耗尽
+    pub async fn run<F, Fut>(self, mut handler: F) -> Result<()>
+    where
+        F: FnMut(ChangeBatch) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut replication = ReplicationClient::new(self.addr.clone(), self.policy.clone());
+        if let Some(checkpoint) = self.checkpoint.load()? {
+            tracing::info!("Resuming change data capture after transaction group {}", checkpoint);
+            replication = replication.with_position(checkpoint);
+        }
+        let mut stream = replication.stream(CDC_BUFFER);
+        let mut schemas = HashMap::new();
+
+        while let Some(event) = stream.next().await {
+            let message = match event? {
+                ReplicationEvent::TransactionGroup(message) => message,
+                ReplicationEvent::State(state) => {
+                    tracing::debug!("Change data capture connection state: {:?}", state);
+                    continue;
+                }
+            };
+
+            let group = message.decode()?;
+            let batch = self.to_batch(group, &mut schemas).await?;
+            let transaction_group_id = batch.transaction_group_id;
+            self.deliver(batch, &mut handler).await?;
+            self.checkpoint.save(transaction_group_id)?;
+        }
+
+        Ok(())
+    }
+
+    async fn deliver<F, Fut>(&self, batch: ChangeBatch, handler: &mut F) -> Result<()>
+    where
+        F: FnMut(ChangeBatch) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut attempt: u32 = 0;
+        loop {
+            let error = match handler(batch.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            attempt += 1;
+            if self.retry.max_retries.is_some_and(|max_retries| attempt > max_retries) {
+                return Err(error);
+            }
+            let delay = self.retry.backoff(attempt);
+            tracing::warn!(
+                "Change handler failed for transaction group {}: {}, retrying in {:?}",
+                batch.transaction_group_id, error, delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn to_batch(&self, mut group: TransactionGroup, schemas: &mut HashMap<String, Vec<DataShape>>) -> Result<ChangeBatch> {
+        let mut changes: BTreeMap<String, RowsWritten> = BTreeMap::new();
+
+        for set in &mut group.write_sets {
+            let key = set.key()?;
+            if set.data_shapes.is_empty() {
+                set.data_shapes = self.resolve_schema(&key, schemas).await?;
+            } else {
+                schemas.insert(key.clone(), set.data_shapes.clone());
+            }
+
+            let rows = set.rows()?;
+            let entry = changes.entry(key.clone()).or_insert_with(|| RowsWritten {
+                key,
+                schema: result_schema(&set.data_shapes, set.record_type),
+                rows: Vec::new(),
+            });
+            entry.rows.extend(rows);
+        }
+
+        Ok(ChangeBatch {
+            transaction_group_id: group.transaction_group_id,
+            changes: changes.into_values().collect(),
+        })
+    }
+
+    async fn resolve_schema(&self, key: &str, schemas: &mut HashMap<String, Vec<DataShape>>) -> Result<Vec<DataShape>> {
+        if let Some(schema) = schemas.get(key) {
+            return Ok(schema.clone());
+        }
+        let resolver = self
+            .resolver
+            .as_ref()
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("No data shapes for {} and no schema resolver", key)))?;
+        let schema = resolver.resolve(key).await?;
+        schemas.insert(key.to_string(), schema.clone());
+        Ok(schema)
+    }
+}
+
+/// 行中实际包含的列：Epoch在前，可变长记录追加Nanoseconds
+fn result_schema(data_shapes: &[DataShape], record_type: RecordType) -> Vec<DataShape> {
+    let mut schema = Vec::with_capacity(data_shapes.len() + 1);
+    if !data_shapes.iter().any(|shape| shape.name == "Epoch") {
+        schema.push(DataShape { name: "Epoch".to_string(), data_type: "i8".to_string() });
+    }
+    schema.extend(data_shapes.iter().cloned());
+    if record_type == RecordType::Variable {
+        schema.push(DataShape { name: "Nanoseconds".to_string(), data_type: "i4".to_string() });
+    }
+    schema
+}
@@ -2,7 +2,8 @@ use tonic::{transport::Channel, Request};
 use async_trait::async_trait;
 use crate::{
     error::{MarketStoreError, Result},
-    models::{QueryRequest, OHLCVData, SymbolFormat, DataShape, NumpyMultiDataset},
+    models::{QueryRequest, OHLCVData, SymbolFormat, DataShape, NumpyMultiDataset, WriteRequest as KeyWrite},
+    utils::bucket_key,
 };
 
 // 生成的protobuf代码
@@ -22,12 +23,15 @@ use proto::{
 pub trait GrpcClientTrait {
     async fn query(&mut self, request: QueryRequest) -> Result<NumpyMultiDataset>;
     async fn write(&mut self, symbol: &str, timeframe: &str, attr_group: &str, data: Vec<OHLCVData>) -> Result<()>;
+    /// 在一个 `MultiWriteRequest` 中写入多个key
+    async fn write_batch(&mut self, requests: Vec<KeyWrite>) -> Result<()>;
     async fn list_symbols(&mut self, format: SymbolFormat) -> Result<Vec<String>>;
     async fn create_bucket(&mut self, symbol: &str, timeframe: &str, attr_group: &str, data_shapes: Vec<DataShape>) -> Result<()>;
     async fn destroy_bucket(&mut self, symbol: &str, timeframe: &str, attr_group: &str) -> Result<()>;
     async fn server_version(&mut self) -> Result<String>;
 }
 
+#[derive(Clone)]
 pub struct GrpcClient {
     client: MarketstoreClient<Channel>,
 }
@@ -42,6 +46,16 @@ impl GrpcClient {
         let client = MarketstoreClient::new(channel);
         Ok(Self { client })
     }
+
+    /// 延迟建立连接，首次调用RPC时才真正连接（适用于启动时可能不可达的节点）
+    pub fn connect_lazy(addr: String) -> Result<Self> {
+        let channel = Channel::from_shared(addr)
+            .map_err(|e| MarketStoreError::InvalidData(e.to_string()))?
+            .connect_lazy();
+
+        let client = MarketstoreClient::new(channel);
+        Ok(Self { client })
+    }
 }
 
 #[async_trait]
@@ -100,6 +114,42 @@ impl GrpcClientTrait for GrpcClient {
         Ok(())
     }
 
+    async fn write_batch(&mut self, requests: Vec<KeyWrite>) -> Result<()> {
+        let requests = requests
+            .iter()
+            .map(|request| {
+                let key = bucket_key(&request.symbol, &request.timeframe, &request.attr_group);
+                let numpy_dataset = convert_ohlcv_to_numpy_dataset(&request.data);
+                WriteRequest {
+                    data: Some(ProtoNumpyMultiDataset {
+                        data: Some(convert_numpy_dataset_to_proto(&numpy_dataset)),
+                        start_index: [(key.clone(), 0)].into_iter().collect(),
+                        lengths: [(key, request.data.len() as i32)].into_iter().collect(),
+                    }),
+                    is_variable_length: false,
+                }
+            })
+            .collect();
+
+        let response = self.client
+            .write(Request::new(MultiWriteRequest { requests }))
+            .await?;
+
+        // 服务器对每个key单独返回错误
+        let errors: Vec<String> = response
+            .into_inner()
+            .responses
+            .into_iter()
+            .map(|response| response.error)
+            .filter(|error| !error.is_empty())
+            .collect();
+        if !errors.is_empty() {
+            return Err(MarketStoreError::InvalidData(format!("Write failed: {}", errors.join("; "))));
+        }
+
+        Ok(())
+    }
+
     async fn list_symbols(&mut self, format: SymbolFormat) -> Result<Vec<String>> {
         let request = ListSymbolsRequest {
             format: format.into(),
@@ -174,12 +224,12 @@ fn convert_ohlcv_to_numpy_dataset(data: &[OHLCVData]) -> crate::models::NumpyDat
         column_names: vec!["Epoch".to_string(), "Open".to_string(), "High".to_string(),
                           "Low".to_string(), "Close".to_string(), "Volume".to_string()],
         column_data: vec![
-            epochs.into_iter().map(|e| e.to_le_bytes()).flatten().collect(),
-            opens.into_iter().map(|o| o.to_le_bytes()).flatten().collect(),
-            highs.into_iter().map(|h| h.to_le_bytes()).flatten().collect(),
-            lows.into_iter().map(|l| l.to_le_bytes()).flatten().collect(),
-            closes.into_iter().map(|c| c.to_le_bytes()).flatten().collect(),
-            volumes.into_iter().map(|v| v.to_le_bytes()).flatten().collect(),
+            epochs.into_iter().flat_map(|e| e.to_le_bytes()).collect(),
+            opens.into_iter().flat_map(|o| o.to_le_bytes()).collect(),
+            highs.into_iter().flat_map(|h| h.to_le_bytes()).collect(),
+            lows.into_iter().flat_map(|l| l.to_le_bytes()).collect(),
+            closes.into_iter().flat_map(|c| c.to_le_bytes()).collect(),
+            volumes.into_iter().flat_map(|v| v.to_le_bytes()).collect(),
         ],
         length: data.len() as i32,
     }
@@ -196,16 +246,12 @@ fn convert_numpy_dataset_to_proto(dataset: &crate::models::NumpyDataset) -> Prot
 }
 
 fn convert_proto_to_numpy_multi_dataset(proto_dataset: &ProtoNumpyMultiDataset) -> NumpyMultiDataset {
-    let data = if let Some(proto_data) = &proto_dataset.data {
-        Some(crate::models::NumpyDataset {
-            column_types: proto_data.column_types.clone(),
-            column_names: proto_data.column_names.clone(),
-            column_data: proto_data.column_data.clone(),
-            length: proto_data.length,
-        })
-    } else {
-        None
-    };
+    let data = proto_dataset.data.as_ref().map(|proto_data| crate::models::NumpyDataset {
+        column_types: proto_data.column_types.clone(),
+        column_names: proto_data.column_names.clone(),
+        column_data: proto_data.column_data.clone(),
+        length: proto_data.length,
+    });
 
     NumpyMultiDataset {
         data,
@@ -0,0 +1,133 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// WebSocket心跳与死连接检测配置
+///
+/// 半开的TCP连接不会产生任何错误，只能依靠主动ping和读超时发现。
+/// 连接被判定失效时消息循环返回 `MarketStoreError::Timeout`，启用重连时会据此重新连接。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    /// 客户端发送ping的间隔，`None` 表示不主动ping
+    pub ping_interval: Option<Duration>,
+    /// 发送ping后等待任意入站数据（通常是pong）的最长时间
+    pub pong_timeout: Duration,
+    /// 连续未收到任何数据的最长时间，`None` 表示不限制
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Some(Duration::from_secs(30)),
+            pong_timeout: Duration::from_secs(10),
+            // 读空闲超时需显式开启，避免长时间无推送的订阅被断开
+            idle_timeout: None,
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    /// 关闭心跳，只被动响应服务器的ping
+    pub fn disabled() -> Self {
+        Self {
+            ping_interval: None,
+            pong_timeout: Duration::from_secs(10),
+            idle_timeout: None,
+        }
+    }
+
+    /// 开启读空闲超时；服务器每54秒ping一次，超时应大于该间隔，如90秒
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+}
+
+/// 心跳计时器到期后需要执行的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HeartbeatAction {
+    None,
+    SendPing,
+    /// 连接已失效，附带原因
+    Dead(String),
+}
+
+/// 单次消息循环内的心跳状态
+pub(crate) struct HeartbeatState {
+    config: HeartbeatConfig,
+    last_received: Instant,
+    next_ping: Option<Instant>,
+    pong_deadline: Option<Instant>,
+    // 暂停读取socket的起始时间，暂停期间收不到pong，不做任何判定
+    paused_at: Option<Instant>,
+}
+
+impl HeartbeatState {
+    pub(crate) fn new(config: &HeartbeatConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            config: config.clone(),
+            last_received: now,
+            next_ping: config.ping_interval.map(|interval| now + interval),
+            pong_deadline: None,
+            paused_at: None,
+        }
+    }
+
+    /// 消费方积压导致暂停读取时冻结全部计时
+    pub(crate) fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+    }
+
+    /// 恢复读取，各截止时间顺延暂停的时长
+    pub(crate) fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            let paused = paused_at.elapsed();
+            self.last_received += paused;
+            self.next_ping = self.next_ping.map(|deadline| deadline + paused);
+            self.pong_deadline = self.pong_deadline.map(|deadline| deadline + paused);
+        }
+    }
+
+    /// 收到任意帧都说明连接仍然存活
+    pub(crate) fn received(&mut self) {
+        self.last_received = Instant::now();
+        self.pong_deadline = None;
+    }
+
+    /// 下一次需要检查的时间点，没有任何计时或已暂停时返回 `None`
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        if self.paused_at.is_some() {
+            return None;
+        }
+        let idle_deadline = self.config.idle_timeout.map(|timeout| self.last_received + timeout);
+        [self.next_ping, self.pong_deadline, idle_deadline].into_iter().flatten().min()
+    }
+
+    pub(crate) fn on_timer(&mut self) -> HeartbeatAction {
+        let now = Instant::now();
+
+        if self.pong_deadline.is_some_and(|deadline| deadline <= now) {
+            return HeartbeatAction::Dead(format!(
+                "No pong received within {:?}", self.config.pong_timeout
+            ));
+        }
+        if let Some(timeout) = self.config.idle_timeout {
+            if self.last_received + timeout <= now {
+                return HeartbeatAction::Dead(format!("No data received for {:?}", timeout));
+            }
+        }
+        match (self.next_ping, self.config.ping_interval) {
+            (Some(next_ping), Some(interval)) if next_ping <= now => {
+                self.next_ping = Some(now + interval);
+                if self.pong_deadline.is_none() {
+                    self.pong_deadline = Some(now + self.config.pong_timeout);
+                }
+                HeartbeatAction::SendPing
+            }
+            _ => HeartbeatAction::None,
+        }
+    }
+}
@@ -1,15 +1,24 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use futures::StreamExt;
 use tokio::sync::Mutex;
 use tokio::sync::oneshot;
 use crate::{
-    error::Result,
-    models::{QueryRequest, OHLCVData, StreamSubscription, SymbolFormat, DataShape, NumpyMultiDataset},
-    client::{GrpcClient, WebSocketClient, GrpcClientTrait},
+    error::{MarketStoreError, Result},
+    models::{QueryRequest, OHLCVData, StreamSubscription, SymbolFormat, DataShape, NumpyMultiDataset, StreamData, WriteRequest},
+    client::{GrpcClient, WebSocketClient, GrpcClientTrait, ReconnectingSubscription, ReconnectPolicy, ConnectionState, SubscriptionStream, StreamHub, HeartbeatConfig, SnapshotStream, StreamRecorder},
+    utils::{numpy_dataset_to_records, split_bucket_key},
+    adjustment::{Adjuster, AdjustMode, CorporateActions},
 };
 
+/// 快照订阅的实时缓冲区容量
+const SNAPSHOT_BUFFER: usize = 1024;
+
 pub struct MarketStoreClient {
     grpc_client: Arc<Mutex<GrpcClient>>,
     websocket_url: String,
+    heartbeat: HeartbeatConfig,
+    recorder: Option<StreamRecorder>,
 }
 
 impl MarketStoreClient {
@@ -19,9 +28,36 @@ impl MarketStoreClient {
         Ok(Self {
             grpc_client: Arc::new(Mutex::new(grpc_client)),
             websocket_url,
+            heartbeat: HeartbeatConfig::default(),
+            recorder: None,
         })
     }
     
+    /// 设置所有WebSocket订阅使用的心跳与死连接检测参数
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+    
+    /// 将所有WebSocket订阅收到的帧写入录制文件，可用 `StreamReplayer` 回放
+    pub fn with_recorder(mut self, recorder: StreamRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+    
+    async fn connect_websocket(&self) -> Result<WebSocketClient> {
+        let ws_client = WebSocketClient::connect(&self.websocket_url).await?;
+        Ok(self.configure_websocket(ws_client))
+    }
+    
+    fn configure_websocket(&self, ws_client: WebSocketClient) -> WebSocketClient {
+        let ws_client = ws_client.with_heartbeat(self.heartbeat.clone());
+        match &self.recorder {
+            Some(recorder) => ws_client.with_recorder(recorder.clone()),
+            None => ws_client,
+        }
+    }
+    
     pub async fn query(
         &mut self,
         symbol: &str,
@@ -43,7 +79,30 @@ impl MarketStoreClient {
         let mut client = self.grpc_client.lock().await;
         client.query(request).await
     }
-    
+
+    /// 查询并按 `adjuster` 复权，`AdjustMode::Raw` 时返回原始数据
+    pub async fn query_adjusted(&mut self, request: QueryRequest, adjuster: &Adjuster) -> Result<NumpyMultiDataset> {
+        let mut result = self.grpc_client.lock().await.query(request).await?;
+        if adjuster.mode() != AdjustMode::Raw {
+            if let Some(dataset) = &result.data {
+                result.data = Some(adjuster.adjust_dataset(dataset)?);
+            }
+        }
+        Ok(result)
+    }
+
+    /// 读取服务器 `<symbol>/1D/ACTIONS` bucket中的公司行动
+    pub async fn corporate_actions(&mut self, symbol: &str) -> Result<CorporateActions> {
+        let mut client = self.grpc_client.lock().await;
+        CorporateActions::query(&mut *client, symbol).await
+    }
+
+    /// 为 `adjuster` 中的现金分红查询除权日前一个交易日的收盘价
+    pub async fn resolve_previous_closes(&mut self, adjuster: Adjuster, symbol: &str) -> Result<Adjuster> {
+        let mut client = self.grpc_client.lock().await;
+        adjuster.resolve_previous_closes(&mut *client, symbol).await
+    }
+
     pub async fn write(
         &mut self,
         symbol: &str,
@@ -95,29 +154,134 @@ impl MarketStoreClient {
         F: FnMut(crate::models::StreamPayload) -> Result<()> + Send + 'static,
     {
         let websocket_url = self.websocket_url.clone();
+        let heartbeat = self.heartbeat.clone();
+        let recorder = self.recorder.clone();
         
         let handle = tokio::spawn(async move {
-            let ws_client = WebSocketClient::connect(&websocket_url).await?;
+            let mut ws_client = WebSocketClient::connect(&websocket_url).await?.with_heartbeat(heartbeat);
+            if let Some(recorder) = recorder {
+                ws_client = ws_client.with_recorder(recorder);
+            }
             ws_client.subscribe_with_handler(subscription, handler).await
         });
         
         Ok(handle)
     }
     
-    pub async fn subscribe_realtime_with_cancel<F>(
+    /// 以 `Stream` 形式订阅实时数据，返回的流被drop时自动关闭连接
+    pub async fn subscribe_stream(
         &self,
         subscription: StreamSubscription,
+        buffer: usize,
+    ) -> Result<SubscriptionStream> {
+        let ws_client = self.connect_websocket().await?;
+        ws_client.subscribe_stream(subscription, buffer).await
+    }
+    
+    /// 以 `Stream` 形式订阅实时数据并将推送解码为 `T`（如 `OHLCVData` 或 `StreamColumns`）
+    pub async fn subscribe_stream_as<T>(
+        &self,
+        subscription: StreamSubscription,
+        buffer: usize,
+    ) -> Result<SubscriptionStream<T>>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let ws_client = self.connect_websocket().await?;
+        ws_client.subscribe_stream_as(subscription, buffer).await
+    }
+    
+    /// 建立共享的订阅中心，多个消费者通过 `StreamHub::subscribe` 复用同一条WebSocket连接
+    pub async fn stream_hub(&self) -> Result<StreamHub> {
+        Ok(StreamHub::from_client(self.connect_websocket().await?))
+    }
+    
+    /// 先订阅 `keys` 并缓冲实时推送，再查询每个key最近 `lookback` 条历史，
+    /// 依次发出快照、`SnapshotEnd` 以及按epoch排序并去重后的实时数据，避免两次调用之间的数据丢失或重复
+    pub async fn subscribe_with_snapshot(&self, keys: Vec<String>, lookback: i32) -> Result<SnapshotStream> {
+        for key in &keys {
+            let concrete = split_bucket_key(key).is_ok() && !key.contains(['*', '?', '[', '{', '\\']);
+            if !concrete {
+                return Err(MarketStoreError::InvalidData(format!(
+                    "Snapshot key must be a concrete SYMBOL/TIMEFRAME/ATTRGROUP: {}", key
+                )));
+            }
+        }
+        
+        let subscription = StreamSubscription::new().add_streams(keys.clone());
+        let mut live = self.subscribe_stream(subscription, SNAPSHOT_BUFFER).await?;
+        
+        // 查询快照的同时持续读取实时推送，防止缓冲区溢出造成缺口
+        let mut pending = Vec::new();
+        let snapshot = {
+            let query = self.query_snapshot(&keys, lookback);
+            tokio::pin!(query);
+            loop {
+                tokio::select! {
+                    result = &mut query => break result?,
+                    event = live.next() => match event {
+                        Some(Ok(event)) => pending.push(event),
+                        Some(Err(e)) => return Err(e),
+                        None => {
+                            return Err(MarketStoreError::Connection(
+                                "Subscription closed before snapshot completed".to_string(),
+                            ));
+                        }
+                    },
+                }
+            }
+        };
+        
+        Ok(SnapshotStream::spawn(keys, snapshot, pending, live, SNAPSHOT_BUFFER))
+    }
+    
+    async fn query_snapshot(&self, keys: &[String], lookback: i32) -> Result<HashMap<String, Vec<StreamData>>> {
+        let mut snapshot = HashMap::new();
+        for key in keys {
+            let (symbol, timeframe, attr_group) = split_bucket_key(key)?;
+            let request = QueryRequest::builder()
+                .symbol(symbol)
+                .timeframe(timeframe)
+                .attr_group(attr_group)
+                .start_time(0)
+                .end_time(i64::MAX)
+                .limit(lookback)
+                .build()?;
+            
+            let dataset = self.grpc_client.lock().await.query(request).await?;
+            let mut records = match dataset.data {
+                Some(data) => numpy_dataset_to_records(&data)?,
+                None => Vec::new(),
+            };
+            records.sort_by_key(|record| record.get("Epoch").and_then(|epoch| epoch.as_i64()).unwrap_or(i64::MIN));
+            tracing::debug!("Snapshot for {} has {} records", key, records.len());
+            snapshot.insert(key.clone(), records);
+        }
+        Ok(snapshot)
+    }
+    
+    /// 断线后按 `policy` 自动重连并重放订阅，开启 `policy.backfill` 时通过gRPC补齐断线期间的数据
+    pub async fn subscribe_realtime_with_reconnect<F, S>(
+        &self,
+        subscription: StreamSubscription,
+        policy: ReconnectPolicy,
         handler: F,
+        on_state: S,
         cancel: oneshot::Receiver<()>,
     ) -> Result<tokio::task::JoinHandle<Result<()>>>
     where
         F: FnMut(crate::models::StreamPayload) -> Result<()> + Send + 'static,
+        S: FnMut(ConnectionState) + Send + 'static,
     {
-        let websocket_url = self.websocket_url.clone();
+        let mut subscription = ReconnectingSubscription::new(&self.websocket_url, subscription, policy)
+            .with_backfill_client(self.grpc_client.clone())
+            .with_heartbeat(self.heartbeat.clone());
+        if let Some(recorder) = &self.recorder {
+            subscription = subscription.with_recorder(recorder.clone());
+        }
         
         let handle = tokio::spawn(async move {
-            let ws_client = WebSocketClient::connect(&websocket_url).await?;
-            ws_client.subscribe_with_handler_and_cancel(subscription, handler, cancel).await
+            subscription.run(handler, on_state, cancel).await
         });
         
         Ok(handle)
@@ -157,6 +321,12 @@ impl MarketStoreClient {
         Ok(())
     }
     
+    /// 在一个 `MultiWriteRequest` 中执行多个写入请求，如 `BarBuilder::write_requests` 的输出
+    pub async fn write_requests(&mut self, requests: Vec<WriteRequest>) -> Result<()> {
+        let mut client = self.grpc_client.lock().await;
+        client.write_batch(requests).await
+    }
+    
     pub async fn health_check(&mut self) -> Result<bool> {
         match self.server_version().await {
             Ok(_) => Ok(true),
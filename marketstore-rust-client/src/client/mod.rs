@@ -1,7 +1,31 @@
 pub mod grpc_client;
 pub mod websocket_client;
 pub mod hybrid_client;
+pub mod routed_client;
+pub mod reconnect;
+pub mod subscription_stream;
+pub mod stream_hub;
+pub mod heartbeat;
+pub mod snapshot;
+pub mod recording;
+pub mod replication_client;
+pub mod cdc;
+pub mod buffered_writer;
+pub mod spool;
+pub mod backfill;
 
 pub use grpc_client::*;
 pub use websocket_client::*;
-pub use hybrid_client::*; 
\ No newline at end of file
+pub use hybrid_client::*;
+pub use routed_client::*;
+pub use reconnect::*;
+pub use subscription_stream::*;
+pub use stream_hub::*;
+pub use heartbeat::*;
+pub use snapshot::*;
+pub use recording::*;
+pub use replication_client::*;
+pub use cdc::*;
+pub use buffered_writer::*;
+pub use spool::*;
+pub use backfill::*; 
\ No newline at end of file
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{QueryRequest, StreamPayload, StreamSubscription},
+    client::{payloads_only, GrpcClient, GrpcClientTrait, HeartbeatConfig, LoopExit, StreamRecorder, WebSocketClient},
+    utils::{numpy_dataset_to_records, split_bucket_key},
+};
+
+/// 断线重连的退避策略
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// 第一次重连前的等待时间
+    pub initial_backoff: Duration,
+    /// 退避时间上限
+    pub max_backoff: Duration,
+    /// 每次失败后退避时间的倍数
+    pub multiplier: f64,
+    /// 连续重连失败的最大次数，None表示无限重试
+    pub max_retries: Option<u32>,
+    /// 重连后是否通过gRPC查询补齐断线期间缺失的数据
+    pub backfill: bool,
+    /// 补数据时每次查询的最大记录数，超出时分页查询直到补齐
+    pub backfill_limit: i32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            max_retries: None,
+            backfill: false,
+            backfill_limit: 10000,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次重连（从1开始）前的等待时间
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(32) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exp);
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// 订阅连接的状态变化
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    /// 正在建立首次连接
+    Connecting,
+    /// 已连接并发送订阅
+    Connected,
+    /// 连接断开
+    Disconnected { reason: String },
+    /// 等待 `delay` 后进行第 `attempt` 次重连
+    Reconnecting { attempt: u32, delay: Duration },
+    /// 订阅结束（取消或放弃重连）
+    Closed,
+}
+
+/// 断线自动重连并重放订阅的WebSocket订阅
+pub struct ReconnectingSubscription {
+    url: String,
+    subscription: StreamSubscription,
+    policy: ReconnectPolicy,
+    grpc_client: Option<Arc<Mutex<GrpcClient>>>,
+    heartbeat: HeartbeatConfig,
+    recorder: Option<StreamRecorder>,
+}
+
+impl ReconnectingSubscription {
+    pub fn new(url: &str, subscription: StreamSubscription, policy: ReconnectPolicy) -> Self {
+        Self {
+            url: url.to_string(),
+            subscription,
+            policy,
+            grpc_client: None,
+            heartbeat: HeartbeatConfig::default(),
+            recorder: None,
+        }
+    }
+
+    /// 设置补数据使用的gRPC客户端（需要 `policy.backfill` 为 true）
+    pub fn with_backfill_client(mut self, grpc_client: Arc<Mutex<GrpcClient>>) -> Self {
+        self.grpc_client = Some(grpc_client);
+        self
+    }
+
+    /// 设置每条连接的心跳参数，连接被判定失效时按断线处理并重连
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// 将每条连接收到的帧写入同一个录制文件
+    pub fn with_recorder(mut self, recorder: StreamRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// 运行订阅直到取消或重连次数耗尽，状态变化通过 `on_state` 通知
+    pub async fn run<F, S>(self, mut handler: F, mut on_state: S, mut cancel: oneshot::Receiver<()>) -> Result<()>
+    where
+        F: FnMut(StreamPayload) -> Result<()> + Send + 'static,
+        S: FnMut(ConnectionState) + Send + 'static,
+    {
+        let mut last_epochs: HashMap<String, i64> = HashMap::new();
+        // 本次重连补齐到的epoch，重连后推送的不大于该值的行已经交付过
+        let mut backfilled: HashMap<String, i64> = HashMap::new();
+        let mut disconnected_at: Option<i64> = None;
+        let mut attempt: u32 = 0;
+
+        on_state(ConnectionState::Connecting);
+
+        loop {
+            let mut was_connected = false;
+            let connected = WebSocketClient::connect(&self.url)
+                .await
+                .map(|ws_client| {
+                    let ws_client = ws_client.with_heartbeat(self.heartbeat.clone());
+                    match &self.recorder {
+                        Some(recorder) => ws_client.with_recorder(recorder.clone()),
+                        None => ws_client,
+                    }
+                });
+            let exit = match connected {
+                Ok(mut ws_client) => match ws_client.subscribe(self.subscription.clone()).await {
+                    Ok(()) => {
+                        attempt = 0;
+                        was_connected = true;
+                        on_state(ConnectionState::Connected);
+
+                        backfilled.clear();
+                        if let Some(since) = disconnected_at.take() {
+                            self.backfill(since, &mut last_epochs, &mut backfilled, &mut handler).await;
+                        }
+
+                        let mut tracking_handler = payloads_only(|payload: StreamPayload| {
+                            if let Some(epoch) = payload_epoch(&payload) {
+                                if let Some(&through) = backfilled.get(&payload.key) {
+                                    if epoch <= through {
+                                        tracing::debug!("Dropping live row of {} at {} already backfilled", payload.key, epoch);
+                                        return Ok(());
+                                    }
+                                    backfilled.remove(&payload.key);
+                                }
+                                last_epochs.insert(payload.key.clone(), epoch);
+                            }
+                            handler(payload)
+                        });
+                        ws_client.run_loop(&mut tracking_handler, Some(&mut cancel), None).await
+                    }
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+
+            // 服务器拒绝订阅属于配置错误，重连也无法恢复
+            if let Err(MarketStoreError::SubscriptionRejected(_)) = &exit {
+                on_state(ConnectionState::Closed);
+                return exit.map(|_| ());
+            }
+
+            let reason = match exit {
+                Ok(LoopExit::Cancelled) => {
+                    on_state(ConnectionState::Closed);
+                    return Ok(());
+                }
+                Ok(LoopExit::ServerClosed) => "closed by server".to_string(),
+                Ok(LoopExit::StreamEnded) => "stream ended".to_string(),
+                Err(e) => e.to_string(),
+            };
+            if was_connected {
+                tracing::warn!("WebSocket subscription disconnected: {}", reason);
+                on_state(ConnectionState::Disconnected { reason });
+                disconnected_at = Some(chrono::Utc::now().timestamp());
+            } else {
+                tracing::warn!("WebSocket connection attempt failed: {}", reason);
+            }
+
+            attempt += 1;
+            if let Some(max_retries) = self.policy.max_retries {
+                if attempt > max_retries {
+                    on_state(ConnectionState::Closed);
+                    return Err(MarketStoreError::Connection(format!(
+                        "Gave up reconnecting after {} attempts", max_retries
+                    )));
+                }
+            }
+
+            let delay = self.policy.backoff(attempt);
+            on_state(ConnectionState::Reconnecting { attempt, delay });
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = &mut cancel => {
+                    on_state(ConnectionState::Closed);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// 查询断线期间每个key缺失的数据，按 `backfill_limit` 分页直到补齐，并按照实时推送的格式交给handler
+    async fn backfill<F>(
+        &self,
+        since: i64,
+        last_epochs: &mut HashMap<String, i64>,
+        backfilled: &mut HashMap<String, i64>,
+        handler: &mut F,
+    ) where
+        F: FnMut(StreamPayload) -> Result<()>,
+    {
+        if !self.policy.backfill {
+            return;
+        }
+        let grpc_client = match &self.grpc_client {
+            Some(client) => client,
+            None => {
+                tracing::warn!("Backfill enabled but no gRPC client configured");
+                return;
+            }
+        };
+
+        // 已收到过数据的key，以及订阅中不含通配符的key
+        let mut keys: Vec<String> = last_epochs.keys().cloned().collect();
+        for stream in &self.subscription.streams {
+            if !stream.contains('*') && !keys.contains(stream) {
+                keys.push(stream.clone());
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        for key in keys {
+            let mut start = last_epochs.get(&key).map(|epoch| epoch + 1).unwrap_or(since);
+            let Ok((symbol, timeframe, attr_group)) = split_bucket_key(&key) else {
+                continue;
+            };
+
+            let mut total = 0;
+            while start <= now {
+                let mut request = match QueryRequest::builder()
+                    .symbol(symbol)
+                    .timeframe(timeframe)
+                    .attr_group(attr_group)
+                    .start_time(start)
+                    .end_time(now)
+                    .limit(self.policy.backfill_limit)
+                    .build()
+                {
+                    Ok(request) => request,
+                    Err(e) => {
+                        tracing::warn!("Invalid backfill key {}: {}", key, e);
+                        break;
+                    }
+                };
+                request.limit_from_start = true;
+
+                let result = grpc_client.lock().await.query(request).await;
+                let records = match result.and_then(|dataset| match dataset.data {
+                    Some(data) => numpy_dataset_to_records(&data),
+                    None => Ok(Vec::new()),
+                }) {
+                    Ok(records) => records,
+                    Err(e) => {
+                        tracing::warn!("Backfill query for {} failed after {} records: {}", key, total, e);
+                        break;
+                    }
+                };
+
+                let page = records.len();
+                let mut page_end = None;
+                for data in records {
+                    let payload = StreamPayload { key: key.clone(), data };
+                    if let Some(epoch) = payload_epoch(&payload) {
+                        last_epochs.insert(key.clone(), epoch);
+                        backfilled.insert(key.clone(), epoch);
+                        page_end = Some(epoch);
+                    }
+                    if let Err(e) = handler(payload) {
+                        tracing::warn!("Handler error: {}", e);
+                    }
+                }
+                total += page;
+
+                // 不足一页说明已经补齐；整页但无法推进起点时停止，避免重复查询同一页
+                if self.policy.backfill_limit <= 0 || page < self.policy.backfill_limit as usize {
+                    break;
+                }
+                match page_end {
+                    Some(end) if end >= start => start = end + 1,
+                    _ => {
+                        tracing::warn!("Backfill for {} truncated after {} records at {}", key, total, start);
+                        break;
+                    }
+                }
+            }
+
+            tracing::info!("Backfilled {} records for {}", total, key);
+        }
+    }
+}
+
+fn payload_epoch(payload: &StreamPayload) -> Option<i64> {
+    payload.data.get("Epoch").and_then(|value| value.as_i64())
+}
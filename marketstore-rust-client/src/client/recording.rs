@@ -0,0 +1,285 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use futures::{SinkExt, StreamExt};
+use serde::de::IgnoredAny;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{ErrorMessage, StreamMatcher, StreamPayload, SubscribeMessage},
+};
+
+/// 录制文件头
+const RECORDING_MAGIC: &[u8; 8] = b"MSWSREC\x01";
+
+/// 录制文件中的一帧：接收时间（Unix纳秒）与原始msgpack数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    pub received_at: i64,
+    pub data: Vec<u8>,
+}
+
+/// 写入线程队列中最多等待的帧数，超出时丢弃新帧，避免录制拖慢订阅
+const RECORDER_QUEUE: usize = 4096;
+
+enum RecorderCommand {
+    Frame(RecordedFrame),
+    Flush(mpsc::Sender<std::io::Result<()>>),
+}
+
+/// 将 `WebSocketClient` 收到的每个二进制帧追加写入录制文件
+///
+/// 文件格式：8字节文件头，之后每帧为 `i64` 接收时间、`u32` 长度（均为小端）和原始数据。
+/// 文件I/O在独立的写入线程中进行，每批帧写完即刷到文件，不阻塞订阅的异步循环。
+/// 可clone后在多条连接（如重连）间共享同一文件，所有clone释放后写入线程退出。
+#[derive(Clone)]
+pub struct StreamRecorder {
+    sender: mpsc::SyncSender<RecorderCommand>,
+}
+
+impl StreamRecorder {
+    /// 创建（覆盖）录制文件并启动写入线程
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(RECORDING_MAGIC)?;
+        writer.flush()?;
+
+        let (sender, receiver) = mpsc::sync_channel(RECORDER_QUEUE);
+        std::thread::Builder::new()
+            .name("stream-recorder".to_string())
+            .spawn(move || write_frames(writer, receiver))?;
+        Ok(Self { sender })
+    }
+
+    /// 记录一帧；队列已满或写入失败只记录日志，不影响订阅
+    pub fn record(&self, data: &[u8]) {
+        let received_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as i64)
+            .unwrap_or_default();
+
+        let frame = RecordedFrame { received_at, data: data.to_vec() };
+        match self.sender.try_send(RecorderCommand::Frame(frame)) {
+            Ok(()) => {}
+            Err(mpsc::TrySendError::Full(_)) => tracing::warn!("Recorder queue is full, dropping stream frame"),
+            Err(mpsc::TrySendError::Disconnected(_)) => tracing::warn!("Recorder writer has stopped, dropping stream frame"),
+        }
+    }
+
+    /// 等待写入线程将此前记录的帧写入文件
+    pub fn flush(&self) -> Result<()> {
+        let (ack, done) = mpsc::channel();
+        let stopped = || MarketStoreError::Connection("Recorder writer has stopped".to_string());
+        self.sender.send(RecorderCommand::Flush(ack)).map_err(|_| stopped())?;
+        done.recv().map_err(|_| stopped())??;
+        Ok(())
+    }
+}
+
+/// 写入线程：阻塞等待下一帧，写完队列中已有的帧后刷到文件
+fn write_frames(mut writer: BufWriter<File>, receiver: mpsc::Receiver<RecorderCommand>) {
+    let write_frame = |writer: &mut BufWriter<File>, frame: &RecordedFrame| {
+        writer.write_all(&frame.received_at.to_le_bytes())?;
+        writer.write_all(&(frame.data.len() as u32).to_le_bytes())?;
+        writer.write_all(&frame.data)
+    };
+
+    while let Ok(command) = receiver.recv() {
+        let mut acks = Vec::new();
+        let mut result = Ok(());
+        for command in std::iter::once(command).chain(receiver.try_iter()) {
+            match command {
+                RecorderCommand::Frame(frame) => {
+                    if result.is_ok() {
+                        result = write_frame(&mut writer, &frame);
+                    }
+                }
+                RecorderCommand::Flush(ack) => acks.push(ack),
+            }
+        }
+        let result = result.and_then(|_| writer.flush());
+        if let Err(e) = &result {
+            tracing::warn!("Failed to record stream frames: {}", e);
+        }
+        for ack in acks {
+            let _ = ack.send(result.as_ref().map(|_| ()).map_err(|e| std::io::Error::new(e.kind(), e.to_string())));
+        }
+    }
+}
+
+/// 读取录制文件；末尾不完整的帧（录制进程异常退出）会被忽略
+pub fn read_recording<P: AsRef<Path>>(path: P) -> Result<Vec<RecordedFrame>> {
+    let mut buf = Vec::new();
+    File::open(path)?.read_to_end(&mut buf)?;
+
+    if buf.len() < RECORDING_MAGIC.len() || &buf[..RECORDING_MAGIC.len()] != RECORDING_MAGIC {
+        return Err(MarketStoreError::InvalidData("Not a stream recording file".to_string()));
+    }
+
+    let mut frames = Vec::new();
+    let mut pos = RECORDING_MAGIC.len();
+    while pos + 12 <= buf.len() {
+        let received_at = i64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap());
+        let len = u32::from_le_bytes(buf[pos + 8..pos + 12].try_into().unwrap()) as usize;
+        if pos + 12 + len > buf.len() {
+            break;
+        }
+        frames.push(RecordedFrame {
+            received_at,
+            data: buf[pos + 12..pos + 12 + len].to_vec(),
+        });
+        pos += 12 + len;
+    }
+    if pos != buf.len() {
+        tracing::warn!("Ignoring {} trailing bytes of truncated recording", buf.len() - pos);
+    }
+    Ok(frames)
+}
+
+/// 回放速度
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReplaySpeed {
+    /// 按录制时的帧间隔回放
+    #[default]
+    RealTime,
+    /// 按倍速回放，如 `Accelerated(10.0)` 表示帧间隔缩短为十分之一
+    Accelerated(f64),
+    /// 不等待，尽快发送
+    AsFastAsPossible,
+}
+
+impl ReplaySpeed {
+    fn delay(&self, gap_nanos: i64) -> Duration {
+        let gap = Duration::from_nanos(gap_nanos.max(0) as u64);
+        match self {
+            ReplaySpeed::RealTime => gap,
+            ReplaySpeed::Accelerated(factor) if *factor > 0.0 => gap.div_f64(*factor),
+            ReplaySpeed::Accelerated(_) | ReplaySpeed::AsFastAsPossible => Duration::ZERO,
+        }
+    }
+}
+
+/// 在本地WebSocket端口上按服务器协议回放录制文件
+///
+/// 回放服务器与真实服务器一样校验并回显订阅，只推送匹配订阅模式的帧，回放结束后关闭连接，
+/// 因此 `WebSocketClient`、`StreamHub` 等订阅API连接到 `url()` 即可离线测试handler。
+/// 每条连接都从头回放。
+pub struct StreamReplayer {
+    url: String,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl StreamReplayer {
+    /// 读取录制文件并启动回放服务器
+    pub async fn open<P: AsRef<Path>>(path: P, speed: ReplaySpeed) -> Result<Self> {
+        Self::from_frames(read_recording(path)?, speed).await
+    }
+
+    /// 回放内存中的帧
+    pub async fn from_frames(frames: Vec<RecordedFrame>, speed: ReplaySpeed) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("ws://{}", listener.local_addr()?);
+        let frames = Arc::new(frames);
+
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("Replay server accept failed: {}", e);
+                        return;
+                    }
+                };
+                let frames = frames.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = replay_connection(stream, &frames, speed).await {
+                        tracing::warn!("Replay connection failed: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { url, task })
+    }
+
+    /// 订阅API应连接的地址
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for StreamReplayer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn replay_connection(stream: TcpStream, frames: &[RecordedFrame], speed: ReplaySpeed) -> Result<()> {
+    let mut ws = tokio_tungstenite::accept_async(stream).await?;
+    let mut matcher: Option<StreamMatcher> = None;
+    let mut next = 0;
+    let mut next_at = tokio::time::Instant::now();
+
+    loop {
+        // 收到订阅后才开始推送
+        let sending = matcher.is_some() && next < frames.len();
+        tokio::select! {
+            msg = ws.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    let Ok(subscribe) = rmp_serde::from_slice::<SubscribeMessage>(&data) else {
+                        continue;
+                    };
+                    // 与服务器一致：空列表保持原订阅，非法模式返回错误消息
+                    if subscribe.streams.is_empty() {
+                        ws.send(Message::Binary(data)).await?;
+                        continue;
+                    }
+                    match StreamMatcher::from_patterns(&subscribe.streams) {
+                        Ok(updated) => {
+                            if matcher.is_none() {
+                                next_at = tokio::time::Instant::now();
+                            }
+                            matcher = Some(updated);
+                            ws.send(Message::Binary(data)).await?;
+                        }
+                        Err(e) => {
+                            let error = ErrorMessage { error: e.to_string() };
+                            ws.send(Message::Binary(rmp_serde::to_vec_named(&error)?.into())).await?;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+            _ = tokio::time::sleep_until(next_at), if sending => {
+                let frame = &frames[next];
+                if should_replay(&frame.data, matcher.as_ref().unwrap()) {
+                    ws.send(Message::Binary(frame.data.clone().into())).await?;
+                }
+                next += 1;
+                if let Some(following) = frames.get(next) {
+                    next_at += speed.delay(following.received_at - frame.received_at);
+                }
+            }
+        }
+
+        if matcher.is_some() && next == frames.len() {
+            ws.close(None).await?;
+            // 等待客户端回应关闭帧
+            while let Some(Ok(_)) = ws.next().await {}
+            return Ok(());
+        }
+    }
+}
+
+/// 推送按订阅过滤；录制到的订阅回显与错误消息不回放；无法识别的帧原样回放
+fn should_replay(data: &[u8], matcher: &StreamMatcher) -> bool {
+    if let Ok(payload) = rmp_serde::from_slice::<StreamPayload<IgnoredAny>>(data) {
+        return matcher.is_match(&payload.key);
+    }
+    rmp_serde::from_slice::<SubscribeMessage>(data).is_err() && rmp_serde::from_slice::<ErrorMessage>(data).is_err()
+}
@@ -0,0 +1,237 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::task::{Context, Poll};
+use futures::Stream;
+use tokio::sync::{mpsc, oneshot};
+use tonic::transport::Channel;
+use crate::{
+    error::{MarketStoreError, Result},
+    client::{ConnectionState, ReconnectPolicy},
+    client::grpc_client::proto::{replication_client::ReplicationClient as ProtoReplicationClient, GetWalStreamRequest},
+    storage::TransactionGroup,
+};
+
+/// 主节点推送的一个序列化事务组（Transaction Group）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalMessage {
+    /// 事务组ID，即序列化数据开头的 `int64`
+    pub transaction_group_id: i64,
+    /// `GetWALStreamResponse.transaction_group` 原始字节
+    pub transaction_group: Vec<u8>,
+}
+
+impl WalMessage {
+    /// 解码为结构化的事务组
+    pub fn decode(&self) -> Result<TransactionGroup> {
+        TransactionGroup::decode(&self.transaction_group)
+    }
+}
+
+/// `ReplicationStream` 产出的事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicationEvent {
+    TransactionGroup(WalMessage),
+    /// 连接状态变化；服务器不支持断点续传，断线期间的事务组不会重发
+    State(ConnectionState),
+}
+
+/// 已消费的位置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicationPosition {
+    /// 最后交付的事务组ID
+    pub last_transaction_group_id: Option<i64>,
+    /// 本次运行交付的事务组数量
+    pub delivered: u64,
+    /// 因ID不大于当前位置而跳过的事务组数量
+    pub skipped: u64,
+}
+
+/// 订阅主节点 `Replication.GetWALStream` 的客户端
+pub struct ReplicationClient {
+    addr: String,
+    policy: ReconnectPolicy,
+    start_after: Option<i64>,
+}
+
+impl ReplicationClient {
+    pub fn new(addr: String, policy: ReconnectPolicy) -> Self {
+        Self {
+            addr,
+            policy,
+            start_after: None,
+        }
+    }
+
+    /// 从检查点恢复：ID不大于 `transaction_group_id` 的事务组会被跳过
+    pub fn with_position(mut self, transaction_group_id: i64) -> Self {
+        self.start_after = Some(transaction_group_id);
+        self
+    }
+
+    /// 在后台连接并以 `Stream` 形式交付事务组；缓冲区满时等待消费者，不丢弃WAL数据
+    pub fn stream(self, buffer: usize) -> ReplicationStream {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let position = Arc::new(StdMutex::new(ReplicationPosition {
+            last_transaction_group_id: self.start_after,
+            ..Default::default()
+        }));
+
+        let task = tokio::spawn(self.run(sender, position.clone(), cancel_rx));
+
+        ReplicationStream {
+            receiver,
+            position,
+            cancel: Some(cancel_tx),
+            task: Some(task),
+        }
+    }
+
+    async fn run(
+        self,
+        sender: mpsc::Sender<Result<ReplicationEvent>>,
+        position: Arc<StdMutex<ReplicationPosition>>,
+        mut cancel: oneshot::Receiver<()>,
+    ) {
+        let state = |state| Ok(ReplicationEvent::State(state));
+        let mut attempt: u32 = 0;
+        // 已放入缓冲区的最后一个事务组ID，用于去重；消费位置在消费者取走消息时才前进
+        let mut enqueued = position.lock().unwrap().last_transaction_group_id;
+
+        if sender.send(state(ConnectionState::Connecting)).await.is_err() {
+            return;
+        }
+
+        loop {
+            let reason = tokio::select! {
+                result = self.consume(&sender, &position, &mut enqueued, &mut attempt) => match result {
+                    Ok(()) => return,
+                    Err(e) => e.to_string(),
+                },
+                _ = &mut cancel => {
+                    let _ = sender.send(state(ConnectionState::Closed)).await;
+                    return;
+                }
+            };
+
+            tracing::warn!("Replication stream disconnected: {}", reason);
+            if sender.send(state(ConnectionState::Disconnected { reason })).await.is_err() {
+                return;
+            }
+
+            attempt += 1;
+            if let Some(max_retries) = self.policy.max_retries {
+                if attempt > max_retries {
+                    let _ = sender.send(state(ConnectionState::Closed)).await;
+                    let _ = sender
+                        .send(Err(MarketStoreError::Connection(format!(
+                            "Gave up reconnecting after {} attempts", max_retries
+                        ))))
+                        .await;
+                    return;
+                }
+            }
+
+            let delay = self.policy.backoff(attempt);
+            if sender.send(state(ConnectionState::Reconnecting { attempt, delay })).await.is_err() {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = &mut cancel => {
+                    let _ = sender.send(state(ConnectionState::Closed)).await;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 连接并持续读取，返回 `Ok` 表示消费者已释放，返回错误表示需要重连
+    async fn consume(
+        &self,
+        sender: &mpsc::Sender<Result<ReplicationEvent>>,
+        position: &StdMutex<ReplicationPosition>,
+        enqueued: &mut Option<i64>,
+        attempt: &mut u32,
+    ) -> Result<()> {
+        let channel = Channel::from_shared(self.addr.clone())
+            .map_err(|e| MarketStoreError::Connection(e.to_string()))?
+            .connect()
+            .await?;
+        let mut stream = ProtoReplicationClient::new(channel)
+            .get_wal_stream(GetWalStreamRequest {})
+            .await?
+            .into_inner();
+
+        *attempt = 0;
+        tracing::info!("Connected to replication stream at {}", self.addr);
+        if sender.send(Ok(ReplicationEvent::State(ConnectionState::Connected))).await.is_err() {
+            return Ok(());
+        }
+
+        while let Some(response) = stream.message().await? {
+            let transaction_group = response.transaction_group;
+            let Some(id_bytes) = transaction_group.get(..8) else {
+                tracing::warn!("Skipping replication message of {} bytes without TGID", transaction_group.len());
+                continue;
+            };
+            let transaction_group_id = i64::from_le_bytes(id_bytes.try_into().unwrap());
+
+            if enqueued.is_some_and(|last| transaction_group_id <= last) {
+                tracing::debug!("Skipping already delivered transaction group {}", transaction_group_id);
+                position.lock().unwrap().skipped += 1;
+                continue;
+            }
+            *enqueued = Some(transaction_group_id);
+
+            let message = WalMessage { transaction_group_id, transaction_group };
+            if sender.send(Ok(ReplicationEvent::TransactionGroup(message))).await.is_err() {
+                return Ok(());
+            }
+        }
+
+        Err(MarketStoreError::Connection("Replication stream ended".to_string()))
+    }
+}
+
+/// 复制流，drop时断开连接
+pub struct ReplicationStream {
+    receiver: mpsc::Receiver<Result<ReplicationEvent>>,
+    position: Arc<StdMutex<ReplicationPosition>>,
+    cancel: Option<oneshot::Sender<()>>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl ReplicationStream {
+    /// 当前消费位置（只包含已被消费者取走的事务组），可持久化后通过 `ReplicationClient::with_position` 恢复
+    pub fn position(&self) -> ReplicationPosition {
+        self.position.lock().unwrap().clone()
+    }
+
+    /// 断开连接并等待后台任务结束
+    pub async fn close(mut self) -> Result<()> {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        // 释放接收端，避免后台任务阻塞在发送上
+        self.receiver.close();
+        if let Some(task) = self.task.take() {
+            task.await.map_err(|e| MarketStoreError::Connection(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Stream for ReplicationStream {
+    type Item = Result<ReplicationEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = self.receiver.poll_recv(cx);
+        if let Poll::Ready(Some(Ok(ReplicationEvent::TransactionGroup(message)))) = &item {
+            let mut position = self.position.lock().unwrap();
+            position.last_transaction_group_id = Some(message.transaction_group_id);
+            position.delivered += 1;
+        }
+        item
+    }
+}
@@ -0,0 +1,350 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{QueryRequest, OHLCVData, SymbolFormat, DataShape, NumpyMultiDataset, WriteRequest},
+    client::{GrpcClient, GrpcClientTrait},
+};
+
+/// 只读请求在副本之间的分配策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadStrategy {
+    /// 依次轮询健康副本
+    RoundRobin,
+    /// 选择最近探测延迟最低的健康副本
+    LeastLatency,
+}
+
+/// 主从路由配置
+#[derive(Debug, Clone)]
+pub struct RoutingConfig {
+    pub read_strategy: ReadStrategy,
+    /// 连续失败多少次后将副本剔除
+    pub failure_threshold: u32,
+    /// 被剔除的副本需要连续探测成功多少次才重新加入
+    pub recovery_threshold: u32,
+    /// 后台健康探测间隔
+    pub probe_interval: Duration,
+    /// 单次 ServerVersion 探测的超时时间
+    pub probe_timeout: Duration,
+    /// 没有健康副本时是否把读请求转发到主节点
+    pub fallback_to_primary: bool,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            read_strategy: ReadStrategy::RoundRobin,
+            failure_threshold: 3,
+            recovery_threshold: 2,
+            probe_interval: Duration::from_secs(5),
+            probe_timeout: Duration::from_secs(2),
+            fallback_to_primary: true,
+        }
+    }
+}
+
+/// 副本健康状态快照
+#[derive(Debug, Clone)]
+pub struct ReplicaStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub latency: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct NodeState {
+    healthy: bool,
+    /// 是否已有探测成功过，首次探测成功即加入
+    admitted: bool,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    latency: Option<Duration>,
+    last_probe: Option<Instant>,
+}
+
+struct ReplicaNode {
+    url: String,
+    client: GrpcClient,
+    state: StdMutex<NodeState>,
+}
+
+impl ReplicaNode {
+    fn new(url: String, client: GrpcClient) -> Self {
+        Self {
+            url,
+            client,
+            state: StdMutex::new(NodeState {
+                healthy: false,
+                admitted: false,
+                consecutive_failures: 0,
+                consecutive_successes: 0,
+                latency: None,
+                last_probe: None,
+            }),
+        }
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.state.lock().unwrap().healthy
+    }
+
+    fn latency(&self) -> Option<Duration> {
+        self.state.lock().unwrap().latency
+    }
+
+    fn record_success(&self, elapsed: Duration, config: &RoutingConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.consecutive_successes = state.consecutive_successes.saturating_add(1);
+        // 指数加权平均，避免单次抖动影响选择
+        state.latency = Some(match state.latency {
+            Some(prev) => prev.mul_f64(0.8) + elapsed.mul_f64(0.2),
+            None => elapsed,
+        });
+        if !state.admitted {
+            tracing::info!("Replica {} admitted after first successful probe", self.url);
+            state.admitted = true;
+            state.healthy = true;
+        } else if !state.healthy && state.consecutive_successes >= config.recovery_threshold {
+            tracing::info!("Replica {} readmitted after {} successful probes", self.url, state.consecutive_successes);
+            state.healthy = true;
+        }
+    }
+
+    fn record_failure(&self, config: &RoutingConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_successes = 0;
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.healthy && state.consecutive_failures >= config.failure_threshold {
+            tracing::warn!("Replica {} ejected after {} consecutive failures", self.url, state.consecutive_failures);
+            state.healthy = false;
+        }
+    }
+
+    /// 不健康且距上次探测超过 `probe_interval` 时返回true，并记下本次探测时间
+    fn claim_probe(&self, config: &RoutingConfig) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let due = !state.healthy && state.last_probe.is_none_or(|last| last.elapsed() >= config.probe_interval);
+        if due {
+            state.last_probe = Some(Instant::now());
+        }
+        due
+    }
+
+    fn status(&self) -> ReplicaStatus {
+        let state = self.state.lock().unwrap();
+        ReplicaStatus {
+            url: self.url.clone(),
+            healthy: state.healthy,
+            consecutive_failures: state.consecutive_failures,
+            latency: state.latency,
+        }
+    }
+}
+
+/// 主从路由客户端：写入/建表/删表发往主节点，查询在健康副本之间分配
+///
+/// 副本在首次探测成功前不参与读请求；被剔除的副本在读请求路径上按 `probe_interval` 于后台重新探测，
+/// 不依赖 `spawn_health_checker` 也能恢复。
+pub struct RoutedClient {
+    primary: GrpcClient,
+    replicas: Arc<Vec<ReplicaNode>>,
+    config: RoutingConfig,
+    next: AtomicUsize,
+}
+
+impl RoutedClient {
+    pub async fn connect(primary_url: String, replica_urls: Vec<String>, config: RoutingConfig) -> Result<Self> {
+        let primary = GrpcClient::connect(primary_url).await?;
+
+        // 副本延迟连接，启动时不可达的副本会在首次探测中被记为失败
+        let mut replicas = Vec::with_capacity(replica_urls.len());
+        for url in replica_urls {
+            let client = GrpcClient::connect_lazy(url.clone())?;
+            replicas.push(ReplicaNode::new(url, client));
+        }
+
+        let client = Self {
+            primary,
+            replicas: Arc::new(replicas),
+            config,
+            next: AtomicUsize::new(0),
+        };
+        client.probe_replicas().await;
+
+        Ok(client)
+    }
+
+    pub fn config(&self) -> &RoutingConfig {
+        &self.config
+    }
+
+    pub fn replica_status(&self) -> Vec<ReplicaStatus> {
+        self.replicas.iter().map(|node| node.status()).collect()
+    }
+
+    /// 立即对所有副本执行一次 ServerVersion 健康探测
+    pub async fn probe_replicas(&self) {
+        probe_nodes(self.replicas.iter(), &self.config).await;
+    }
+
+    /// 启动后台健康探测任务，按 `probe_interval` 周期剔除/恢复副本
+    pub fn spawn_health_checker(&self) -> tokio::task::JoinHandle<()> {
+        let replicas = self.replicas.clone();
+        let config = self.config.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.probe_interval);
+            loop {
+                interval.tick().await;
+                probe_nodes(replicas.iter(), &config).await;
+            }
+        })
+    }
+
+    /// 在后台重新探测到期的不健康副本，不阻塞当前读请求
+    fn reprobe_unhealthy(&self) {
+        let due: Vec<usize> = (0..self.replicas.len())
+            .filter(|idx| self.replicas[*idx].claim_probe(&self.config))
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+
+        let replicas = self.replicas.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            probe_nodes(due.iter().map(|idx| &replicas[*idx]), &config).await;
+        });
+    }
+
+    fn select_replica(&self, tried: &[usize]) -> Option<usize> {
+        let candidates: Vec<usize> = (0..self.replicas.len())
+            .filter(|idx| !tried.contains(idx) && self.replicas[*idx].is_healthy())
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        match self.config.read_strategy {
+            ReadStrategy::RoundRobin => {
+                let n = self.next.fetch_add(1, Ordering::Relaxed);
+                Some(candidates[n % candidates.len()])
+            }
+            ReadStrategy::LeastLatency => candidates
+                .into_iter()
+                .min_by_key(|idx| self.replicas[*idx].latency().unwrap_or(Duration::MAX)),
+        }
+    }
+
+    async fn route_read<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: Fn(GrpcClient) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T>> + Send,
+        T: Send,
+    {
+        if self.replicas.is_empty() {
+            return op(self.primary.clone()).await;
+        }
+
+        self.reprobe_unhealthy();
+        let mut tried = Vec::new();
+        while let Some(idx) = self.select_replica(&tried) {
+            tried.push(idx);
+            let node = &self.replicas[idx];
+            let start = Instant::now();
+            match op(node.client.clone()).await {
+                Ok(value) => {
+                    node.record_success(start.elapsed(), &self.config);
+                    return Ok(value);
+                }
+                Err(e) if is_node_failure(&e) => {
+                    tracing::warn!("Read on replica {} failed: {}", node.url, e);
+                    node.record_failure(&self.config);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if self.config.fallback_to_primary {
+            tracing::debug!("No healthy replica available, routing read to primary");
+            op(self.primary.clone()).await
+        } else {
+            Err(MarketStoreError::Connection("No healthy replica available".to_string()))
+        }
+    }
+}
+
+#[async_trait]
+impl GrpcClientTrait for RoutedClient {
+    async fn query(&mut self, request: QueryRequest) -> Result<NumpyMultiDataset> {
+        self.route_read(|mut client| {
+            let request = request.clone();
+            async move { client.query(request).await }
+        }).await
+    }
+
+    async fn write(&mut self, symbol: &str, timeframe: &str, attr_group: &str, data: Vec<OHLCVData>) -> Result<()> {
+        self.primary.write(symbol, timeframe, attr_group, data).await
+    }
+
+    async fn write_batch(&mut self, requests: Vec<WriteRequest>) -> Result<()> {
+        self.primary.write_batch(requests).await
+    }
+
+    async fn list_symbols(&mut self, format: SymbolFormat) -> Result<Vec<String>> {
+        self.route_read(|mut client| {
+            let format = format.clone();
+            async move { client.list_symbols(format).await }
+        }).await
+    }
+
+    async fn create_bucket(&mut self, symbol: &str, timeframe: &str, attr_group: &str, data_shapes: Vec<DataShape>) -> Result<()> {
+        self.primary.create_bucket(symbol, timeframe, attr_group, data_shapes).await
+    }
+
+    async fn destroy_bucket(&mut self, symbol: &str, timeframe: &str, attr_group: &str) -> Result<()> {
+        self.primary.destroy_bucket(symbol, timeframe, attr_group).await
+    }
+
+    async fn server_version(&mut self) -> Result<String> {
+        self.primary.server_version().await
+    }
+}
+
+async fn probe_nodes<'a>(replicas: impl Iterator<Item = &'a ReplicaNode>, config: &RoutingConfig) {
+    let probes = replicas.map(|node| async move {
+        node.state.lock().unwrap().last_probe = Some(Instant::now());
+        let mut client = node.client.clone();
+        let start = Instant::now();
+        match tokio::time::timeout(config.probe_timeout, client.server_version()).await {
+            Ok(Ok(_)) => node.record_success(start.elapsed(), config),
+            Ok(Err(e)) => {
+                tracing::debug!("Health probe on replica {} failed: {}", node.url, e);
+                node.record_failure(config);
+            }
+            Err(_) => {
+                tracing::debug!("Health probe on replica {} timed out", node.url);
+                node.record_failure(config);
+            }
+        }
+    });
+    futures::future::join_all(probes).await;
+}
+
+/// 判断错误是否由节点不可用引起（需要计入失败并换节点重试）；`Unknown` 是服务器返回的应用错误，不计入
+pub(crate) fn is_node_failure(err: &MarketStoreError) -> bool {
+    match err {
+        MarketStoreError::Transport(_) | MarketStoreError::Connection(_) | MarketStoreError::Timeout(_) => true,
+        MarketStoreError::Grpc(status) => matches!(
+            status.code(),
+            tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+        ),
+        _ => false,
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::{Stream, StreamExt};
+use tokio::sync::{mpsc, oneshot};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{StreamData, StreamPayload},
+    client::{StreamEvent, SubscriptionStream},
+};
+
+/// `subscribe_with_snapshot` 产出的事件
+#[derive(Debug, Clone)]
+pub enum SnapshotEvent {
+    /// 历史快照中的一行，按key分组、组内按epoch升序
+    Snapshot(StreamPayload),
+    /// 快照已全部发出，之后只有实时事件
+    SnapshotEnd,
+    /// 实时事件；推送已拆分为单行并去除与快照重复的部分
+    Live(StreamEvent),
+}
+
+/// 先快照后实时的订阅流，drop时取消订阅并关闭连接
+pub struct SnapshotStream {
+    receiver: mpsc::Receiver<Result<SnapshotEvent>>,
+    cancel: Option<oneshot::Sender<()>>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl SnapshotStream {
+    /// 在后台按顺序发出快照、快照期间缓冲的实时数据以及后续实时数据
+    ///
+    /// `snapshot` 为每个key按epoch升序的历史行，`pending` 为查询快照期间收到的实时事件。
+    pub(crate) fn spawn(
+        keys: Vec<String>,
+        mut snapshot: HashMap<String, Vec<StreamData>>,
+        pending: Vec<StreamEvent>,
+        mut live: SubscriptionStream,
+        buffer: usize,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
+
+        let task = tokio::spawn(async move {
+            let mut dedup = LiveDeduplicator::default();
+
+            for key in &keys {
+                for data in snapshot.remove(key).unwrap_or_default() {
+                    let payload = StreamPayload { key: key.clone(), data };
+                    dedup.observe(&payload);
+                    if sender.send(Ok(SnapshotEvent::Snapshot(payload))).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            if sender.send(Ok(SnapshotEvent::SnapshotEnd)).await.is_err() {
+                return;
+            }
+
+            // 快照期间到达的实时数据按epoch排序后再去重
+            let mut buffered: Vec<StreamEvent> = Vec::new();
+            let mut rows: Vec<StreamPayload> = Vec::new();
+            for event in pending {
+                match event {
+                    StreamEvent::Payload(payload) => rows.extend(split_rows(payload)),
+                    other => buffered.push(other),
+                }
+            }
+            rows.sort_by_key(|row| row_epoch(&row.data).unwrap_or(i64::MIN));
+            let events = buffered
+                .into_iter()
+                .chain(rows.into_iter().filter(|row| dedup.accept(row)).map(StreamEvent::Payload));
+            for event in events {
+                if sender.send(Ok(SnapshotEvent::Live(event))).await.is_err() {
+                    return;
+                }
+            }
+
+            loop {
+                let event = tokio::select! {
+                    event = live.next() => event,
+                    _ = &mut cancel_rx => break,
+                };
+                let events = match event {
+                    Some(Ok(StreamEvent::Payload(payload))) => split_rows(payload)
+                        .into_iter()
+                        .filter(|row| dedup.accept(row))
+                        .map(|row| Ok(SnapshotEvent::Live(StreamEvent::Payload(row))))
+                        .collect(),
+                    Some(Ok(other)) => vec![Ok(SnapshotEvent::Live(other))],
+                    Some(Err(e)) => vec![Err(e)],
+                    None => return,
+                };
+                for event in events {
+                    if sender.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if let Err(e) = live.close().await {
+                tracing::warn!("Failed to close snapshot subscription: {}", e);
+            }
+        });
+
+        Self {
+            receiver,
+            cancel: Some(cancel_tx),
+            task: Some(task),
+        }
+    }
+
+    /// 取消订阅并等待关闭握手完成
+    pub async fn close(mut self) -> Result<()> {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        if let Some(task) = self.task.take() {
+            task.await.map_err(|e| MarketStoreError::WebSocket(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Stream for SnapshotStream {
+    type Item = Result<SnapshotEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// 记录每个key最后发出的行，丢弃更早的行以及与最后一行完全相同的行
+#[derive(Default)]
+struct LiveDeduplicator {
+    last_rows: HashMap<String, (i64, StreamData)>,
+}
+
+impl LiveDeduplicator {
+    fn observe(&mut self, row: &StreamPayload) {
+        if let Some(epoch) = row_epoch(&row.data) {
+            self.last_rows.insert(row.key.clone(), (epoch, row.data.clone()));
+        }
+    }
+
+    fn accept(&mut self, row: &StreamPayload) -> bool {
+        let Some(epoch) = row_epoch(&row.data) else {
+            return true;
+        };
+        if let Some((last_epoch, last_data)) = self.last_rows.get(&row.key) {
+            // 同一epoch的更新（如未收盘的bar）仍需发出，完全相同的重复行则丢弃
+            if epoch < *last_epoch || (epoch == *last_epoch && same_row(last_data, &row.data)) {
+                return false;
+            }
+        }
+        self.observe(row);
+        true
+    }
+}
+
+fn row_epoch(data: &StreamData) -> Option<i64> {
+    data.get("Epoch").and_then(|value| value.as_i64())
+}
+
+/// 快照经JSON转换得到的整数与推送中的浮点数可能类型不同，按数值比较
+fn same_row(a: &StreamData, b: &StreamData) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|(column, value)| match (value, b.get(column)) {
+            (serde_json::Value::Number(x), Some(serde_json::Value::Number(y))) => x.as_f64() == y.as_f64(),
+            (x, Some(y)) => x == y,
+            (_, None) => false,
+        })
+}
+
+/// 将列为数组的多行推送拆分为单行推送，标量列在每行中重复
+fn split_rows(payload: StreamPayload) -> Vec<StreamPayload> {
+    let rows = payload
+        .data
+        .values()
+        .filter_map(|value| value.as_array().map(|values| values.len()))
+        .max();
+    let Some(rows) = rows else {
+        return vec![payload];
+    };
+
+    (0..rows)
+        .map(|row| StreamPayload {
+            key: payload.key.clone(),
+            data: payload
+                .data
+                .iter()
+                .map(|(column, value)| {
+                    let value = match value.as_array() {
+                        Some(values) => values.get(row).cloned().unwrap_or(serde_json::Value::Null),
+                        None => value.clone(),
+                    };
+                    (column.clone(), value)
+                })
+                .collect(),
+        })
+        .collect()
+}
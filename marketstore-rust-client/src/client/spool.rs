@@ -0,0 +1,441 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{DataShape, NumpyMultiDataset, OHLCVData, QueryRequest, SymbolFormat, WriteRequest},
+    client::{routed_client::is_node_failure, GrpcClient, GrpcClientTrait},
+    utils::{bucket_key, split_bucket_key},
+};
+
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_SUFFIX: &str = ".spool";
+/// 被服务器拒绝的记录追加到该文件，格式与段文件相同
+const DEAD_LETTER_FILE: &str = "dead-letter.spool";
+/// 含损坏记录的段重放有效部分后移入该子目录
+const QUARANTINE_DIR: &str = "quarantine";
+/// 记录头：4字节负载长度 + 16字节负载MD5
+const RECORD_HEADER_SIZE: usize = 4 + 16;
+
+/// 追加写入后何时调用fsync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// 每次追加后fsync，进程或机器崩溃都不丢数据
+    #[default]
+    Always,
+    /// 仅在切换段文件时fsync，机器崩溃可能丢失当前段未落盘的部分
+    OnRotate,
+    /// 交给操作系统，仅保证进程崩溃不丢数据
+    Never,
+}
+
+/// 本地写入暂存配置
+#[derive(Debug, Clone)]
+pub struct SpoolConfig {
+    pub dir: PathBuf,
+    /// 所有段文件的总大小上限，超过时追加失败
+    pub max_bytes: u64,
+    /// 当前段达到该大小后切换到新段
+    pub segment_bytes: u64,
+    pub fsync: FsyncPolicy,
+}
+
+impl SpoolConfig {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_bytes: 1024 * 1024 * 1024,
+            segment_bytes: 16 * 1024 * 1024,
+            fsync: FsyncPolicy::default(),
+        }
+    }
+}
+
+/// 暂存的一次写入
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpooledWrite {
+    /// bucket key，如 `AAPL/1Min/OHLCV`
+    pub key: String,
+    pub rows: Vec<OHLCVData>,
+}
+
+impl SpooledWrite {
+    pub fn into_request(self) -> Result<WriteRequest> {
+        let (symbol, timeframe, attr_group) = split_bucket_key(&self.key)?;
+        Ok(WriteRequest::new(symbol, timeframe, attr_group, self.rows))
+    }
+}
+
+impl From<&WriteRequest> for SpooledWrite {
+    fn from(request: &WriteRequest) -> Self {
+        Self {
+            key: bucket_key(&request.symbol, &request.timeframe, &request.attr_group),
+            rows: request.data.clone(),
+        }
+    }
+}
+
+/// 段文件概况
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentInfo {
+    pub path: PathBuf,
+    pub sequence: u64,
+    pub bytes: u64,
+    pub records: usize,
+    pub rows: usize,
+    /// 末尾无法解析的字节数（写入中途崩溃留下的残缺记录）
+    pub torn_bytes: u64,
+}
+
+/// 磁盘上按顺序追加的写入暂存，服务器不可用时保存写入，恢复后按原顺序重放
+///
+/// 数据保存在 `segment-<序号>.spool` 段文件中，每条记录为长度、MD5与JSON负载。
+/// 打开时截掉最后一个段末尾的残缺记录。重放按段逐条进行，一个段处理完后才删除，
+/// 重放中途因节点不可用失败时该段会被完整重放，即至少写入一次；同一Epoch的重复写入在服务器端相互覆盖。
+/// 服务器拒绝的记录移入 `dead-letter.spool`，不阻塞后续重放；含损坏记录的段在重放损坏点之前的记录后
+/// 整体移入 `quarantine/` 目录，留待人工处理。
+#[derive(Debug)]
+pub struct Spool {
+    config: SpoolConfig,
+    /// 序号 -> 段文件大小
+    segments: BTreeMap<u64, u64>,
+    /// 追加中的段
+    active: Option<(u64, File)>,
+}
+
+impl Spool {
+    pub fn open(config: SpoolConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        let mut segments = BTreeMap::new();
+        for entry in std::fs::read_dir(&config.dir)? {
+            let entry = entry?;
+            if let Some(sequence) = parse_segment_name(&entry.file_name().to_string_lossy()) {
+                segments.insert(sequence, entry.metadata()?.len());
+            }
+        }
+
+        let mut spool = Self { config, segments, active: None };
+        if let Some((&sequence, &bytes)) = spool.segments.iter().next_back() {
+            let path = spool.segment_path(sequence);
+            let (_, valid) = read_records(&std::fs::read(&path)?);
+            if (valid as u64) < bytes {
+                tracing::warn!("Truncating {} torn bytes at the end of {}", bytes - valid as u64, path.display());
+                let file = OpenOptions::new().write(true).open(&path)?;
+                file.set_len(valid as u64)?;
+                file.sync_all()?;
+                spool.segments.insert(sequence, valid as u64);
+            }
+        }
+        Ok(spool)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.config.dir
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.values().all(|&bytes| bytes == 0)
+    }
+
+    /// 所有段文件的总字节数
+    pub fn total_bytes(&self) -> u64 {
+        self.segments.values().sum()
+    }
+
+    /// 追加写入；超过 `max_bytes` 时整体失败，不写入任何记录
+    pub fn append(&mut self, writes: &[SpooledWrite]) -> Result<()> {
+        let buf = encode_records(writes)?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        if self.total_bytes() + buf.len() as u64 > self.config.max_bytes {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Spool {} is full: {} of {} bytes used",
+                self.config.dir.display(),
+                self.total_bytes(),
+                self.config.max_bytes
+            )));
+        }
+
+        let active_full = self.active.as_ref().is_some_and(|(sequence, _)| self.segments[sequence] >= self.config.segment_bytes);
+        if active_full {
+            self.rotate()?;
+        }
+        if self.active.is_none() {
+            self.open_active()?;
+        }
+
+        let (sequence, file) = self.active.as_mut().unwrap();
+        file.write_all(&buf)?;
+        if self.config.fsync == FsyncPolicy::Always {
+            file.sync_data()?;
+        }
+        *self.segments.entry(*sequence).or_default() += buf.len() as u64;
+        Ok(())
+    }
+
+    /// 按顺序列出段文件
+    pub fn segments(&self) -> Result<Vec<SegmentInfo>> {
+        self.segments
+            .keys()
+            .map(|&sequence| {
+                let path = self.segment_path(sequence);
+                let buf = std::fs::read(&path)?;
+                let (writes, valid) = read_records(&buf);
+                Ok(SegmentInfo {
+                    sequence,
+                    bytes: buf.len() as u64,
+                    records: writes.len(),
+                    rows: writes.iter().map(|write| write.rows.len()).sum(),
+                    torn_bytes: (buf.len() - valid) as u64,
+                    path,
+                })
+            })
+            .collect()
+    }
+
+    /// 按追加顺序读出全部暂存的写入
+    pub fn read_all(&self) -> Result<Vec<SpooledWrite>> {
+        let mut writes = Vec::new();
+        for &sequence in self.segments.keys() {
+            writes.extend(read_records(&std::fs::read(self.segment_path(sequence))?).0);
+        }
+        Ok(writes)
+    }
+
+    /// 服务器拒绝而移入死信文件的写入
+    pub fn dead_letters(&self) -> Result<Vec<SpooledWrite>> {
+        match std::fs::read(self.config.dir.join(DEAD_LETTER_FILE)) {
+            Ok(buf) => Ok(read_records(&buf).0),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 因记录损坏被隔离的段文件
+    pub fn quarantined(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.config.dir.join(QUARANTINE_DIR);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut paths = Vec::new();
+        for entry in entries {
+            paths.push(entry?.path());
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// 服务器 `ServerVersion` 正常时按顺序逐条重放全部段，返回写入的行数
+    ///
+    /// 节点不可用时返回错误，当前段及之后的段保留；服务器拒绝的记录移入死信文件后继续。
+    pub async fn drain<C: GrpcClientTrait + Send>(&mut self, client: &mut C) -> Result<usize> {
+        client.server_version().await?;
+
+        let mut rows = 0;
+        for sequence in self.segments.keys().copied().collect::<Vec<_>>() {
+            let path = self.segment_path(sequence);
+            let buf = std::fs::read(&path)?;
+            let (writes, valid) = read_records(&buf);
+
+            let mut count = 0;
+            let mut rejected = Vec::new();
+            for write in writes {
+                let result = match write.clone().into_request() {
+                    Ok(request) => client.write_batch(vec![request]).await,
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(()) => count += write.rows.len(),
+                    Err(e) if is_node_failure(&e) => return Err(e),
+                    Err(e) => {
+                        tracing::error!("Server rejected spooled write for {}, moving it to dead letters: {}", write.key, e);
+                        rejected.push(write);
+                    }
+                }
+            }
+            self.append_dead_letters(&rejected)?;
+
+            if self.active.as_ref().is_some_and(|(active, _)| *active == sequence) {
+                self.active = None;
+            }
+            if valid < buf.len() {
+                let quarantine = self.config.dir.join(QUARANTINE_DIR);
+                std::fs::create_dir_all(&quarantine)?;
+                let target = quarantine.join(path.file_name().unwrap_or_default());
+                tracing::error!(
+                    "{} has {} unreadable bytes after offset {}, moving it to {}",
+                    path.display(),
+                    buf.len() - valid,
+                    valid,
+                    target.display()
+                );
+                std::fs::rename(&path, &target)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+            self.segments.remove(&sequence);
+            tracing::info!("Replayed {} spooled rows from {}", count, path.display());
+            rows += count;
+        }
+        Ok(rows)
+    }
+
+    fn append_dead_letters(&self, writes: &[SpooledWrite]) -> Result<()> {
+        if writes.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().append(true).create(true).open(self.config.dir.join(DEAD_LETTER_FILE))?;
+        file.write_all(&encode_records(writes)?)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        if let Some((_, file)) = self.active.take() {
+            if self.config.fsync != FsyncPolicy::Never {
+                file.sync_all()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 新建下一个序号的段，目录项fsync后才追加
+    fn open_active(&mut self) -> Result<()> {
+        let sequence = self.segments.keys().next_back().map_or(0, |last| last + 1);
+        let file = OpenOptions::new().append(true).create_new(true).open(self.segment_path(sequence))?;
+        if self.config.fsync != FsyncPolicy::Never {
+            File::open(&self.config.dir)?.sync_all()?;
+        }
+        self.segments.insert(sequence, 0);
+        self.active = Some((sequence, file));
+        Ok(())
+    }
+
+    fn segment_path(&self, sequence: u64) -> PathBuf {
+        self.config.dir.join(format!("{}{:020}{}", SEGMENT_PREFIX, sequence, SEGMENT_SUFFIX))
+    }
+}
+
+fn parse_segment_name(name: &str) -> Option<u64> {
+    name.strip_prefix(SEGMENT_PREFIX)?.strip_suffix(SEGMENT_SUFFIX)?.parse().ok()
+}
+
+/// 编码为长度、MD5与JSON负载组成的记录
+fn encode_records(writes: &[SpooledWrite]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    for write in writes {
+        let payload = serde_json::to_vec(write)?;
+        buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&md5::compute(&payload).0);
+        buf.extend_from_slice(&payload);
+    }
+    Ok(buf)
+}
+
+/// 解析段文件中的记录，返回记录与有效部分的长度；遇到残缺或校验失败的记录即停止
+fn read_records(buf: &[u8]) -> (Vec<SpooledWrite>, usize) {
+    let mut writes = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = buf.get(offset..offset + RECORD_HEADER_SIZE) {
+        let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let start = offset + RECORD_HEADER_SIZE;
+        let Some(payload) = buf.get(start..start + length) else {
+            break;
+        };
+        if md5::compute(payload).0 != header[4..] {
+            break;
+        }
+        match serde_json::from_slice(payload) {
+            Ok(write) => writes.push(write),
+            Err(_) => break,
+        }
+        offset = start + length;
+    }
+    (writes, offset)
+}
+
+/// 写入失败时暂存到本地的客户端
+///
+/// 写入因节点不可用失败时保存到 `Spool` 并返回成功；暂存非空时每次写入前先尝试重放，
+/// 重放未完成时新的写入也进入暂存，保证按原顺序到达服务器。其它请求直接转发。
+pub struct SpoolingClient<C = GrpcClient> {
+    client: C,
+    spool: Spool,
+}
+
+impl<C: GrpcClientTrait + Send> SpoolingClient<C> {
+    pub fn new(client: C, spool: Spool) -> Self {
+        Self { client, spool }
+    }
+
+    pub fn spool(&self) -> &Spool {
+        &self.spool
+    }
+
+    /// 服务器恢复时重放暂存的写入，返回写入的行数；可由调用方定期调用
+    pub async fn replay(&mut self) -> Result<usize> {
+        if self.spool.is_empty() {
+            return Ok(0);
+        }
+        self.spool.drain(&mut self.client).await
+    }
+
+    fn spool_requests(&mut self, requests: &[WriteRequest]) -> Result<()> {
+        let writes: Vec<SpooledWrite> = requests.iter().map(SpooledWrite::from).collect();
+        self.spool.append(&writes)
+    }
+}
+
+#[async_trait]
+impl<C: GrpcClientTrait + Send> GrpcClientTrait for SpoolingClient<C> {
+    async fn query(&mut self, request: QueryRequest) -> Result<NumpyMultiDataset> {
+        self.client.query(request).await
+    }
+
+    async fn write(&mut self, symbol: &str, timeframe: &str, attr_group: &str, data: Vec<OHLCVData>) -> Result<()> {
+        self.write_batch(vec![WriteRequest::new(symbol, timeframe, attr_group, data)]).await
+    }
+
+    async fn write_batch(&mut self, requests: Vec<WriteRequest>) -> Result<()> {
+        if !self.spool.is_empty() {
+            match self.replay().await {
+                Ok(_) => {}
+                Err(e) if is_node_failure(&e) => {
+                    tracing::debug!("Server still unavailable, spooling write: {}", e);
+                    return self.spool_requests(&requests);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        match self.client.write_batch(requests.clone()).await {
+            Err(e) if is_node_failure(&e) => {
+                tracing::warn!("Write failed, spooling {} requests to {}: {}", requests.len(), self.spool.dir().display(), e);
+                self.spool_requests(&requests)
+            }
+            result => result,
+        }
+    }
+
+    async fn list_symbols(&mut self, format: SymbolFormat) -> Result<Vec<String>> {
+        self.client.list_symbols(format).await
+    }
+
+    async fn create_bucket(&mut self, symbol: &str, timeframe: &str, attr_group: &str, data_shapes: Vec<DataShape>) -> Result<()> {
+        self.client.create_bucket(symbol, timeframe, attr_group, data_shapes).await
+    }
+
+    async fn destroy_bucket(&mut self, symbol: &str, timeframe: &str, attr_group: &str) -> Result<()> {
+        self.client.destroy_bucket(symbol, timeframe, attr_group).await
+    }
+
+    async fn server_version(&mut self) -> Result<String> {
+        self.client.server_version().await
+    }
+}
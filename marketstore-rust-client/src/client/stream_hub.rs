@@ -0,0 +1,461 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::task::{Context, Poll, Waker};
+use futures::Stream;
+use serde::de::DeserializeOwned;
+use tokio::sync::{mpsc, oneshot, Notify, Semaphore};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{StreamData, StreamMatcher, SubscribeMessage},
+    client::{LoopExit, StreamEvent, SubscribeCommand, WebSocketClient, SUBSCRIBE_ACK_TIMEOUT},
+};
+
+/// 已从连接读取但尚未分发完的帧数上限，达到后暂停读取socket
+const HUB_FRAME_BUFFER: usize = 1024;
+
+/// 单个消费者缓冲区已满时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackpressurePolicy {
+    /// 等待消费者腾出空间；分发会暂停，其它消费者也随之等待，
+    /// 已读取的帧达到上限后停止读取连接，由TCP将背压传给服务器；停止读取期间心跳超时暂停计时
+    Block,
+    /// 丢弃缓冲区中最旧的一条，写入新数据
+    DropOldest,
+    /// 丢弃新数据
+    #[default]
+    DropNewest,
+}
+
+/// 多个消费者共享一条WebSocket连接的订阅中心
+///
+/// 服务器端订阅集合始终是全部消费者模式的并集，推送按各消费者的模式在本地分发。
+/// hub被drop时取消订阅并关闭所有消费者。
+pub struct StreamHub<T = StreamData> {
+    shared: Arc<HubShared<T>>,
+    cancel: Option<oneshot::Sender<()>>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+struct HubShared<T> {
+    registry: StdMutex<HubRegistry<T>>,
+    commands: mpsc::UnboundedSender<SubscribeCommand>,
+    next_id: AtomicU64,
+    // 服务器已确认的订阅集合
+    acked: Arc<StdMutex<AckedStreams>>,
+}
+
+struct HubRegistry<T> {
+    consumers: BTreeMap<u64, Consumer<T>>,
+    // 最近一次发送给服务器的订阅集合及其序号
+    requested: Vec<String>,
+    sequence: u64,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct AckedStreams {
+    sequence: u64,
+    streams: Vec<String>,
+}
+
+impl AckedStreams {
+    /// 确认可能乱序到达，只接受比当前更新的订阅集合
+    fn apply(&mut self, sequence: u64, streams: Vec<String>) {
+        if sequence > self.sequence {
+            self.sequence = sequence;
+            self.streams = streams;
+        }
+    }
+}
+
+/// 已发送、等待服务器确认的订阅变更
+struct PendingSync {
+    sequence: u64,
+    streams: Vec<String>,
+    ack: oneshot::Receiver<Result<()>>,
+}
+
+struct Consumer<T> {
+    matcher: StreamMatcher,
+    queue: Arc<ConsumerQueue<T>>,
+}
+
+impl<T> StreamHub<T>
+where
+    T: DeserializeOwned + Clone + Send + 'static,
+{
+    /// 建立共享连接，订阅在第一个消费者加入时发送
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self::from_client(WebSocketClient::connect(url).await?))
+    }
+
+    /// 基于已建立的连接创建hub，连接的心跳配置继续生效
+    pub fn from_client(ws_client: WebSocketClient) -> Self {
+        let (command_tx, mut command_rx) = mpsc::unbounded_channel();
+        let (cancel_tx, mut cancel_rx) = oneshot::channel();
+        let shared = Arc::new(HubShared {
+            registry: StdMutex::new(HubRegistry {
+                consumers: BTreeMap::new(),
+                requested: Vec::new(),
+                sequence: 0,
+                closed: false,
+            }),
+            commands: command_tx,
+            next_id: AtomicU64::new(0),
+            acked: Arc::new(StdMutex::new(AckedStreams::default())),
+        });
+
+        // 连接只在有许可时读取下一帧，分发完一个事件后归还许可，因此通道中的事件不会超过容量
+        let gate = Arc::new(Semaphore::new(HUB_FRAME_BUFFER));
+        let mut ws_client = ws_client.with_frame_gate(gate.clone());
+        let task_shared = shared.clone();
+        let task = tokio::spawn(async move {
+            let (event_tx, event_rx) = mpsc::channel(HUB_FRAME_BUFFER);
+            let reader = async {
+                let mut handler = |event| {
+                    if let Err(mpsc::error::TrySendError::Full(_)) = event_tx.try_send(event) {
+                        tracing::warn!("Stream hub dispatch queue is full, dropping event");
+                    }
+                    Ok(())
+                };
+                let result = ws_client.run_loop(&mut handler, Some(&mut cancel_rx), Some(&mut command_rx)).await;
+                drop(event_tx);
+                // 主动取消时不再投递剩余推送，避免阻塞型消费者拖住关闭
+                if let Ok(LoopExit::Cancelled) = result {
+                    task_shared.close_all(|| None);
+                }
+                result
+            };
+            let (result, ()) = tokio::join!(reader, task_shared.dispatch(event_rx, &gate));
+
+            match result {
+                Ok(exit) => {
+                    tracing::info!("Stream hub connection finished: {:?}", exit);
+                    task_shared.close_all(|| None);
+                }
+                Err(e) => {
+                    tracing::warn!("Stream hub connection failed: {}", e);
+                    let message = e.to_string();
+                    task_shared.close_all(|| {
+                        Some(MarketStoreError::Connection(format!("Stream hub disconnected: {}", message)))
+                    });
+                }
+            }
+        });
+
+        Self {
+            shared,
+            cancel: Some(cancel_tx),
+            task: Some(task),
+        }
+    }
+}
+
+impl<T> StreamHub<T> {
+    /// 加入一个消费者；若订阅并集发生变化，等待服务器确认后返回
+    pub async fn subscribe(
+        &self,
+        patterns: Vec<String>,
+        buffer: usize,
+        policy: BackpressurePolicy,
+    ) -> Result<HubSubscription<T>> {
+        let matcher = StreamMatcher::from_patterns(&patterns)?;
+        if matcher.is_empty() {
+            return Err(MarketStoreError::InvalidData("Subscription has no streams".to_string()));
+        }
+
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let queue = Arc::new(ConsumerQueue::new(buffer.max(1), policy));
+        let ack = {
+            let mut registry = self.shared.registry.lock().unwrap();
+            if registry.closed {
+                return Err(MarketStoreError::Connection("Stream hub is closed".to_string()));
+            }
+            registry.consumers.insert(id, Consumer { matcher, queue: queue.clone() });
+            self.shared.sync_streams(&mut registry)
+        };
+        let subscription = HubSubscription {
+            id,
+            patterns,
+            shared: self.shared.clone(),
+            queue,
+        };
+
+        if let Some(PendingSync { sequence, streams, ack }) = ack {
+            let closed = || MarketStoreError::Connection("Stream hub is closed".to_string());
+            match tokio::time::timeout(SUBSCRIBE_ACK_TIMEOUT, ack).await {
+                Ok(Ok(result)) => {
+                    result?;
+                    self.shared.acked.lock().unwrap().apply(sequence, streams);
+                }
+                Ok(Err(_)) => return Err(closed()),
+                Err(_) => {
+                    return Err(MarketStoreError::Timeout(
+                        "Timed out waiting for subscription acknowledgement".to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(subscription)
+    }
+
+    /// 服务器最近确认的订阅并集
+    pub fn streams(&self) -> Vec<String> {
+        self.shared.acked.lock().unwrap().streams.clone()
+    }
+
+    /// 当前消费者数量
+    pub fn consumer_count(&self) -> usize {
+        self.shared.registry.lock().unwrap().consumers.len()
+    }
+
+    /// 关闭共享连接并等待关闭握手完成，所有消费者随之结束
+    pub async fn close(mut self) -> Result<()> {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        if let Some(task) = self.task.take() {
+            task.await.map_err(|e| MarketStoreError::WebSocket(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Drop for StreamHub<T> {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+    }
+}
+
+impl<T> HubShared<T> {
+    /// 重新计算模式并集，变化时发送订阅消息并返回确认通道；调用方需持有registry锁以保证发送顺序
+    fn sync_streams(&self, registry: &mut HubRegistry<T>) -> Option<PendingSync> {
+        let mut union: Vec<String> = Vec::new();
+        for consumer in registry.consumers.values() {
+            for pattern in consumer.matcher.patterns() {
+                if !union.iter().any(|p| p == pattern) {
+                    union.push(pattern.to_string());
+                }
+            }
+        }
+
+        // 服务器会忽略空列表，多余的推送在本地过滤
+        if union.is_empty() || union == registry.requested {
+            return None;
+        }
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        let command = SubscribeCommand {
+            message: SubscribeMessage { streams: union.clone() },
+            ack: ack_tx,
+        };
+        if self.commands.send(command).is_err() {
+            return None;
+        }
+        tracing::debug!("Stream hub subscription requested: {:?}", union);
+        registry.requested = union.clone();
+        registry.sequence += 1;
+        Some(PendingSync { sequence: registry.sequence, streams: union, ack: ack_rx })
+    }
+
+    fn remove_consumer(&self, id: u64) {
+        let pending = {
+            let mut registry = self.registry.lock().unwrap();
+            if registry.consumers.remove(&id).is_none() || registry.closed {
+                return;
+            }
+            self.sync_streams(&mut registry)
+        };
+
+        // 消费者在drop中移除，无法等待确认，在后台确认后再更新已确认的订阅集合
+        let (Some(PendingSync { sequence, streams, ack }), Ok(runtime)) = (pending, tokio::runtime::Handle::try_current()) else {
+            return;
+        };
+        let acked = self.acked.clone();
+        runtime.spawn(async move {
+            match tokio::time::timeout(SUBSCRIBE_ACK_TIMEOUT, ack).await {
+                Ok(Ok(Ok(()))) => acked.lock().unwrap().apply(sequence, streams),
+                Ok(Ok(Err(e))) => tracing::warn!("Stream hub failed to shrink subscription: {}", e),
+                _ => {}
+            }
+        });
+    }
+
+    fn close_all<E>(&self, error: E)
+    where
+        E: Fn() -> Option<MarketStoreError>,
+    {
+        let mut registry = self.registry.lock().unwrap();
+        registry.closed = true;
+        for consumer in registry.consumers.values() {
+            consumer.queue.close(error());
+        }
+    }
+}
+
+impl<T: Clone> HubShared<T> {
+    async fn dispatch(&self, mut events: mpsc::Receiver<StreamEvent<T>>, gate: &Semaphore) {
+        while let Some(event) = events.recv().await {
+            let targets: Vec<Arc<ConsumerQueue<T>>> = {
+                let registry = self.registry.lock().unwrap();
+                registry
+                    .consumers
+                    .values()
+                    .filter(|consumer| match &event {
+                        StreamEvent::Payload(payload) => consumer.matcher.is_match(&payload.key),
+                        // 协议层事件与具体模式无关，通知所有消费者
+                        _ => true,
+                    })
+                    .map(|consumer| consumer.queue.clone())
+                    .collect()
+            };
+
+            for queue in targets {
+                queue.push(event.clone()).await;
+            }
+            gate.add_permits(1);
+        }
+    }
+}
+
+/// hub中的一个消费者，drop时退出并在需要时收缩服务器端订阅
+pub struct HubSubscription<T = StreamData> {
+    id: u64,
+    patterns: Vec<String>,
+    shared: Arc<HubShared<T>>,
+    queue: Arc<ConsumerQueue<T>>,
+}
+
+impl<T> HubSubscription<T> {
+    /// 该消费者订阅的模式
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+}
+
+impl<T> Stream for HubSubscription<T> {
+    type Item = Result<StreamEvent<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.queue.poll_pop(cx)
+    }
+}
+
+impl<T> Drop for HubSubscription<T> {
+    fn drop(&mut self) {
+        self.queue.detach();
+        self.shared.remove_consumer(self.id);
+    }
+}
+
+/// 按策略限制容量的消费者缓冲区
+struct ConsumerQueue<T> {
+    state: StdMutex<QueueState<T>>,
+    space: Notify,
+    capacity: usize,
+    policy: BackpressurePolicy,
+}
+
+struct QueueState<T> {
+    items: VecDeque<Result<StreamEvent<T>>>,
+    dropped: u64,
+    closed: bool,
+    detached: bool,
+    waker: Option<Waker>,
+}
+
+impl<T> ConsumerQueue<T> {
+    fn new(capacity: usize, policy: BackpressurePolicy) -> Self {
+        Self {
+            state: StdMutex::new(QueueState {
+                items: VecDeque::with_capacity(capacity),
+                dropped: 0,
+                closed: false,
+                detached: false,
+                waker: None,
+            }),
+            space: Notify::new(),
+            capacity,
+            policy,
+        }
+    }
+
+    async fn push(&self, event: StreamEvent<T>) {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.closed || state.detached {
+                    return;
+                }
+                if state.items.len() < self.capacity {
+                    state.items.push_back(Ok(event));
+                    Self::wake(&mut state);
+                    return;
+                }
+                match self.policy {
+                    BackpressurePolicy::Block => {}
+                    BackpressurePolicy::DropOldest => {
+                        state.items.pop_front();
+                        state.items.push_back(Ok(event));
+                        state.dropped += 1;
+                        Self::wake(&mut state);
+                        return;
+                    }
+                    BackpressurePolicy::DropNewest => {
+                        state.dropped += 1;
+                        Self::wake(&mut state);
+                        return;
+                    }
+                }
+            }
+            // 消费者取出数据、退出或队列关闭时都会发出通知
+            self.space.notified().await;
+        }
+    }
+
+    fn close(&self, error: Option<MarketStoreError>) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+        state.closed = true;
+        if let Some(error) = error {
+            state.items.push_back(Err(error));
+        }
+        Self::wake(&mut state);
+        self.space.notify_one();
+    }
+
+    fn detach(&self) {
+        self.state.lock().unwrap().detached = true;
+        self.space.notify_one();
+    }
+
+    fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<Option<Result<StreamEvent<T>>>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(item) = state.items.pop_front() {
+            self.space.notify_one();
+            return Poll::Ready(Some(item));
+        }
+        // 缓冲区已取空但仍有未报告的丢弃
+        if state.dropped > 0 {
+            let dropped = std::mem::take(&mut state.dropped);
+            return Poll::Ready(Some(Ok(StreamEvent::Lagged { dropped })));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn wake(state: &mut QueueState<T>) {
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use futures::Stream;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{validate_streams, StreamData, StreamPayload, SubscribeMessage},
+    client::SUBSCRIBE_ACK_TIMEOUT,
+};
+
+/// `subscribe_stream` 产出的事件
+#[derive(Debug, Clone)]
+pub enum StreamEvent<T = StreamData> {
+    /// 服务器推送的数据
+    Payload(StreamPayload<T>),
+    /// 消费速度跟不上，缓冲区已满期间丢弃了 `dropped` 条数据
+    Lagged { dropped: u64 },
+    /// 服务器主动发送的、不对应任何订阅请求的错误消息
+    ServerError { message: String },
+    /// 无法解码的二进制帧，`frame` 为原始数据，`error` 为按推送格式解码时的错误
+    Undecodable { frame: Vec<u8>, error: String },
+}
+
+/// 发送给消息循环的订阅变更，服务器确认或拒绝后通过 `ack` 回复
+pub(crate) struct SubscribeCommand {
+    pub(crate) message: SubscribeMessage,
+    pub(crate) ack: oneshot::Sender<Result<()>>,
+}
+
+/// 基于有界缓冲区的订阅流，drop时自动取消订阅并执行关闭握手
+pub struct SubscriptionStream<T = StreamData> {
+    receiver: mpsc::Receiver<Result<StreamEvent<T>>>,
+    dropped: Arc<AtomicU64>,
+    handle: SubscriptionHandle,
+    cancel: Option<oneshot::Sender<()>>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl<T> SubscriptionStream<T> {
+    pub(crate) fn new(
+        receiver: mpsc::Receiver<Result<StreamEvent<T>>>,
+        dropped: Arc<AtomicU64>,
+        handle: SubscriptionHandle,
+        cancel: oneshot::Sender<()>,
+        task: tokio::task::JoinHandle<()>,
+    ) -> Self {
+        Self {
+            receiver,
+            dropped,
+            handle,
+            cancel: Some(cancel),
+            task: Some(task),
+        }
+    }
+
+    /// 获取可在连接存活期间增删订阅的handle
+    pub fn handle(&self) -> SubscriptionHandle {
+        self.handle.clone()
+    }
+
+    /// 取消订阅并等待关闭握手完成
+    pub async fn close(mut self) -> Result<()> {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        if let Some(task) = self.task.take() {
+            task.await.map_err(|e| MarketStoreError::WebSocket(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Stream for SubscriptionStream<T> {
+    type Item = Result<StreamEvent<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.receiver.poll_recv(cx) {
+            Poll::Ready(Some(item)) => Poll::Ready(Some(item)),
+            // 缓冲区已取空但仍有未报告的丢弃，立即报告而不必等下一条推送
+            other => match self.dropped.swap(0, Ordering::AcqRel) {
+                0 => other,
+                dropped => Poll::Ready(Some(Ok(StreamEvent::Lagged { dropped }))),
+            },
+        }
+    }
+}
+
+/// 在同一连接上修改订阅集合的handle
+///
+/// 服务器收到新的 `SubscribeMessage` 时会整体替换订阅集合，因此handle在客户端维护完整集合，
+/// 每次变更都发送合并后的列表。变更在服务器确认后才生效，被拒绝时保持原集合不变。
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    streams: Arc<StdMutex<Vec<String>>>,
+    commands: mpsc::UnboundedSender<SubscribeCommand>,
+    // 服务器按顺序逐条确认，串行化变更避免集合与服务器状态错位
+    update_lock: Arc<Mutex<()>>,
+}
+
+impl SubscriptionHandle {
+    pub(crate) fn new(streams: Vec<String>, commands: mpsc::UnboundedSender<SubscribeCommand>) -> Self {
+        let mut unique = Vec::with_capacity(streams.len());
+        for stream in streams {
+            if !unique.contains(&stream) {
+                unique.push(stream);
+            }
+        }
+        Self {
+            streams: Arc::new(StdMutex::new(unique)),
+            commands,
+            update_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// 当前订阅的完整集合
+    pub fn streams(&self) -> Vec<String> {
+        self.streams.lock().unwrap().clone()
+    }
+
+    /// 追加订阅
+    pub async fn add(&self, streams: Vec<String>) -> Result<()> {
+        self.update(|current| {
+            for stream in streams {
+                if !current.contains(&stream) {
+                    current.push(stream);
+                }
+            }
+        }).await
+    }
+
+    /// 移除订阅
+    pub async fn remove(&self, streams: &[String]) -> Result<()> {
+        self.update(|current| current.retain(|stream| !streams.contains(stream))).await
+    }
+
+    /// 用新的集合替换全部订阅
+    pub async fn replace(&self, streams: Vec<String>) -> Result<()> {
+        self.update(|current| {
+            current.clear();
+            for stream in streams {
+                if !current.contains(&stream) {
+                    current.push(stream);
+                }
+            }
+        }).await
+    }
+
+    async fn update<U>(&self, update: U) -> Result<()>
+    where
+        U: FnOnce(&mut Vec<String>),
+    {
+        let _guard = self.update_lock.lock().await;
+        let current = self.streams();
+        let mut next = current.clone();
+        update(&mut next);
+
+        if next == current {
+            return Ok(());
+        }
+        // 服务器会忽略空的订阅列表，无法表达"全部取消"
+        if next.is_empty() {
+            return Err(MarketStoreError::InvalidData(
+                "Cannot remove all streams from a live subscription; drop the stream instead".to_string(),
+            ));
+        }
+        validate_streams(&next)?;
+
+        let closed = || MarketStoreError::Connection("Subscription is closed".to_string());
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.commands
+            .send(SubscribeCommand { message: SubscribeMessage { streams: next.clone() }, ack: ack_tx })
+            .map_err(|_| closed())?;
+        match tokio::time::timeout(SUBSCRIBE_ACK_TIMEOUT, ack_rx).await {
+            Ok(Ok(result)) => result?,
+            Ok(Err(_)) => return Err(closed()),
+            Err(_) => {
+                return Err(MarketStoreError::Timeout(
+                    "Timed out waiting for subscription acknowledgement".to_string(),
+                ))
+            }
+        }
+
+        *self.streams.lock().unwrap() = next;
+        Ok(())
+    }
+}
+
+/// 将推送写入有界通道；通道满时计数丢弃条数，并在下一次成功写入前先发送 `Lagged` 事件
+pub(crate) struct BoundedForwarder<T> {
+    sender: mpsc::Sender<Result<StreamEvent<T>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T> BoundedForwarder<T> {
+    pub(crate) fn new(sender: mpsc::Sender<Result<StreamEvent<T>>>) -> Self {
+        Self {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 与订阅流共享的丢弃计数
+    pub(crate) fn dropped_counter(&self) -> Arc<AtomicU64> {
+        self.dropped.clone()
+    }
+
+    pub(crate) fn forward(&mut self, event: StreamEvent<T>) -> Result<()> {
+        let dropped = self.dropped.swap(0, Ordering::AcqRel);
+        if dropped > 0 {
+            match self.sender.try_send(Ok(StreamEvent::Lagged { dropped })) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(dropped + 1, Ordering::AcqRel);
+                    return Ok(());
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    return Err(MarketStoreError::Connection("Subscription stream dropped".to_string()));
+                }
+            }
+        }
+
+        match self.sender.try_send(Ok(event)) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::AcqRel);
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                Err(MarketStoreError::Connection("Subscription stream dropped".to_string()))
+            }
+        }
+    }
+
+    pub(crate) async fn fail(&self, error: MarketStoreError) {
+        let _ = self.sender.send(Err(error)).await;
+    }
+}
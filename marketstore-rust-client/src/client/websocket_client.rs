@@ -1,28 +1,137 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio_tungstenite::{connect_async, WebSocketStream, MaybeTlsStream};
 use tokio::net::TcpStream;
+use tokio::sync::{oneshot, Semaphore};
 use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
 use crate::{
     error::{Result, MarketStoreError},
-    models::{StreamSubscription, StreamPayload, SubscribeMessage, ErrorMessage},
+    models::{StreamSubscription, StreamPayload, StreamData, SubscribeMessage, ErrorMessage},
+    client::{
+        BoundedForwarder, HeartbeatAction, HeartbeatConfig, HeartbeatState, StreamEvent, StreamRecorder,
+        SubscribeCommand, SubscriptionHandle, SubscriptionStream,
+    },
 };
 
+/// 等待服务器确认订阅（回显订阅消息或返回错误消息）的超时时间
+pub(crate) const SUBSCRIBE_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 消息循环结束的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LoopExit {
+    /// 调用方取消
+    Cancelled,
+    /// 服务器发送了关闭帧
+    ServerClosed,
+    /// 底层流结束
+    StreamEnded,
+}
+
 pub struct WebSocketClient {
     stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    // 等待订阅确认期间收到的数据帧，由消息循环优先处理
+    pending_frames: VecDeque<Vec<u8>>,
+    heartbeat: HeartbeatConfig,
+    recorder: Option<StreamRecorder>,
+    // 每读取一个产生事件的帧消耗一个许可，由消费方处理完事件后归还；许可耗尽时停止读取socket
+    frame_gate: Option<Arc<Semaphore>>,
 }
 
 impl WebSocketClient {
     pub async fn connect(url: &str) -> Result<Self> {
         let (ws_stream, _) = connect_async(url).await?;
         
-        Ok(Self { stream: ws_stream })
+        Ok(Self {
+            stream: ws_stream,
+            pending_frames: VecDeque::new(),
+            heartbeat: HeartbeatConfig::default(),
+            recorder: None,
+            frame_gate: None,
+        })
+    }
+    
+    /// 将收到的每个二进制帧写入录制文件
+    pub fn with_recorder(mut self, recorder: StreamRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+    
+    /// 设置消息循环使用的心跳与死连接检测参数
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = heartbeat;
+        self
     }
     
+    /// 限制已交给handler但尚未被消费的帧数，消费方每处理完一个事件需 `add_permits(1)`
+    pub(crate) fn with_frame_gate(mut self, gate: Arc<Semaphore>) -> Self {
+        self.frame_gate = Some(gate);
+        self
+    }
+    
+    /// 校验订阅模式后发送订阅并等待服务器确认；服务器拒绝时返回 `MarketStoreError::SubscriptionRejected`
     pub async fn subscribe(&mut self, subscription: StreamSubscription) -> Result<()> {
+        subscription.validate()?;
         let message = SubscribeMessage {
             streams: subscription.streams,
         };
         
-        let msgpack_data = rmp_serde::to_vec(&message)?;
+        self.send_subscribe_message(&message).await?;
+        self.wait_for_ack().await
+    }
+    
+    /// 服务器对每条订阅消息回复一次：成功时原样回显，失败时返回 `ErrorMessage`
+    async fn wait_for_ack(&mut self) -> Result<()> {
+        let deadline = tokio::time::sleep(SUBSCRIBE_ACK_TIMEOUT);
+        tokio::pin!(deadline);
+        
+        loop {
+            let msg = tokio::select! {
+                msg = self.stream.next() => msg,
+                _ = &mut deadline => {
+                    return Err(MarketStoreError::Timeout(
+                        "Timed out waiting for subscription acknowledgement".to_string(),
+                    ));
+                }
+            };
+            
+            match msg {
+                Some(Ok(tokio_tungstenite::tungstenite::Message::Binary(data))) => {
+                    if let Some(recorder) = &self.recorder {
+                        recorder.record(&data);
+                    }
+                    if let Ok(ack) = rmp_serde::from_slice::<SubscribeMessage>(&data) {
+                        tracing::debug!("Subscription acknowledged: {:?}", ack.streams);
+                        return Ok(());
+                    } else if let Ok(error_msg) = rmp_serde::from_slice::<ErrorMessage>(&data) {
+                        tracing::warn!("Subscription rejected: {}", error_msg.error);
+                        return Err(MarketStoreError::SubscriptionRejected(error_msg.error));
+                    } else {
+                        self.pending_frames.push_back(data.to_vec());
+                    }
+                }
+                Some(Ok(tokio_tungstenite::tungstenite::Message::Ping(data))) => {
+                    self.stream.send(tokio_tungstenite::tungstenite::Message::Pong(data)).await?;
+                }
+                Some(Ok(tokio_tungstenite::tungstenite::Message::Close(frame))) => {
+                    return Err(MarketStoreError::Connection(format!(
+                        "Connection closed before subscription was acknowledged: {:?}", frame
+                    )));
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(MarketStoreError::WebSocket(e.to_string())),
+                None => {
+                    return Err(MarketStoreError::Connection(
+                        "Connection closed before subscription was acknowledged".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+    
+    async fn send_subscribe_message(&mut self, message: &SubscribeMessage) -> Result<()> {
+        let msgpack_data = rmp_serde::to_vec(message)?;
         self.stream.send(tokio_tungstenite::tungstenite::Message::Binary(msgpack_data.into())).await?;
         
         Ok(())
@@ -78,125 +187,150 @@ impl WebSocketClient {
     pub async fn subscribe_with_handler<F>(
         mut self,
         subscription: StreamSubscription,
-        mut handler: F,
+        handler: F,
     ) -> Result<()>
     where
         F: FnMut(StreamPayload) -> Result<()> + Send + 'static,
     {
         self.subscribe(subscription).await?;
-        
-        while let Some(msg) = self.stream.next().await {
-            match msg {
-                Ok(tokio_tungstenite::tungstenite::Message::Binary(data)) => {
-                    // 尝试反序列化为不同类型的消息
-                    if let Ok(payload) = rmp_serde::from_slice::<StreamPayload>(&data) {
-                        if let Err(e) = handler(payload) {
-                            tracing::warn!("Handler error: {}", e);
-                        }
-                    } else if let Ok(subscribe_msg) = rmp_serde::from_slice::<SubscribeMessage>(&data) {
-                        tracing::debug!("Received subscribe message: {:?}", subscribe_msg);
-                    } else if let Ok(error_msg) = rmp_serde::from_slice::<ErrorMessage>(&data) {
-                        tracing::warn!("Received error message: {}", error_msg.error);
-                    } else {
-                        tracing::warn!("Failed to deserialize message as any known type");
-                    }
-                }
-                Ok(tokio_tungstenite::tungstenite::Message::Close(frame)) => {
-                    tracing::info!("WebSocket connection closed by server: {:?}", frame);
-                    // 服务器发起了关闭，我们需要响应关闭帧
-                    if let Some(close_frame) = frame {
-                        let response_frame = tokio_tungstenite::tungstenite::protocol::CloseFrame {
-                            code: close_frame.code,
-                            reason: close_frame.reason.clone(),
-                        };
-                        if let Err(e) = self.stream.send(tokio_tungstenite::tungstenite::Message::Close(Some(response_frame))).await {
-                            tracing::warn!("Failed to send close response: {}", e);
-                        }
-                    } else {
-                        if let Err(e) = self.stream.send(tokio_tungstenite::tungstenite::Message::Close(None)).await {
-                            tracing::warn!("Failed to send close response: {}", e);
-                        }
-                    }
-                    break;
-                }
-                Ok(tokio_tungstenite::tungstenite::Message::Ping(data)) => {
-                    if let Err(e) = self.stream.send(tokio_tungstenite::tungstenite::Message::Pong(data)).await {
-                        tracing::warn!("Failed to send pong: {}", e);
-                        break;
-                    }
-                }
-                Ok(tokio_tungstenite::tungstenite::Message::Pong(_)) => {
-                    // 忽略pong消息
-                }
-                Ok(tokio_tungstenite::tungstenite::Message::Text(text)) => {
-                    tracing::debug!("Received text message: {}", text);
-                }
-                Ok(tokio_tungstenite::tungstenite::Message::Frame(_)) => {
-                    // 忽略原始帧
-                }
-                Err(e) => {
-                    tracing::warn!("WebSocket error: {}", e);
-                    return Err(MarketStoreError::WebSocket(e.to_string()));
-                }
-            }
-        }
-        
+        self.run_loop(&mut payloads_only(handler), None, None).await?;
         Ok(())
     }
     
-    pub async fn subscribe_with_handler_and_cancel<F>(
+    /// 发送订阅并以 `Stream` 形式返回推送，`buffer` 为内部缓冲区容量，返回的流被drop时取消订阅
+    pub async fn subscribe_stream(
+        self,
+        subscription: StreamSubscription,
+        buffer: usize,
+    ) -> Result<SubscriptionStream> {
+        self.subscribe_stream_as::<StreamData>(subscription, buffer).await
+    }
+    
+    /// 与 `subscribe_stream` 相同，但将推送数据解码为 `T`
+    pub async fn subscribe_stream_as<T>(
         mut self,
         subscription: StreamSubscription,
-        mut handler: F,
-        cancel: tokio::sync::oneshot::Receiver<()>,
-    ) -> Result<()>
+        buffer: usize,
+    ) -> Result<SubscriptionStream<T>>
     where
-        F: FnMut(StreamPayload) -> Result<()> + Send + 'static,
+        T: DeserializeOwned + Send + 'static,
     {
+        let handle_streams = subscription.streams.clone();
         self.subscribe(subscription).await?;
         
-        let mut cancel = cancel;
+        let (sender, receiver) = tokio::sync::mpsc::channel(buffer.max(1));
+        let (cancel_tx, mut cancel_rx) = tokio::sync::oneshot::channel();
+        let (command_tx, mut command_rx) = tokio::sync::mpsc::unbounded_channel();
+        let handle = SubscriptionHandle::new(handle_streams, command_tx);
+        
+        let mut forwarder = BoundedForwarder::new(sender);
+        let dropped = forwarder.dropped_counter();
+        
+        let task = tokio::spawn(async move {
+            let mut handler = |event| forwarder.forward(event);
+            if let Err(e) = self.run_loop(&mut handler, Some(&mut cancel_rx), Some(&mut command_rx)).await {
+                forwarder.fail(e).await;
+            }
+        });
+        
+        Ok(SubscriptionStream::new(receiver, dropped, handle, cancel_tx, task))
+    }
+    
+    /// 读取消息并分发给handler，直到连接关闭、出错或收到取消信号；
+    /// `commands` 中收到的订阅消息发送给服务器，并在服务器回显或报错时回复确认结果
+    pub(crate) async fn run_loop<T, F>(
+        &mut self,
+        handler: &mut F,
+        mut cancel: Option<&mut oneshot::Receiver<()>>,
+        mut commands: Option<&mut tokio::sync::mpsc::UnboundedReceiver<SubscribeCommand>>,
+    ) -> Result<LoopExit>
+    where
+        T: DeserializeOwned,
+        F: FnMut(StreamEvent<T>) -> Result<()>,
+    {
+        // 已发送、等待服务器回复的订阅变更，服务器按顺序逐条回复
+        let mut pending_acks: VecDeque<oneshot::Sender<Result<()>>> = VecDeque::new();
+        
+        let gate = self.frame_gate.clone();
+        while let Some(data) = self.pending_frames.pop_front() {
+            if dispatch_frame(&data, handler, &mut pending_acks) {
+                if let Some(permit) = gate.as_ref().and_then(|gate| gate.try_acquire().ok()) {
+                    permit.forget();
+                }
+            }
+        }
         
+        let mut heartbeat = HeartbeatState::new(&self.heartbeat);
+        let mut permit = None;
         loop {
+            let wait_for_permit = gate.clone().filter(|_| permit.is_none());
+            let can_read = gate.is_none() || permit.is_some();
+            // 暂停读取时pong无法被读到，心跳计时随之暂停，避免背压被误判为死连接
+            if can_read {
+                heartbeat.resume();
+            } else {
+                heartbeat.pause();
+            }
+            let permit_ready = async {
+                match wait_for_permit {
+                    Some(gate) => gate.acquire_owned().await.ok(),
+                    None => futures::future::pending().await,
+                }
+            };
+            let cancelled = async {
+                match cancel.as_mut() {
+                    Some(cancel) => {
+                        let _ = (&mut **cancel).await;
+                    }
+                    None => futures::future::pending::<()>().await,
+                }
+            };
+            let command = async {
+                match commands.as_mut() {
+                    Some(commands) => commands.recv().await,
+                    None => futures::future::pending().await,
+                }
+            };
+            
+            let heartbeat_due = heartbeat.next_deadline();
+            let heartbeat_timer = async {
+                match heartbeat_due {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => futures::future::pending().await,
+                }
+            };
+            
             tokio::select! {
-                msg = self.stream.next() => {
+                acquired = permit_ready => permit = acquired,
+                msg = self.stream.next(), if can_read => {
+                    heartbeat.received();
                     match msg {
                         Some(Ok(tokio_tungstenite::tungstenite::Message::Binary(data))) => {
-                            // 尝试反序列化为不同类型的消息
-                            if let Ok(payload) = rmp_serde::from_slice::<StreamPayload>(&data) {
-                                if let Err(e) = handler(payload) {
-                                    tracing::warn!("Handler error: {}", e);
+                            if let Some(recorder) = &self.recorder {
+                                recorder.record(&data);
+                            }
+                            if dispatch_frame(&data, handler, &mut pending_acks) {
+                                if let Some(permit) = permit.take() {
+                                    permit.forget();
                                 }
-                            } else if let Ok(subscribe_msg) = rmp_serde::from_slice::<SubscribeMessage>(&data) {
-                                tracing::debug!("Received subscribe message: {:?}", subscribe_msg);
-                            } else if let Ok(error_msg) = rmp_serde::from_slice::<ErrorMessage>(&data) {
-                                tracing::warn!("Received error message: {}", error_msg.error);
-                            } else {
-                                tracing::warn!("Failed to deserialize message as any known type");
                             }
                         }
                         Some(Ok(tokio_tungstenite::tungstenite::Message::Close(frame))) => {
                             tracing::info!("WebSocket connection closed by server: {:?}", frame);
                             // 服务器发起了关闭，我们需要响应关闭帧
-                            if let Some(close_frame) = frame {
-                                let response_frame = tokio_tungstenite::tungstenite::protocol::CloseFrame {
-                                    code: close_frame.code,
-                                    reason: close_frame.reason.clone(),
-                                };
-                                if let Err(e) = self.stream.send(tokio_tungstenite::tungstenite::Message::Close(Some(response_frame))).await {
-                                    tracing::warn!("Failed to send close response: {}", e);
-                                }
-                            } else {
-                                if let Err(e) = self.stream.send(tokio_tungstenite::tungstenite::Message::Close(None)).await {
-                                    tracing::warn!("Failed to send close response: {}", e);
-                                }
+                            let response_frame = frame.map(|close_frame| tokio_tungstenite::tungstenite::protocol::CloseFrame {
+                                code: close_frame.code,
+                                reason: close_frame.reason.clone(),
+                            });
+                            if let Err(e) = self.stream.send(tokio_tungstenite::tungstenite::Message::Close(response_frame)).await {
+                                tracing::warn!("Failed to send close response: {}", e);
                             }
-                            break;
+                            return Ok(LoopExit::ServerClosed);
                         }
                         Some(Ok(tokio_tungstenite::tungstenite::Message::Ping(data))) => {
                             if let Err(e) = self.stream.send(tokio_tungstenite::tungstenite::Message::Pong(data)).await {
                                 tracing::warn!("Failed to send pong: {}", e);
-                                break;
+                                return Ok(LoopExit::StreamEnded);
                             }
                         }
                         Some(Ok(tokio_tungstenite::tungstenite::Message::Pong(_))) => {
@@ -214,22 +348,44 @@ impl WebSocketClient {
                         }
                         None => {
                             tracing::info!("WebSocket stream ended");
-                            break;
+                            return Ok(LoopExit::StreamEnded);
                         }
                     }
                 }
-                _ = &mut cancel => {
+                command = command => {
+                    match command {
+                        Some(SubscribeCommand { message, ack }) => {
+                            tracing::debug!("Updating subscription: {:?}", message.streams);
+                            if let Err(e) = self.send_subscribe_message(&message).await {
+                                let _ = ack.send(Err(MarketStoreError::WebSocket(e.to_string())));
+                                return Err(e);
+                            }
+                            pending_acks.push_back(ack);
+                        }
+                        // 所有handle都已释放，不再有订阅变更
+                        None => commands = None,
+                    }
+                }
+                _ = heartbeat_timer => {
+                    match heartbeat.on_timer() {
+                        HeartbeatAction::SendPing => self.send_ping().await?,
+                        HeartbeatAction::Dead(reason) => {
+                            tracing::warn!("WebSocket connection declared dead: {}", reason);
+                            return Err(MarketStoreError::Timeout(reason));
+                        }
+                        HeartbeatAction::None => {}
+                    }
+                }
+                _ = cancelled => {
                     tracing::info!("Received cancel signal, performing RFC 6455 close handshake");
                     // 按照RFC 6455执行正确的关闭握手
                     if let Err(e) = self.perform_close_handshake().await {
                         tracing::warn!("Close handshake failed: {}", e);
                     }
-                    break;
+                    return Ok(LoopExit::Cancelled);
                 }
             }
         }
-        
-        Ok(())
     }
     
     pub async fn send_ping(&mut self) -> Result<()> {
@@ -241,4 +397,71 @@ impl WebSocketClient {
     pub async fn close(&mut self) -> Result<()> {
         self.perform_close_handshake().await
     }
-} 
\ No newline at end of file
+}
+
+/// 解码一个二进制帧：推送交给handler，订阅回显与错误消息用于回复等待中的订阅变更，
+/// 其余内容作为事件上报而不是静默丢弃；返回是否调用了handler
+fn dispatch_frame<T, F>(
+    data: &[u8],
+    handler: &mut F,
+    pending_acks: &mut VecDeque<oneshot::Sender<Result<()>>>,
+) -> bool
+where
+    T: DeserializeOwned,
+    F: FnMut(StreamEvent<T>) -> Result<()>,
+{
+    let event = match rmp_serde::from_slice::<StreamPayload<T>>(data) {
+        Ok(payload) => StreamEvent::Payload(payload),
+        Err(payload_error) => {
+            if let Ok(subscribe_msg) = rmp_serde::from_slice::<SubscribeMessage>(data) {
+                tracing::debug!("Received subscribe message: {:?}", subscribe_msg);
+                if let Some(ack) = pending_acks.pop_front() {
+                    let _ = ack.send(Ok(()));
+                }
+                return false;
+            } else if let Ok(error_msg) = rmp_serde::from_slice::<ErrorMessage>(data) {
+                match pending_acks.pop_front() {
+                    Some(ack) => {
+                        tracing::warn!("Subscription update rejected: {}", error_msg.error);
+                        let _ = ack.send(Err(MarketStoreError::SubscriptionRejected(error_msg.error)));
+                        return false;
+                    }
+                    None => {
+                        tracing::warn!("Received error message: {}", error_msg.error);
+                        StreamEvent::ServerError { message: error_msg.error }
+                    }
+                }
+            } else {
+                tracing::warn!("Failed to deserialize message as any known type: {}", payload_error);
+                StreamEvent::Undecodable { frame: data.to_vec(), error: payload_error.to_string() }
+            }
+        }
+    };
+    
+    if let Err(e) = handler(event) {
+        tracing::warn!("Handler error: {}", e);
+    }
+    true
+}
+
+/// 将只处理推送的回调适配为事件handler，其余事件记录日志
+pub(crate) fn payloads_only<T, F>(mut handler: F) -> impl FnMut(StreamEvent<T>) -> Result<()>
+where
+    F: FnMut(StreamPayload<T>) -> Result<()>,
+{
+    move |event| match event {
+        StreamEvent::Payload(payload) => handler(payload),
+        StreamEvent::Lagged { dropped } => {
+            tracing::warn!("Dropped {} stream messages", dropped);
+            Ok(())
+        }
+        StreamEvent::ServerError { message } => {
+            tracing::warn!("Stream server error: {}", message);
+            Ok(())
+        }
+        StreamEvent::Undecodable { frame, error } => {
+            tracing::warn!("Skipping undecodable stream frame ({} bytes): {}", frame.len(), error);
+            Ok(())
+        }
+    }
+}
@@ -25,6 +25,15 @@ pub enum MarketStoreError {
     
     #[error("Protocol error: {0}")]
     Protocol(String),
+    
+    #[error("Subscription rejected: {0}")]
+    SubscriptionRejected(String),
+    
+    #[error("Checksum mismatch: {0}")]
+    ChecksumMismatch(String),
+    
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, MarketStoreError>;
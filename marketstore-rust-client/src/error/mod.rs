@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod error;
 
 pub use error::*; 
\ No newline at end of file
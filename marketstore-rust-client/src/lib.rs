@@ -2,6 +2,10 @@ pub mod error;
 pub mod models;
 pub mod client;
 pub mod utils;
+pub mod storage;
+pub mod aggregation;
+pub mod calendar;
+pub mod adjustment;
 
 pub use models::*;
 pub use client::*;
@@ -1,8 +1,8 @@
 use marketstore_rust_client::{
-    MarketStoreClient, OHLCVData, StreamSubscription, SymbolFormat, DataShape,
+    MarketStoreClient, OHLCVData, StreamSubscription, SymbolFormat, DataShape, StreamEvent,
     error::Result,
 };
-use tokio::sync::oneshot;
+use futures::StreamExt;
 use tracing::info;
 
 #[tokio::main]
@@ -103,29 +103,43 @@ async fn main() -> Result<()> {
         .add_stream("TEST/1Min/OHLCV")
         .add_stream("BTCUSDT/1Min/OHLCV");
 
-    let received_count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let received_count_clone = received_count.clone();
-    let handler = move |payload| {
-        let count = received_count_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        info!("Received real-time data #{}: {:?}", count + 1, payload);
-        Ok(())
-    };
-
-    let (tx, rx) = oneshot::channel();
-    
-    let stream_handle = client.subscribe_realtime_with_cancel(subscription, handler, rx).await?;
+    let mut stream = client.subscribe_stream(subscription, 1024).await?;
     info!("Subscribed to real-time data streams");
 
-    // 等待一段时间接收数据
-    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-    
-    // 发送取消信号
-    let _ = tx.send(());
+    // 在一段时间内接收数据
+    let mut received_count = 0;
+    let deadline = tokio::time::sleep(tokio::time::Duration::from_secs(10));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            event = stream.next() => match event {
+                Some(Ok(StreamEvent::Payload(payload))) => {
+                    received_count += 1;
+                    info!("Received real-time data #{}: {:?}", received_count, payload);
+                }
+                Some(Ok(StreamEvent::Lagged { dropped })) => {
+                    info!("Dropped {} real-time messages", dropped);
+                }
+                Some(Ok(StreamEvent::ServerError { message })) => {
+                    info!("Stream server error: {}", message);
+                }
+                Some(Ok(StreamEvent::Undecodable { error, .. })) => {
+                    info!("Undecodable stream frame: {}", error);
+                }
+                Some(Err(e)) => {
+                    info!("Subscription error: {}", e);
+                    break;
+                }
+                None => break,
+            },
+            _ = &mut deadline => break,
+        }
+    }
     
-    // 等待流处理完成
-    let _ = stream_handle.await;
+    // 取消订阅并等待关闭握手完成
+    stream.close().await?;
     
-    info!("Received {} real-time messages", received_count.load(std::sync::atomic::Ordering::SeqCst));
+    info!("Received {} real-time messages", received_count);
 
     // 批量操作示例
     let batch_queries = vec![
@@ -3,15 +3,21 @@ use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct OHLCVData {
+    #[serde(alias = "Epoch")]
     pub epoch: i64,
+    #[serde(alias = "Open")]
     pub open: f32,
+    #[serde(alias = "High")]
     pub high: f32,
+    #[serde(alias = "Low")]
     pub low: f32,
+    #[serde(alias = "Close")]
     pub close: f32,
+    #[serde(alias = "Volume")]
     pub volume: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DataShape {
     pub name: String,
     pub data_type: String,
@@ -29,14 +35,14 @@ impl From<SymbolFormat> for i32 {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct NumpyMultiDataset {
     pub data: Option<NumpyDataset>,
     pub start_index: HashMap<String, i32>,
     pub lengths: HashMap<String, i32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct NumpyDataset {
     pub column_types: Vec<String>,
     pub column_names: Vec<String>,
@@ -44,31 +50,15 @@ pub struct NumpyDataset {
     pub length: i32,
 }
 
-impl Default for NumpyDataset {
-    fn default() -> Self {
-        Self {
-            column_types: Vec::new(),
-            column_names: Vec::new(),
-            column_data: Vec::new(),
-            length: 0,
-        }
-    }
-}
-
-impl Default for NumpyMultiDataset {
-    fn default() -> Self {
-        Self {
-            data: None,
-            start_index: HashMap::new(),
-            lengths: HashMap::new(),
-        }
-    }
-}
+/// 未指定类型时推送数据的默认解码形式
+pub type StreamData = HashMap<String, serde_json::Value>;
 
+/// WebSocket推送，`T` 可以是用户自定义记录（如 `OHLCVData`）、
+/// 保留msgpack宽度的 `HashMap<String, StreamValue>` 或列式的 `StreamColumns`
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StreamPayload {
+pub struct StreamPayload<T = StreamData> {
     pub key: String,
-    pub data: HashMap<String, serde_json::Value>,
+    pub data: T,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
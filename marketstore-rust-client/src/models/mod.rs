@@ -1,5 +1,9 @@
 pub mod data_types;
 pub mod requests;
+pub mod stream_data;
+pub mod stream_pattern;
 
 pub use data_types::*;
-pub use requests::*; 
\ No newline at end of file
+pub use requests::*;
+pub use stream_data::*;
+pub use stream_pattern::*; 
\ No newline at end of file
@@ -1,5 +1,6 @@
 use crate::error::{MarketStoreError, Result};
-use crate::models::{OHLCVData, DataShape};
+use crate::models::{OHLCVData, DataShape, StreamPattern};
+use crate::calendar::TradingCalendar;
 
 #[derive(Debug, Clone)]
 pub struct QueryRequest {
@@ -54,6 +55,16 @@ impl QueryRequestBuilder {
         self
     }
     
+    /// 将时间范围设为截至 `now` 的最近 `days` 个交易日，见 `TradingCalendar::last_trading_days`
+    pub fn last_trading_days(mut self, calendar: &TradingCalendar, days: usize, now: i64) -> Result<Self> {
+        let (start, end) = calendar.last_trading_days(days, now).ok_or_else(|| {
+            MarketStoreError::InvalidData(format!("No {} trading days before {} in calendar {}", days, now, calendar.name()))
+        })?;
+        self.start_time = Some(start);
+        self.end_time = Some(end);
+        Ok(self)
+    }
+    
     pub fn limit(mut self, limit: i32) -> Self {
         self.limit = Some(limit);
         self
@@ -141,7 +152,7 @@ impl DestroyRequest {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct StreamSubscription {
     pub streams: Vec<String>,
 }
@@ -160,4 +171,17 @@ impl StreamSubscription {
         self.streams.extend(streams);
         self
     }
+    
+    /// 按服务器的glob规则校验所有订阅模式，避免发送后才被拒绝
+    pub fn validate(&self) -> Result<()> {
+        validate_streams(&self.streams)
+    }
+}
+
+/// 校验订阅模式列表，返回第一个非法模式对应的错误
+pub fn validate_streams(streams: &[String]) -> Result<()> {
+    for stream in streams {
+        StreamPattern::parse(stream)?;
+    }
+    Ok(())
 } 
\ No newline at end of file
@@ -0,0 +1,336 @@
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::collections::HashMap;
+use std::fmt;
+use crate::error::{MarketStoreError, Result};
+
+/// 保留msgpack原始宽度的值
+///
+/// 服务器的 `stream.Push` 直接把Go类型交给msgpack编码，float32列编码为F32、int32列编码为I32，
+/// 小的正整数会被编码为fixint（解码为 `UInt8`）。这里按标记原样保留，不做隐式的宽度转换。
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamValue {
+    Nil,
+    Bool(bool),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    Float32(f32),
+    Float64(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<StreamValue>),
+    Map(Vec<(String, StreamValue)>),
+}
+
+impl StreamValue {
+    /// 任意宽度的整数转换为i64（u64超出范围时返回None）
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            StreamValue::Int8(v) => Some(v as i64),
+            StreamValue::Int16(v) => Some(v as i64),
+            StreamValue::Int32(v) => Some(v as i64),
+            StreamValue::Int64(v) => Some(v),
+            StreamValue::UInt8(v) => Some(v as i64),
+            StreamValue::UInt16(v) => Some(v as i64),
+            StreamValue::UInt32(v) => Some(v as i64),
+            StreamValue::UInt64(v) => i64::try_from(v).ok(),
+            _ => None,
+        }
+    }
+
+    /// 任意数值转换为f64
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            StreamValue::Float32(v) => Some(v as f64),
+            StreamValue::Float64(v) => Some(v),
+            StreamValue::UInt64(v) => Some(v as f64),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            StreamValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            StreamValue::Int8(_) | StreamValue::Int16(_) | StreamValue::Int32(_) | StreamValue::Int64(_)
+                | StreamValue::UInt8(_) | StreamValue::UInt16(_) | StreamValue::UInt32(_) | StreamValue::UInt64(_)
+        )
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, StreamValue::Float32(_) | StreamValue::Float64(_))
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(StreamValueVisitor)
+    }
+}
+
+struct StreamValueVisitor;
+
+impl<'de> Visitor<'de> for StreamValueVisitor {
+    type Value = StreamValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a msgpack value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Nil)
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Nil)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> std::result::Result<StreamValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        StreamValue::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Bool(v))
+    }
+
+    fn visit_i8<E: de::Error>(self, v: i8) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Int8(v))
+    }
+
+    fn visit_i16<E: de::Error>(self, v: i16) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Int16(v))
+    }
+
+    fn visit_i32<E: de::Error>(self, v: i32) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Int32(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Int64(v))
+    }
+
+    fn visit_u8<E: de::Error>(self, v: u8) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::UInt8(v))
+    }
+
+    fn visit_u16<E: de::Error>(self, v: u16) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::UInt16(v))
+    }
+
+    fn visit_u32<E: de::Error>(self, v: u32) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::UInt32(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::UInt64(v))
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Float32(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Float64(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Binary(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<StreamValue, E> {
+        Ok(StreamValue::Binary(v))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<StreamValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(StreamValue::Array(values))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<StreamValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<String, StreamValue>()? {
+            entries.push((key, value));
+        }
+        Ok(StreamValue::Map(entries))
+    }
+}
+
+/// 列式数据中的一列，元素类型按该列所有值中最宽的msgpack类型确定
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamColumn {
+    Bool(Vec<bool>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    UInt64(Vec<u64>),
+    Float32(Vec<f32>),
+    Float64(Vec<f64>),
+    String(Vec<String>),
+}
+
+impl StreamColumn {
+    /// 按以下规则推断列类型：
+    /// - 全部为不超过32位的有符号整数（或能放入i32的无符号整数）时为 `Int32`，否则为 `Int64`
+    /// - 全部为无符号整数且存在超出i64范围的值时为 `UInt64`
+    /// - 全部为F32时为 `Float32`；存在F64或整数与浮点混合时为 `Float64`
+    pub fn from_values(name: &str, values: &[StreamValue]) -> Result<Self> {
+        let mismatch = || MarketStoreError::InvalidData(format!("Column {} has mixed or unsupported value types", name));
+
+        if values.iter().all(|v| matches!(v, StreamValue::Bool(_))) && !values.is_empty() {
+            return Ok(StreamColumn::Bool(values.iter().map(|v| matches!(v, StreamValue::Bool(true))).collect()));
+        }
+        if values.iter().all(|v| v.as_str().is_some()) && !values.is_empty() {
+            return Ok(StreamColumn::String(values.iter().map(|v| v.as_str().unwrap().to_string()).collect()));
+        }
+        if !values.iter().all(|v| v.is_integer() || v.is_float()) {
+            return Err(mismatch());
+        }
+
+        if values.iter().all(|v| v.is_integer()) {
+            let fits_i32 = values.iter().all(|v| v.as_i64().and_then(|i| i32::try_from(i).ok()).is_some());
+            let narrow = values.iter().all(|v| matches!(
+                v,
+                StreamValue::Int8(_) | StreamValue::Int16(_) | StreamValue::Int32(_)
+                    | StreamValue::UInt8(_) | StreamValue::UInt16(_) | StreamValue::UInt32(_)
+            ));
+            if narrow && fits_i32 {
+                return Ok(StreamColumn::Int32(values.iter().map(|v| v.as_i64().unwrap() as i32).collect()));
+            }
+            if values.iter().all(|v| v.as_i64().is_some()) {
+                return Ok(StreamColumn::Int64(values.iter().map(|v| v.as_i64().unwrap()).collect()));
+            }
+            return values
+                .iter()
+                .map(|v| match *v {
+                    StreamValue::UInt64(u) => Ok(u),
+                    _ => v.as_i64().and_then(|i| u64::try_from(i).ok()).ok_or_else(mismatch),
+                })
+                .collect::<Result<Vec<u64>>>()
+                .map(StreamColumn::UInt64);
+        }
+
+        if values.iter().all(|v| matches!(v, StreamValue::Float32(_))) {
+            return Ok(StreamColumn::Float32(values.iter().map(|v| v.as_f64().unwrap() as f32).collect()));
+        }
+        Ok(StreamColumn::Float64(values.iter().map(|v| v.as_f64().unwrap()).collect()))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            StreamColumn::Bool(v) => v.len(),
+            StreamColumn::Int32(v) => v.len(),
+            StreamColumn::Int64(v) => v.len(),
+            StreamColumn::UInt64(v) => v.len(),
+            StreamColumn::Float32(v) => v.len(),
+            StreamColumn::Float64(v) => v.len(),
+            StreamColumn::String(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 第 `row` 行的值转换为f64（字符串列返回None）
+    pub fn get_f64(&self, row: usize) -> Option<f64> {
+        match self {
+            StreamColumn::Bool(v) => v.get(row).map(|b| if *b { 1.0 } else { 0.0 }),
+            StreamColumn::Int32(v) => v.get(row).map(|x| *x as f64),
+            StreamColumn::Int64(v) => v.get(row).map(|x| *x as f64),
+            StreamColumn::UInt64(v) => v.get(row).map(|x| *x as f64),
+            StreamColumn::Float32(v) => v.get(row).map(|x| *x as f64),
+            StreamColumn::Float64(v) => v.get(row).copied(),
+            StreamColumn::String(_) => None,
+        }
+    }
+}
+
+/// 列式推送数据：每个列名对应一个标量（单行推送）或数组（多行推送）
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamColumns {
+    pub columns: HashMap<String, StreamColumn>,
+    pub length: usize,
+}
+
+impl StreamColumns {
+    pub fn from_map(data: HashMap<String, StreamValue>) -> Result<Self> {
+        let mut columns = HashMap::with_capacity(data.len());
+        let mut length: Option<usize> = None;
+
+        for (name, value) in data {
+            let column = match value {
+                StreamValue::Array(values) => StreamColumn::from_values(&name, &values)?,
+                scalar => StreamColumn::from_values(&name, std::slice::from_ref(&scalar))?,
+            };
+            match length {
+                Some(len) if len != column.len() => {
+                    return Err(MarketStoreError::InvalidData(format!(
+                        "Column {} has {} rows, expected {}", name, column.len(), len
+                    )));
+                }
+                _ => length = Some(column.len()),
+            }
+            columns.insert(name, column);
+        }
+
+        Ok(Self {
+            columns,
+            length: length.unwrap_or(0),
+        })
+    }
+
+    pub fn column(&self, name: &str) -> Option<&StreamColumn> {
+        self.columns.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamColumns {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = HashMap::<String, StreamValue>::deserialize(deserializer)?;
+        StreamColumns::from_map(data).map_err(de::Error::custom)
+    }
+}
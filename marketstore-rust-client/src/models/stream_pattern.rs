@@ -0,0 +1,261 @@
+use std::fmt;
+use crate::error::{MarketStoreError, Result};
+
+/// 服务器的路径分隔符，`*` 与 `?` 不会跨越它
+const SEPARATOR: char = '/';
+
+/// 与服务器使用的 `glob.Compile(pattern, '/')` 语义一致的通配符模式
+///
+/// 支持 `*`（不跨越 `/`）、`**`（任意字符）、`?`、`[abc]`/`[a-z]`/`[!abc]`、`{a,b}` 以及 `\` 转义。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamPattern {
+    pattern: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    Single,
+    /// `*`
+    Any,
+    /// `**`
+    Super,
+    Class { negated: bool, items: Vec<ClassItem> },
+    Alternatives(Vec<Vec<Token>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+}
+
+impl StreamPattern {
+    /// 编译任意glob模式，不检查 `*/*/*` 形状
+    pub fn compile(pattern: &str) -> Result<Self> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pos = 0;
+        let tokens = parse_sequence(&chars, &mut pos, false)
+            .map_err(|e| MarketStoreError::InvalidData(format!("Invalid stream pattern {:?}: {}", pattern, e)))?;
+        Ok(Self { pattern: pattern.to_string(), tokens })
+    }
+
+    /// 编译订阅模式，并按服务器 `validStream` 的规则要求模式本身匹配 `*/*/*`
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let compiled = Self::compile(pattern)?;
+        if !Self::compile("*/*/*")?.matches(pattern) {
+            return Err(MarketStoreError::InvalidData(format!("{} is an invalid stream", pattern)));
+        }
+        Ok(compiled)
+    }
+
+    /// 原始模式字符串
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// 判断推送的key（如 `AAPL/1Min/OHLCV`）是否匹配该模式
+    pub fn matches(&self, key: &str) -> bool {
+        let input: Vec<char> = key.chars().collect();
+        match_tokens(&self.tokens, &input, &|rest| rest.is_empty())
+    }
+}
+
+impl fmt::Display for StreamPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+/// 将推送的key路由到请求它的订阅模式
+#[derive(Debug, Clone, Default)]
+pub struct StreamMatcher {
+    patterns: Vec<StreamPattern>,
+}
+
+impl StreamMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 由一组订阅模式构建，任何模式非法都会返回错误
+    pub fn from_patterns<I, S>(patterns: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut matcher = Self::new();
+        for pattern in patterns {
+            matcher.insert(pattern.as_ref())?;
+        }
+        Ok(matcher)
+    }
+
+    /// 添加模式，重复添加同一模式不生效
+    pub fn insert(&mut self, pattern: &str) -> Result<()> {
+        if !self.contains(pattern) {
+            self.patterns.push(StreamPattern::parse(pattern)?);
+        }
+        Ok(())
+    }
+
+    /// 移除模式，返回是否存在
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let before = self.patterns.len();
+        self.patterns.retain(|p| p.as_str() != pattern);
+        self.patterns.len() != before
+    }
+
+    pub fn contains(&self, pattern: &str) -> bool {
+        self.patterns.iter().any(|p| p.as_str() == pattern)
+    }
+
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.patterns.iter().map(|p| p.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// 与服务器 `Subscriber.Subscribed` 相同：任一模式匹配即为订阅
+    pub fn is_match(&self, key: &str) -> bool {
+        self.patterns.iter().any(|p| p.matches(key))
+    }
+
+    /// 返回匹配该key的全部模式，按添加顺序
+    pub fn matching(&self, key: &str) -> Vec<&str> {
+        self.patterns
+            .iter()
+            .filter(|p| p.matches(key))
+            .map(|p| p.as_str())
+            .collect()
+    }
+}
+
+fn parse_sequence(chars: &[char], pos: &mut usize, in_alternative: bool) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    while *pos < chars.len() {
+        let c = chars[*pos];
+        match c {
+            ',' | '}' if in_alternative => break,
+            '\\' => {
+                *pos += 1;
+                let escaped = *chars.get(*pos).ok_or("trailing escape character")?;
+                tokens.push(Token::Literal(escaped));
+                *pos += 1;
+            }
+            '*' => {
+                if chars.get(*pos + 1) == Some(&'*') {
+                    tokens.push(Token::Super);
+                    *pos += 2;
+                } else {
+                    tokens.push(Token::Any);
+                    *pos += 1;
+                }
+            }
+            '?' => {
+                tokens.push(Token::Single);
+                *pos += 1;
+            }
+            '[' => {
+                *pos += 1;
+                tokens.push(parse_class(chars, pos)?);
+            }
+            '{' => {
+                *pos += 1;
+                let mut alternatives = Vec::new();
+                loop {
+                    alternatives.push(parse_sequence(chars, pos, true)?);
+                    match chars.get(*pos) {
+                        Some(',') => *pos += 1,
+                        Some('}') => {
+                            *pos += 1;
+                            break;
+                        }
+                        _ => return Err("unclosed '{'".to_string()),
+                    }
+                }
+                tokens.push(Token::Alternatives(alternatives));
+            }
+            _ => {
+                tokens.push(Token::Literal(c));
+                *pos += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_class(chars: &[char], pos: &mut usize) -> std::result::Result<Token, String> {
+    let negated = chars.get(*pos) == Some(&'!');
+    if negated {
+        *pos += 1;
+    }
+
+    let mut items = Vec::new();
+    loop {
+        let c = *chars.get(*pos).ok_or("unclosed '['")?;
+        match c {
+            ']' => {
+                *pos += 1;
+                break;
+            }
+            '\\' => {
+                *pos += 1;
+                let escaped = *chars.get(*pos).ok_or("trailing escape character")?;
+                items.push(ClassItem::Char(escaped));
+                *pos += 1;
+            }
+            _ if chars.get(*pos + 1) == Some(&'-') && chars.get(*pos + 2).is_some_and(|&end| end != ']') => {
+                let end = chars[*pos + 2];
+                if end < c {
+                    return Err(format!("invalid range {}-{}", c, end));
+                }
+                items.push(ClassItem::Range(c, end));
+                *pos += 3;
+            }
+            _ => {
+                items.push(ClassItem::Char(c));
+                *pos += 1;
+            }
+        }
+    }
+
+    if items.is_empty() {
+        return Err("empty character class".to_string());
+    }
+    Ok(Token::Class { negated, items })
+}
+
+/// 回溯匹配；`rest` 为匹配完 `tokens` 之后对剩余输入的判断，用于支持 `{a,b}` 后接其它模式
+fn match_tokens(tokens: &[Token], input: &[char], rest: &dyn Fn(&[char]) -> bool) -> bool {
+    let Some((token, tail)) = tokens.split_first() else {
+        return rest(input);
+    };
+
+    match token {
+        Token::Literal(c) => input.first() == Some(c) && match_tokens(tail, &input[1..], rest),
+        Token::Single => {
+            input.first().is_some_and(|&c| c != SEPARATOR) && match_tokens(tail, &input[1..], rest)
+        }
+        Token::Class { negated, items } => {
+            input.first().is_some_and(|&c| {
+                items.iter().any(|item| match *item {
+                    ClassItem::Char(x) => x == c,
+                    ClassItem::Range(lo, hi) => lo <= c && c <= hi,
+                }) != *negated
+            }) && match_tokens(tail, &input[1..], rest)
+        }
+        Token::Any => {
+            let limit = input.iter().position(|&c| c == SEPARATOR).unwrap_or(input.len());
+            (0..=limit).any(|i| match_tokens(tail, &input[i..], rest))
+        }
+        Token::Super => (0..=input.len()).any(|i| match_tokens(tail, &input[i..], rest)),
+        Token::Alternatives(alternatives) => alternatives
+            .iter()
+            .any(|alternative| match_tokens(alternative, input, &|after| match_tokens(tail, after, rest))),
+    }
+}
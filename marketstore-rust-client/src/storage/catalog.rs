@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{NumpyDataset, NumpyMultiDataset, QueryRequest},
+    storage::DataFile,
+    utils::{column_type_width, year_start_epoch},
+};
+
+/// 每层目录中记录该层类别的文件，与服务器 `catalog` 一致
+const CATEGORY_FILE: &str = "category_name";
+
+/// 离线读取的数据目录，对应服务器 `catalog.Directory`
+///
+/// 目录按 `Symbol/Timeframe/AttributeGroup/Year` 分层，每层的 `category_name` 文件记录类别名，
+/// 叶子目录中为 `2024.bin` 形式的年份文件。缺少 `category_name` 的子目录与服务器一样被忽略。
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    root: PathBuf,
+    categories: Vec<String>,
+    /// key -> 年份 -> 年份文件路径
+    files: BTreeMap<String, BTreeMap<i32, PathBuf>>,
+    variable_compression: bool,
+}
+
+impl Catalog {
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        let mut catalog = Self {
+            root: root.clone(),
+            categories: Vec::new(),
+            files: BTreeMap::new(),
+            variable_compression: true,
+        };
+        let category = read_category(&root)?.ok_or_else(|| {
+            MarketStoreError::InvalidData(format!("{} not found under {}", CATEGORY_FILE, root.display()))
+        })?;
+        catalog.load(&root, Vec::new(), category)?;
+        Ok(catalog)
+    }
+
+    /// 对应服务器配置 `disable_variable_compression: true` 时传入false
+    pub fn with_variable_compression(mut self, enabled: bool) -> Self {
+        self.variable_compression = enabled;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 各层的类别名，如 `["Symbol", "Timeframe", "AttributeGroup", "Year"]`
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// 所有bucket key，如 `AAPL/1Min/OHLCV`，按字典序
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// key下已有年份文件的年份，升序
+    pub fn years(&self, key: &str) -> Vec<i32> {
+        self.files.get(key).map(|years| years.keys().copied().collect()).unwrap_or_default()
+    }
+
+    /// 所有年份文件的key、年份与路径
+    pub fn files(&self) -> impl Iterator<Item = (&str, i32, &Path)> {
+        self.files
+            .iter()
+            .flat_map(|(key, years)| years.iter().map(move |(year, path)| (key.as_str(), *year, path.as_path())))
+    }
+
+    pub fn variable_compression(&self) -> bool {
+        self.variable_compression
+    }
+
+    /// 打开key下某一年的数据文件
+    pub fn data_file(&self, key: &str, year: i32) -> Result<DataFile> {
+        let path = self
+            .files
+            .get(key)
+            .and_then(|years| years.get(&year))
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("No data file for {} in {}", key, year)))?;
+        Ok(DataFile::open(path)?.with_variable_compression(self.variable_compression))
+    }
+
+    /// 按查询请求读取数据，返回与网络客户端 `query` 相同的数据集
+    ///
+    /// 时间范围两端均包含；`limit_record_count` 为空时不限制条数，`limit_from_start` 为false时取最后N条；
+    /// `columns` 非空时只保留这些列，Epoch总是保留。
+    pub fn query(&self, request: &QueryRequest) -> Result<NumpyMultiDataset> {
+        let key = request.destination.as_str();
+        let years = self
+            .files
+            .get(key)
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("Unknown key: {}", key)))?;
+
+        let mut dataset: Option<NumpyDataset> = None;
+        for &year in years.keys() {
+            let before_start = request.epoch_start.is_some_and(|start| year_start_epoch(year + 1) <= start);
+            let after_end = request.epoch_end.is_some_and(|end| year_start_epoch(year) > end);
+            if before_start || after_end {
+                continue;
+            }
+
+            let part = self.data_file(key, year)?.read(request.epoch_start, request.epoch_end)?;
+            dataset = Some(match dataset {
+                None => part,
+                Some(mut dataset) => {
+                    if dataset.column_names != part.column_names || dataset.column_types != part.column_types {
+                        return Err(MarketStoreError::InvalidData(format!(
+                            "Data shapes of {} differ between years",
+                            key
+                        )));
+                    }
+                    for (column, data) in dataset.column_data.iter_mut().zip(part.column_data) {
+                        column.extend(data);
+                    }
+                    dataset.length += part.length;
+                    dataset
+                }
+            });
+        }
+
+        let dataset = match dataset {
+            Some(dataset) => dataset,
+            None => {
+                // 范围内没有年份文件时仍返回带列结构的空数据集
+                let latest = *years.keys().next_back().unwrap();
+                let shapes = self.data_file(key, latest)?.header().result_shapes();
+                NumpyDataset {
+                    column_types: shapes.iter().map(|shape| shape.data_type.clone()).collect(),
+                    column_data: vec![Vec::new(); shapes.len()],
+                    column_names: shapes.into_iter().map(|shape| shape.name).collect(),
+                    length: 0,
+                }
+            }
+        };
+        let dataset = select_columns(limit_rows(dataset, request)?, &request.columns)?;
+
+        let length = dataset.length;
+        Ok(NumpyMultiDataset {
+            data: Some(dataset),
+            start_index: HashMap::from([(key.to_string(), 0)]),
+            lengths: HashMap::from([(key.to_string(), length)]),
+        })
+    }
+
+    fn load(&mut self, dir: &Path, items: Vec<String>, category: String) -> Result<()> {
+        if self.categories.len() <= items.len() {
+            self.categories.push(category);
+        }
+
+        let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            if entry.file_type()?.is_dir() {
+                if name == "metadata.db" {
+                    continue;
+                }
+                match read_category(&path)? {
+                    Some(category) => {
+                        let mut items = items.clone();
+                        items.push(name);
+                        self.load(&path, items, category)?;
+                    }
+                    None => tracing::warn!("{} not found under {}, directory ignored", CATEGORY_FILE, path.display()),
+                }
+            } else if let Some(year) = name.strip_suffix(".bin") {
+                let year: i32 = year
+                    .parse()
+                    .map_err(|_| MarketStoreError::InvalidData(format!("Invalid year file name: {}", path.display())))?;
+                self.files.entry(items.join("/")).or_default().insert(year, path);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_category(dir: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(dir.join(CATEGORY_FILE)) {
+        Ok(category) => Ok(Some(category.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 按 `limit_record_count` 与 `limit_from_start` 截取行，与服务器一致不大于0表示不限制
+fn limit_rows(dataset: NumpyDataset, request: &QueryRequest) -> Result<NumpyDataset> {
+    let length = dataset.length.max(0) as usize;
+    let limit = match request.limit_record_count {
+        Some(limit) if limit > 0 && (limit as usize) < length => limit as usize,
+        _ => return Ok(dataset),
+    };
+    let rows = if request.limit_from_start { 0..limit } else { length - limit..length };
+
+    let column_data = dataset
+        .column_types
+        .iter()
+        .zip(dataset.column_data)
+        .map(|(column_type, data)| {
+            let width = column_type_width(column_type)?;
+            Ok(data[rows.start * width..rows.end * width].to_vec())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(NumpyDataset {
+        column_types: dataset.column_types,
+        column_names: dataset.column_names,
+        column_data,
+        length: limit as i32,
+    })
+}
+
+/// 只保留Epoch与请求的列，顺序与文件中一致
+fn select_columns(dataset: NumpyDataset, columns: &[String]) -> Result<NumpyDataset> {
+    if columns.is_empty() {
+        return Ok(dataset);
+    }
+    if let Some(missing) = columns.iter().find(|column| !dataset.column_names.contains(column)) {
+        return Err(MarketStoreError::InvalidData(format!("Unknown column: {}", missing)));
+    }
+
+    let mut selected = NumpyDataset { length: dataset.length, ..Default::default() };
+    for ((name, column_type), data) in dataset.column_names.into_iter().zip(dataset.column_types).zip(dataset.column_data) {
+        if name == "Epoch" || columns.contains(&name) {
+            selected.column_names.push(name);
+            selected.column_types.push(column_type);
+            selected.column_data.push(data);
+        }
+    }
+    Ok(selected)
+}
@@ -0,0 +1,404 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{DataShape, NumpyDataset},
+    storage::{ByteReader, RecordType},
+    utils::{column_type_width, element_type_code, element_type_name, year_start_epoch, Timeframe, INTERVAL_TICKS_LEN},
+};
+
+/// 年份文件头长度，与服务器 `io.Headersize` 一致
+pub const DATA_FILE_HEADER_SIZE: usize = 37024;
+/// 当前文件格式版本，与服务器 `io.FileinfoVersion` 一致
+pub const DATA_FILE_VERSION: i64 = 2;
+
+const DESCRIPTION_LEN: usize = 256;
+const ELEMENT_NAME_LEN: usize = 32;
+const MAX_ELEMENTS: usize = 1024;
+/// 定长部分：版本、描述、年份、周期、记录类型、列数、记录长度与保留字段
+const ELEMENT_NAMES_OFFSET: usize = 8 + DESCRIPTION_LEN + 6 * 8;
+const ELEMENT_TYPES_OFFSET: usize = ELEMENT_NAMES_OFFSET + MAX_ELEMENTS * ELEMENT_NAME_LEN;
+
+/// 可变长数据的主区记录 `{index, offset, len}` 长度
+pub(crate) const INDIRECT_RECORD_LEN: usize = 24;
+const EPOCH_LEN: usize = 8;
+/// 定长记录列宽按64位机器字长对齐，与服务器 `AlignedSize` 一致
+const WORD_LEN: usize = 8;
+
+/// 年份文件头，对应服务器 `io.Header`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataFileHeader {
+    pub version: i64,
+    pub description: String,
+    pub year: i32,
+    pub timeframe: Duration,
+    pub record_type: RecordType,
+    /// 主区单条记录长度：定长记录为8字节index加按机器字长对齐的列宽，可变长记录为24
+    pub record_length: i32,
+    /// 不含Epoch与Nanoseconds的列，类型为numpy类型字符串
+    pub data_shapes: Vec<DataShape>,
+}
+
+impl DataFileHeader {
+    /// 新建定长记录文件头，与服务器 `NewTimeBucketInfo` 一致：`data_shapes` 中的Epoch列被忽略，
+    /// 记录长度为按机器字长对齐的列宽加8字节index
+    pub fn new(year: i32, timeframe: &Timeframe, data_shapes: &[DataShape], description: &str) -> Result<Self> {
+        let data_shapes: Vec<DataShape> = data_shapes.iter().filter(|shape| shape.name != "Epoch").cloned().collect();
+        if data_shapes.is_empty() || data_shapes.len() > MAX_ELEMENTS {
+            return Err(MarketStoreError::InvalidData(format!("Invalid number of data shapes: {}", data_shapes.len())));
+        }
+        if let Some(shape) = data_shapes.iter().find(|shape| shape.name.is_empty() || shape.name.len() > ELEMENT_NAME_LEN) {
+            return Err(MarketStoreError::InvalidData(format!("Invalid column name: {:?}", shape.name)));
+        }
+        if description.len() > DESCRIPTION_LEN {
+            return Err(MarketStoreError::InvalidData(format!("Description longer than {} bytes", DESCRIPTION_LEN)));
+        }
+
+        let mut header = Self {
+            version: DATA_FILE_VERSION,
+            description: description.to_string(),
+            year,
+            timeframe: timeframe.duration,
+            record_type: RecordType::Fixed,
+            record_length: 0,
+            data_shapes,
+        };
+        header.record_length = (header.fields_length()?.next_multiple_of(WORD_LEN) + EPOCH_LEN) as i32;
+        Ok(header)
+    }
+
+    /// 编码为37024字节的文件头，与服务器 `WriteHeader` 一致
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(DATA_FILE_HEADER_SIZE);
+        buf.extend_from_slice(&self.version.to_le_bytes());
+        let mut description = self.description.as_bytes().to_vec();
+        description.resize(DESCRIPTION_LEN, 0);
+        buf.extend_from_slice(&description);
+        for value in [
+            self.year as i64,
+            self.timeframe.as_nanos() as i64,
+            self.record_type.code() as i64,
+            self.data_shapes.len() as i64,
+            self.record_length as i64,
+            0,
+        ] {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        buf.resize(DATA_FILE_HEADER_SIZE, 0);
+
+        for (i, shape) in self.data_shapes.iter().enumerate() {
+            let name_offset = ELEMENT_NAMES_OFFSET + i * ELEMENT_NAME_LEN;
+            let name = &shape.name.as_bytes()[..shape.name.len().min(ELEMENT_NAME_LEN)];
+            buf[name_offset..name_offset + name.len()].copy_from_slice(name);
+            buf[ELEMENT_TYPES_OFFSET + i] = element_type_code(&shape.data_type)?;
+        }
+        Ok(buf)
+    }
+
+    /// 预分配的文件大小，与服务器 `FileSize` 一致：文件头加全年周期数条记录
+    pub fn file_size(&self) -> u64 {
+        let year_seconds = (year_start_epoch(self.year + 1) - year_start_epoch(self.year)) as u64;
+        let records = year_seconds * 1_000_000_000 / self.timeframe.as_nanos().max(1) as u64;
+        DATA_FILE_HEADER_SIZE as u64 + records * self.record_length as u64
+    }
+
+    /// 与 `file_size` 相同，文件头损坏导致溢出时返回None
+    pub(crate) fn checked_file_size(&self) -> Option<u64> {
+        let year_seconds = u64::try_from(year_start_epoch(self.year.checked_add(1)?) - year_start_epoch(self.year)).ok()?;
+        let timeframe_nanos = u64::try_from(self.timeframe.as_nanos().max(1)).ok()?;
+        let records = year_seconds.checked_mul(1_000_000_000)? / timeframe_nanos;
+        records.checked_mul(u64::try_from(self.record_length).ok()?)?.checked_add(DATA_FILE_HEADER_SIZE as u64)
+    }
+
+    /// 按服务器 `readHeader` 的布局解析文件头
+    pub fn parse(buf: &[u8]) -> Result<Self> {
+        if buf.len() < DATA_FILE_HEADER_SIZE {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Data file header truncated: {} of {} bytes",
+                buf.len(),
+                DATA_FILE_HEADER_SIZE
+            )));
+        }
+
+        let mut reader = ByteReader::new(buf, "Data file header");
+        let version = reader.i64()?;
+        let description = trim_nul(reader.take(DESCRIPTION_LEN)?);
+        let year = reader.i64()? as i32;
+        let timeframe = reader.i64()?;
+        let record_type = RecordType::from_code(reader.i64()? as i8)?;
+        let n_elements = reader.i64()?;
+        let record_length = reader.i64()? as i32;
+
+        if timeframe <= 0 {
+            return Err(MarketStoreError::InvalidData(format!("Invalid timeframe in data file header: {}ns", timeframe)));
+        }
+        if !(0..=MAX_ELEMENTS as i64).contains(&n_elements) {
+            return Err(MarketStoreError::InvalidData(format!("Invalid element count in data file header: {}", n_elements)));
+        }
+
+        let data_shapes = (0..n_elements as usize)
+            .map(|i| {
+                let name_offset = ELEMENT_NAMES_OFFSET + i * ELEMENT_NAME_LEN;
+                Ok(DataShape {
+                    name: trim_nul(&buf[name_offset..name_offset + ELEMENT_NAME_LEN]),
+                    data_type: element_type_name(buf[ELEMENT_TYPES_OFFSET + i])?.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version,
+            description,
+            year,
+            timeframe: Duration::from_nanos(timeframe as u64),
+            record_type,
+            record_length,
+            data_shapes,
+        })
+    }
+
+    /// 每天的周期数，与服务器 `GetIntervals` 一致
+    pub fn intervals(&self) -> i64 {
+        self.timeframe_for_index().intervals_per_day()
+    }
+
+    /// 可变长记录的单行长度（列宽加interval ticks），定长记录为0
+    pub fn variable_record_length(&self) -> Result<i32> {
+        match self.record_type {
+            RecordType::Fixed => Ok(0),
+            RecordType::Variable => Ok((self.fields_length()? + INTERVAL_TICKS_LEN) as i32),
+        }
+    }
+
+    /// 查询结果中的列：Epoch在前，可变长记录追加Nanoseconds
+    pub fn result_shapes(&self) -> Vec<DataShape> {
+        let mut shapes = Vec::with_capacity(self.data_shapes.len() + 2);
+        shapes.push(DataShape { name: "Epoch".to_string(), data_type: "i8".to_string() });
+        shapes.extend(self.data_shapes.iter().cloned());
+        if self.record_type == RecordType::Variable {
+            shapes.push(DataShape { name: "Nanoseconds".to_string(), data_type: "i4".to_string() });
+        }
+        shapes
+    }
+
+    pub(crate) fn fields_length(&self) -> Result<usize> {
+        self.data_shapes.iter().map(|shape| column_type_width(&shape.data_type)).sum()
+    }
+
+    /// 仅用于index与epoch换算，名称不参与计算
+    pub(crate) fn timeframe_for_index(&self) -> Timeframe {
+        Timeframe {
+            name: format!("{}Sec", self.timeframe.as_secs()),
+            duration: self.timeframe,
+        }
+    }
+}
+
+/// 离线读取的年份数据文件，如 `data/AAPL/1Min/OHLCV/2024.bin`
+#[derive(Debug, Clone)]
+pub struct DataFile {
+    path: PathBuf,
+    header: DataFileHeader,
+    variable_compression: bool,
+}
+
+impl DataFile {
+    /// 读取并解析文件头；可变长数据默认按snappy压缩读取，与服务器默认配置一致
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut header = vec![0u8; DATA_FILE_HEADER_SIZE];
+        let mut file = std::fs::File::open(&path)?;
+        std::io::Read::read_exact(&mut file, &mut header).map_err(|e| {
+            MarketStoreError::InvalidData(format!("Failed to read header of {}: {}", path.display(), e))
+        })?;
+        Ok(Self {
+            header: DataFileHeader::parse(&header)?,
+            path,
+            variable_compression: true,
+        })
+    }
+
+    /// 对应服务器配置 `disable_variable_compression: true` 时传入false
+    pub fn with_variable_compression(mut self, enabled: bool) -> Self {
+        self.variable_compression = enabled;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> &DataFileHeader {
+        &self.header
+    }
+
+    /// 读取epoch在 `[start, end]` 内的记录，列与 `DataFileHeader::result_shapes` 一致
+    ///
+    /// 只读取范围对应的主区记录，可变长数据按指针逐段读取。
+    pub fn read(&self, start: Option<i64>, end: Option<i64>) -> Result<NumpyDataset> {
+        let shapes = self.header.result_shapes();
+        let mut columns = ColumnsBuilder::new(&shapes)?;
+        let in_range = |epoch: i64| start.is_none_or(|start| epoch >= start) && end.is_none_or(|end| epoch <= end);
+
+        let timeframe = self.header.timeframe_for_index();
+        let record_length = self.header.record_length as usize;
+        let valid_length = match self.header.record_type {
+            RecordType::Fixed => record_length >= EPOCH_LEN,
+            RecordType::Variable => record_length == INDIRECT_RECORD_LEN,
+        };
+        if !valid_length {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Invalid record length {} in {}",
+                record_length,
+                self.path.display()
+            )));
+        }
+
+        let mut file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        // 主区不超过文件实际长度，可变长数据区不会被当作记录
+        let primary_end = self.header.checked_file_size().unwrap_or(file_len).min(file_len);
+        let slots = primary_end.saturating_sub(DATA_FILE_HEADER_SIZE as u64) / record_length as u64;
+        let Some((first, last)) = self.index_range(start, end, slots as i64) else {
+            return Ok(columns.finish());
+        };
+
+        let mut records = vec![0u8; (last - first + 1) as usize * record_length];
+        file.seek(SeekFrom::Start(DATA_FILE_HEADER_SIZE as u64 + (first - 1) as u64 * record_length as u64))?;
+        file.read_exact(&mut records)?;
+        for record in records.chunks_exact(record_length) {
+            let index = i64::from_le_bytes(record[..EPOCH_LEN].try_into().unwrap());
+            // index为0的位置尚未写入
+            if index == 0 {
+                continue;
+            }
+            let epoch = timeframe.index_to_epoch(index, self.header.year);
+
+            match self.header.record_type {
+                RecordType::Fixed => {
+                    if in_range(epoch) {
+                        columns.push_row(epoch, &record[EPOCH_LEN..], None)?;
+                    }
+                }
+                RecordType::Variable => {
+                    let data = self.variable_data(&mut file, file_len, record)?;
+                    let row_len = self.header.variable_record_length()? as usize;
+                    if !data.len().is_multiple_of(row_len) {
+                        return Err(MarketStoreError::InvalidData(format!(
+                            "Variable data of {} bytes at index {} is not a multiple of row length {} in {}",
+                            data.len(), index, row_len, self.path.display()
+                        )));
+                    }
+                    for row in data.chunks_exact(row_len) {
+                        let (fields, ticks) = row.split_at(row_len - INTERVAL_TICKS_LEN);
+                        let (seconds, nanoseconds) = timeframe.ticks_to_time(epoch, u32::from_le_bytes(ticks.try_into().unwrap()));
+                        if in_range(seconds) {
+                            columns.push_row(seconds, fields, Some(nanoseconds))?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(columns.finish())
+    }
+
+    /// `[start, end]` 覆盖的index范围（从1开始，不超过 `slots`），与本年无交集时返回None
+    fn index_range(&self, start: Option<i64>, end: Option<i64>, slots: i64) -> Option<(i64, i64)> {
+        let timeframe = self.header.timeframe_for_index();
+        // 周期不足1秒时无法按秒换算index，读取整个主区
+        let locate = |epoch: i64| (self.header.timeframe.as_secs() > 0).then(|| timeframe.epoch_to_index(epoch));
+        let first = match start.and_then(locate) {
+            Some((year, _)) if year > self.header.year => return None,
+            Some((year, index)) if year == self.header.year => index.max(1),
+            _ => 1,
+        };
+        let last = match end.and_then(locate) {
+            Some((year, _)) if year < self.header.year => return None,
+            Some((year, index)) if year == self.header.year => index.min(slots),
+            _ => slots,
+        };
+        (first <= last).then_some((first, last))
+    }
+
+    /// 按主区记录 `{index, offset, len}` 取出某个interval的全部可变长行
+    fn variable_data(&self, file: &mut File, file_len: u64, record: &[u8]) -> Result<Vec<u8>> {
+        let mut reader = ByteReader::new(record, "Indirect record");
+        reader.i64()?;
+        let offset = reader.i64()?;
+        let len = reader.i64()?;
+        let in_file = offset >= 0 && len >= 0 && offset.checked_add(len).is_some_and(|end| end as u64 <= file_len);
+        if !in_file {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Variable data at offset {} with length {} is out of bounds in {}",
+                offset, len, self.path.display()
+            )));
+        }
+
+        let mut data = vec![0u8; len as usize];
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.read_exact(&mut data)?;
+        if self.variable_compression {
+            snap::raw::Decoder::new()
+                .decompress_vec(&data)
+                .map_err(|e| MarketStoreError::InvalidData(format!("Failed to decompress variable data in {}: {}", self.path.display(), e)))
+        } else {
+            Ok(data)
+        }
+    }
+}
+
+/// 逐行累积为numpy列式数据
+struct ColumnsBuilder {
+    shapes: Vec<DataShape>,
+    widths: Vec<usize>,
+    data: Vec<Vec<u8>>,
+    length: usize,
+}
+
+impl ColumnsBuilder {
+    fn new(shapes: &[DataShape]) -> Result<Self> {
+        Ok(Self {
+            widths: shapes.iter().map(|shape| column_type_width(&shape.data_type)).collect::<Result<_>>()?,
+            data: vec![Vec::new(); shapes.len()],
+            shapes: shapes.to_vec(),
+            length: 0,
+        })
+    }
+
+    /// `fields` 为不含Epoch的列数据，定长记录末尾可能带有对齐填充
+    fn push_row(&mut self, epoch: i64, fields: &[u8], nanoseconds: Option<i32>) -> Result<()> {
+        self.data[0].extend_from_slice(&epoch.to_le_bytes());
+        let field_columns = self.shapes.len() - 1 - usize::from(nanoseconds.is_some());
+        let mut pos = 0;
+        for column in 1..=field_columns {
+            let width = self.widths[column];
+            let value = fields
+                .get(pos..pos + width)
+                .ok_or_else(|| MarketStoreError::InvalidData(format!("Record too short for column {}", self.shapes[column].name)))?;
+            self.data[column].extend_from_slice(value);
+            pos += width;
+        }
+        if let Some(nanoseconds) = nanoseconds {
+            self.data[field_columns + 1].extend_from_slice(&nanoseconds.to_le_bytes());
+        }
+        self.length += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> NumpyDataset {
+        NumpyDataset {
+            column_types: self.shapes.iter().map(|shape| shape.data_type.clone()).collect(),
+            column_names: self.shapes.into_iter().map(|shape| shape.name).collect(),
+            column_data: self.data,
+            length: self.length as i32,
+        }
+    }
+}
+
+fn trim_nul(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
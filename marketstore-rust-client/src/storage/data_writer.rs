@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{DataShape, NumpyDataset, OHLCVData},
+    storage::{DataFileHeader, DATA_FILE_HEADER_SIZE},
+    utils::{column_type_width, create_numpy_dataset_from_ohlcv, split_bucket_key, Timeframe},
+};
+
+/// 默认的key类别，对应服务器 `TimeBucketKey` 的 `Symbol/Timeframe/AttributeGroup`
+const KEY_CATEGORIES: [&str; 3] = ["Symbol", "Timeframe", "AttributeGroup"];
+/// 与服务器通过gRPC写入创建的文件一致
+const DEFAULT_DESCRIPTION: &str = "Default";
+
+/// 离线生成定长记录的年份文件，用于在停机的服务器数据目录中批量预置历史数据
+///
+/// 创建与服务器 `AddTimeBucket` 相同的目录结构与 `category_name` 文件，新年份文件写入文件头后
+/// 按 `FileSize` 预分配，记录写在 `(index - 1) * record_length` 处。已有的年份文件会被复用，
+/// 但其文件头须与本次的数据结构一致。
+pub struct DataFileWriter {
+    dir: PathBuf,
+    key: String,
+    timeframe: Timeframe,
+    data_shapes: Vec<DataShape>,
+    files: BTreeMap<i32, (File, DataFileHeader)>,
+}
+
+impl DataFileWriter {
+    /// `key` 如 `AAPL/1Min/OHLCV`；`data_shapes` 可含Epoch列，写入文件头时会被忽略
+    pub fn create<P: AsRef<Path>>(root: P, key: &str, data_shapes: &[DataShape]) -> Result<Self> {
+        let (symbol, timeframe, attr_group) = split_bucket_key(key)?;
+        let items = [symbol, timeframe, attr_group];
+        let timeframe = Timeframe::parse(timeframe)?;
+        // 提前校验数据结构，避免创建目录后才失败
+        let template = DataFileHeader::new(1970, &timeframe, data_shapes, DEFAULT_DESCRIPTION)?;
+
+        let mut dir = root.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        for (item, category) in items.iter().zip(KEY_CATEGORIES) {
+            std::fs::write(dir.join("category_name"), category)?;
+            dir.push(item);
+            std::fs::create_dir_all(&dir)?;
+        }
+        std::fs::write(dir.join("category_name"), "Year")?;
+
+        Ok(Self {
+            dir,
+            key: key.to_string(),
+            timeframe,
+            data_shapes: template.data_shapes,
+            files: BTreeMap::new(),
+        })
+    }
+
+    /// 写入含Epoch列的数据集，列按名称匹配，返回写入的行数
+    ///
+    /// 同一index的多行以最后一行为准；index为0的时间点（1D周期的1月1日）在服务器文件中没有记录位置，返回错误。
+    pub fn write(&mut self, dataset: &NumpyDataset) -> Result<usize> {
+        let length = dataset.length.max(0) as usize;
+        let epochs = self.column(dataset, "Epoch", "i8", length)?;
+        let fields = self
+            .data_shapes
+            .iter()
+            .map(|shape| Ok((self.column(dataset, &shape.name, &shape.data_type, length)?, column_type_width(&shape.data_type)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // 年份 -> index -> 不含index的记录
+        let mut records: BTreeMap<i32, BTreeMap<i64, Vec<u8>>> = BTreeMap::new();
+        for row in 0..length {
+            let epoch = i64::from_le_bytes(epochs[row * 8..row * 8 + 8].try_into().unwrap());
+            let (year, index) = self.timeframe.epoch_to_index(epoch);
+            if index < 1 {
+                return Err(MarketStoreError::InvalidData(format!(
+                    "Epoch {} maps to index {} which has no record slot in {}",
+                    epoch, index, self.key
+                )));
+            }
+            let record = fields
+                .iter()
+                .flat_map(|(column, width)| &column[row * width..(row + 1) * width])
+                .copied()
+                .collect();
+            records.entry(year).or_default().insert(index, record);
+        }
+
+        for (year, records) in records {
+            self.write_year(year, records)?;
+        }
+        Ok(length)
+    }
+
+    /// 写入OHLCV数据，bucket的数据结构须为 Open/High/Low/Close/Volume 的f4列
+    pub fn write_ohlcv(&mut self, data: &[OHLCVData]) -> Result<usize> {
+        self.write(&create_numpy_dataset_from_ohlcv(data))
+    }
+
+    /// 将所有年份文件刷到磁盘
+    pub fn finish(self) -> Result<()> {
+        for (file, _) in self.files.values() {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// 连续index的记录合并为一次写入
+    fn write_year(&mut self, year: i32, records: BTreeMap<i64, Vec<u8>>) -> Result<()> {
+        let (file, header) = self.year_file(year)?;
+        let record_length = header.record_length as usize;
+
+        let mut run_start: Option<i64> = None;
+        let mut run = Vec::new();
+        let mut previous = 0;
+        for (index, fields) in records {
+            if run_start.is_some() && index != previous + 1 {
+                write_run(file, run_start.take().unwrap(), &run, record_length)?;
+                run.clear();
+            }
+            run_start.get_or_insert(index);
+            run.extend_from_slice(&index.to_le_bytes());
+            run.extend_from_slice(&fields);
+            // 对齐填充
+            run.resize(run.len() + record_length - 8 - fields.len(), 0);
+            previous = index;
+        }
+        if let Some(start) = run_start {
+            write_run(file, start, &run, record_length)?;
+        }
+        Ok(())
+    }
+
+    fn year_file(&mut self, year: i32) -> Result<&mut (File, DataFileHeader)> {
+        if !self.files.contains_key(&year) {
+            let path = self.dir.join(format!("{}.bin", year));
+            let header = DataFileHeader::new(year, &self.timeframe, &self.data_shapes, DEFAULT_DESCRIPTION)?;
+            let entry = if path.exists() {
+                let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+                let mut existing = vec![0u8; DATA_FILE_HEADER_SIZE];
+                file.read_exact(&mut existing)?;
+                let existing = DataFileHeader::parse(&existing)?;
+                if existing.timeframe != header.timeframe
+                    || existing.record_type != header.record_type
+                    || existing.record_length != header.record_length
+                    || existing.data_shapes != header.data_shapes
+                {
+                    return Err(MarketStoreError::InvalidData(format!(
+                        "Existing data file {} does not match the data shapes of {}",
+                        path.display(),
+                        self.key
+                    )));
+                }
+                (file, existing)
+            } else {
+                let mut file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+                file.write_all(&header.encode()?)?;
+                file.set_len(header.file_size())?;
+                tracing::debug!("Created data file {}", path.display());
+                (file, header)
+            };
+            self.files.insert(year, entry);
+        }
+        Ok(self.files.get_mut(&year).unwrap())
+    }
+
+    fn column<'a>(&self, dataset: &'a NumpyDataset, name: &str, data_type: &str, length: usize) -> Result<&'a [u8]> {
+        let position = dataset
+            .column_names
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("Missing column {} for {}", name, self.key)))?;
+        if dataset.column_types.get(position).map(String::as_str) != Some(data_type) {
+            return Err(MarketStoreError::InvalidData(format!("Column {} of {} must be {}", name, self.key, data_type)));
+        }
+        let data = dataset
+            .column_data
+            .get(position)
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("Missing data for column {}", name)))?;
+        let expected = column_type_width(data_type)? * length;
+        data.get(..expected).ok_or_else(|| {
+            MarketStoreError::InvalidData(format!("Column {} has {} bytes, expected {}", name, data.len(), expected))
+        })
+    }
+}
+
+fn write_run(file: &mut File, start_index: i64, run: &[u8], record_length: usize) -> Result<()> {
+    let offset = DATA_FILE_HEADER_SIZE as u64 + (start_index as u64 - 1) * record_length as u64;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(run)?;
+    Ok(())
+}
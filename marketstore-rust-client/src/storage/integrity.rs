@@ -0,0 +1,474 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use serde::Serialize;
+use crate::{
+    models::DataShape,
+    storage::{Catalog, DataFileHeader, RecordType, DATA_FILE_HEADER_SIZE, DATA_FILE_VERSION, INDIRECT_RECORD_LEN},
+    utils::{split_bucket_key, Timeframe, INTERVAL_TICKS_LEN},
+};
+
+/// 每个文件最多记录的问题数，超出部分只计数
+const MAX_ISSUES_PER_FILE: usize = 100;
+/// 与服务器 `integrity` 工具的默认值一致：文件头之外分12块
+pub const DEFAULT_CHECKSUM_CHUNKS: usize = 12;
+/// 逐块读取文件，不把整个年份文件读入内存
+const READ_BUFFER_SIZE: usize = 1 << 20;
+const HEADER_SIZE: u64 = DATA_FILE_HEADER_SIZE as u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// 文件无法读取
+    Io,
+    /// 文件头无法解析或与路径、记录类型不一致
+    Header,
+    /// 同一key不同年份的数据结构不一致
+    Schema,
+    /// 文件大小与周期、记录长度不符
+    FileSize,
+    /// index或interval ticks未按时间递增
+    EpochOrder,
+    /// 记录的index与其所在位置不符或超出该年范围
+    EpochAlignment,
+    /// 可变长记录的 `{index, offset, len}` 指向无效区域
+    IndexPointer,
+    /// 可变长数据无法解压或长度不是单行长度的整数倍
+    VariableData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    /// 问题所在的文件偏移量
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    pub message: String,
+}
+
+/// 与服务器 `integrity` 工具相同的分块校验和：按int64小端累加，块长度按8字节对齐补零
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChunkChecksum {
+    /// `Hdr`，12块时为 `Jan` 至 `Dec`，否则为块序号
+    pub name: String,
+    pub offset: u64,
+    pub length: u64,
+    pub sum: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    /// 相对数据目录的路径，如 `AAPL/1Min/OHLCV/2024.bin`
+    pub path: String,
+    pub key: String,
+    pub year: i32,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<RecordType>,
+    /// 已写入的记录数：定长为非空index数，可变长为行数
+    pub records: u64,
+    pub issues: Vec<IntegrityIssue>,
+    /// 超出 `MAX_ISSUES_PER_FILE` 未列出的问题数
+    pub suppressed_issues: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksums: Option<Vec<ChunkChecksum>>,
+}
+
+impl FileReport {
+    fn issue(&mut self, kind: IssueKind, offset: Option<u64>, message: String) {
+        if self.issues.len() < MAX_ISSUES_PER_FILE {
+            self.issues.push(IntegrityIssue { kind, offset, message });
+        } else {
+            self.suppressed_issues += 1;
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// 整个数据目录的检查结果，可直接序列化为JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub root: String,
+    pub files: Vec<FileReport>,
+    /// 跨文件的问题，如同一key不同年份的数据结构不一致
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty() && self.files.iter().all(FileReport::is_ok)
+    }
+
+    pub fn issue_count(&self) -> usize {
+        self.issues.len() + self.files.iter().map(|file| file.issues.len() + file.suppressed_issues).sum::<usize>()
+    }
+}
+
+/// 离线检查数据目录中的年份文件
+///
+/// 检查文件头与路径是否一致、文件大小是否符合周期与记录长度、定长记录的index是否位于对应位置且递增、
+/// 可变长记录的指针是否有效以及interval ticks是否有序；可选计算与服务器 `integrity` 工具相同的分块校验和。
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityChecker {
+    checksum_chunks: Option<usize>,
+}
+
+impl IntegrityChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为每个文件计算分块校验和，`chunks` 为文件头之外的块数
+    pub fn with_checksums(mut self, chunks: usize) -> Self {
+        self.checksum_chunks = Some(chunks.max(1));
+        self
+    }
+
+    pub fn check(&self, catalog: &Catalog) -> IntegrityReport {
+        let mut report = IntegrityReport {
+            root: catalog.root().display().to_string(),
+            files: Vec::new(),
+            issues: Vec::new(),
+        };
+        let mut schemas: BTreeMap<&str, (i32, Vec<DataShape>)> = BTreeMap::new();
+
+        for (key, year, path) in catalog.files() {
+            let (file, header) = self.check_file(catalog.root(), path, key, year, catalog.variable_compression());
+            if let Some(header) = header {
+                match schemas.get(key) {
+                    Some((first_year, shapes)) if *shapes != header.data_shapes => report.issues.push(IntegrityIssue {
+                        kind: IssueKind::Schema,
+                        offset: None,
+                        message: format!("Data shapes of {} in {} differ from {}", key, year, first_year),
+                    }),
+                    Some(_) => {}
+                    None => {
+                        schemas.insert(key, (year, header.data_shapes));
+                    }
+                }
+            }
+            report.files.push(file);
+        }
+        report
+    }
+
+    fn check_file(&self, root: &Path, path: &Path, key: &str, year: i32, compressed: bool) -> (FileReport, Option<DataFileHeader>) {
+        let mut report = FileReport {
+            path: path.strip_prefix(root).unwrap_or(path).display().to_string(),
+            key: key.to_string(),
+            year,
+            size: 0,
+            record_type: None,
+            records: 0,
+            issues: Vec::new(),
+            suppressed_issues: 0,
+            checksums: None,
+        };
+
+        let opened = File::open(path).and_then(|file| file.metadata().map(|metadata| (file, metadata.len())));
+        let (mut file, size) = match opened {
+            Ok(opened) => opened,
+            Err(e) => {
+                report.issue(IssueKind::Io, None, e.to_string());
+                return (report, None);
+            }
+        };
+        report.size = size;
+        if let Some(chunks) = self.checksum_chunks {
+            match chunk_checksums(&mut file, size, chunks) {
+                Ok(checksums) => report.checksums = Some(checksums),
+                Err(e) => report.issue(IssueKind::Io, None, e.to_string()),
+            }
+        }
+
+        let mut head = Vec::with_capacity(DATA_FILE_HEADER_SIZE);
+        if let Err(e) = file.rewind().and_then(|_| (&mut file).take(HEADER_SIZE).read_to_end(&mut head)) {
+            report.issue(IssueKind::Io, Some(0), e.to_string());
+            return (report, None);
+        }
+        let header = match DataFileHeader::parse(&head) {
+            Ok(header) => header,
+            Err(e) => {
+                report.issue(IssueKind::Header, Some(0), e.to_string());
+                return (report, None);
+            }
+        };
+        report.record_type = Some(header.record_type);
+        let Some(expected_size) = check_header(&mut report, &header, key, year) else {
+            return (report, Some(header));
+        };
+
+        let scanned = match header.record_type {
+            RecordType::Fixed => check_fixed(&mut report, &header, &mut file, size, expected_size),
+            RecordType::Variable => check_variable(&mut report, &header, &mut file, size, expected_size, compressed),
+        };
+        if let Err(e) = scanned {
+            report.issue(IssueKind::Io, None, e.to_string());
+        }
+        (report, Some(header))
+    }
+}
+
+/// 返回主区结束位置；返回None时记录布局不可信，不再逐条检查
+fn check_header(report: &mut FileReport, header: &DataFileHeader, key: &str, year: i32) -> Option<u64> {
+    if header.version != DATA_FILE_VERSION {
+        report.issue(IssueKind::Header, Some(0), format!("Unexpected file version {}", header.version));
+    }
+    match split_bucket_key(key).map(|(_, timeframe, _)| Timeframe::parse(timeframe)) {
+        Ok(Ok(timeframe)) if timeframe.duration != header.timeframe => report.issue(
+            IssueKind::Header,
+            Some(0),
+            format!("Header timeframe {:?} does not match {}", header.timeframe, timeframe.name),
+        ),
+        Ok(Ok(_)) => {}
+        _ => report.issue(IssueKind::Header, None, format!("Key {} has no valid timeframe", key)),
+    }
+    // 主区大小由年份决定，年份不符时无法确定记录位置
+    if header.year != year {
+        report.issue(IssueKind::Header, Some(0), format!("Header year {} does not match file name", header.year));
+        return None;
+    }
+    if header.data_shapes.is_empty() {
+        report.issue(IssueKind::Header, Some(0), "Header has no elements".to_string());
+        return None;
+    }
+
+    let fields_length = match header.fields_length() {
+        Ok(length) => length,
+        Err(e) => {
+            report.issue(IssueKind::Header, Some(0), e.to_string());
+            return None;
+        }
+    };
+    let expected = match header.record_type {
+        RecordType::Fixed => fields_length.next_multiple_of(8) + 8,
+        RecordType::Variable => INDIRECT_RECORD_LEN,
+    };
+    if header.record_length as usize != expected {
+        report.issue(
+            IssueKind::Header,
+            Some(0),
+            format!("Record length {} does not match elements, expected {}", header.record_length, expected),
+        );
+        return None;
+    }
+
+    let expected_size = header.checked_file_size();
+    if expected_size.is_none() {
+        report.issue(
+            IssueKind::Header,
+            Some(0),
+            format!("Header timeframe {:?} gives an invalid file size", header.timeframe),
+        );
+    }
+    expected_size
+}
+
+/// 全年的记录位置数与主区中实际存在的结束位置
+fn primary_area(report: &mut FileReport, header: &DataFileHeader, size: u64, expected_size: u64) -> (u64, u64) {
+    let slots = (expected_size - HEADER_SIZE) / header.record_length as u64;
+    match header.record_type {
+        RecordType::Fixed if size != expected_size => report.issue(
+            IssueKind::FileSize,
+            None,
+            format!("File size {} does not match {} records of {} bytes ({} expected)", size, slots, header.record_length, expected_size),
+        ),
+        RecordType::Variable if size < expected_size => report.issue(
+            IssueKind::FileSize,
+            None,
+            format!("File size {} is smaller than the index area of {} bytes", size, expected_size),
+        ),
+        _ => {}
+    }
+    (slots, expected_size.min(size))
+}
+
+/// 逐个位置检查index：为0表示未写入，否则须等于位置序号且递增；已写入的记录交给 `visit`
+fn scan_slots<F>(report: &mut FileReport, header: &DataFileHeader, file: &mut File, size: u64, expected_size: u64, mut visit: F) -> io::Result<()>
+where
+    F: FnMut(&mut FileReport, u64, i64, &[u8]),
+{
+    let (slots, end) = primary_area(report, header, size, expected_size);
+    let record_length = header.record_length as u64;
+    let mut record = vec![0u8; record_length as usize];
+    let mut previous = 0;
+
+    file.seek(SeekFrom::Start(HEADER_SIZE))?;
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, file);
+    for slot in 0..end.saturating_sub(HEADER_SIZE) / record_length {
+        reader.read_exact(&mut record)?;
+        let offset = HEADER_SIZE + slot * record_length;
+        let index = i64::from_le_bytes(record[..8].try_into().unwrap());
+        if index == 0 {
+            continue;
+        }
+        if index < 1 || index as u64 > slots {
+            report.issue(IssueKind::EpochAlignment, Some(offset), format!("Index {} is outside the year of {} intervals", index, slots));
+        } else if index <= previous {
+            report.issue(IssueKind::EpochOrder, Some(offset), format!("Index {} follows index {}", index, previous));
+        } else if index as u64 != slot + 1 {
+            report.issue(IssueKind::EpochAlignment, Some(offset), format!("Index {} stored at slot {}", index, slot + 1));
+        }
+        previous = previous.max(index);
+        visit(report, offset, index, &record);
+    }
+    Ok(())
+}
+
+fn check_fixed(report: &mut FileReport, header: &DataFileHeader, file: &mut File, size: u64, expected_size: u64) -> io::Result<()> {
+    scan_slots(report, header, file, size, expected_size, |report, _, _, _| report.records += 1)
+}
+
+fn check_variable(
+    report: &mut FileReport,
+    header: &DataFileHeader,
+    file: &mut File,
+    size: u64,
+    expected_size: u64,
+    compressed: bool,
+) -> io::Result<()> {
+    let row_len = match header.variable_record_length() {
+        Ok(length) => length as usize,
+        Err(e) => {
+            report.issue(IssueKind::Header, Some(0), e.to_string());
+            return Ok(());
+        }
+    };
+
+    // (记录偏移量, index, 数据偏移量, 数据长度)
+    let mut pointers = Vec::new();
+    scan_slots(report, header, file, size, expected_size, |_, offset, index, record| {
+        let data_offset = i64::from_le_bytes(record[8..16].try_into().unwrap());
+        let data_len = i64::from_le_bytes(record[16..24].try_into().unwrap());
+        pointers.push((offset, index, data_offset, data_len));
+    })?;
+
+    let mut extents = Vec::new();
+    for (offset, index, data_offset, data_len) in pointers {
+        if data_len <= 0 || data_offset < 0 || (data_offset as u64) < expected_size || data_offset.saturating_add(data_len) as u64 > size {
+            report.issue(
+                IssueKind::IndexPointer,
+                Some(offset),
+                format!("Index {} points to {} bytes at offset {} outside the data area", index, data_len, data_offset),
+            );
+            continue;
+        }
+        let (data_offset, data_len) = (data_offset as u64, data_len as u64);
+        extents.push((data_offset, data_len, offset));
+
+        // 长度已限定在文件大小之内
+        let mut data = vec![0u8; data_len as usize];
+        file.seek(SeekFrom::Start(data_offset))?;
+        file.read_exact(&mut data)?;
+        let data = if compressed {
+            match snap::raw::Decoder::new().decompress_vec(&data) {
+                Ok(data) => data,
+                Err(e) => {
+                    report.issue(IssueKind::VariableData, Some(data_offset), format!("Index {}: {}", index, e));
+                    continue;
+                }
+            }
+        } else {
+            data
+        };
+        if !data.len().is_multiple_of(row_len) {
+            report.issue(
+                IssueKind::VariableData,
+                Some(data_offset),
+                format!("Index {} has {} bytes, not a multiple of row length {}", index, data.len(), row_len),
+            );
+            continue;
+        }
+
+        // 服务器写入时按interval ticks稳定排序
+        let mut previous_ticks = 0u32;
+        for row in data.chunks_exact(row_len) {
+            let ticks = u32::from_le_bytes(row[row_len - INTERVAL_TICKS_LEN..].try_into().unwrap());
+            if ticks < previous_ticks {
+                report.issue(
+                    IssueKind::EpochOrder,
+                    Some(data_offset),
+                    format!("Interval ticks of index {} are not sorted", index),
+                );
+                break;
+            }
+            previous_ticks = ticks;
+        }
+        report.records += (data.len() / row_len) as u64;
+    }
+
+    extents.sort();
+    for pair in extents.windows(2) {
+        let ((first_offset, first_len, _), (second_offset, _, pointer)) = (pair[0], pair[1]);
+        if first_offset + first_len > second_offset {
+            report.issue(
+                IssueKind::IndexPointer,
+                Some(pointer),
+                format!("Data at offset {} overlaps data at offset {}", second_offset, first_offset),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 与服务器 `integrity` 工具的分块方式一致：块长度为 `size / chunks + size % chunks` 按8字节对齐
+fn chunk_checksums(file: &mut File, size: u64, chunks: usize) -> io::Result<Vec<ChunkChecksum>> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    file.rewind()?;
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, file);
+    let header_len = HEADER_SIZE.min(size);
+    let mut checksums = vec![ChunkChecksum {
+        name: "Hdr".to_string(),
+        offset: 0,
+        length: header_len,
+        sum: stream_sum(&mut reader, header_len)?,
+    }];
+
+    // 各块首尾相接，顺序读取即可
+    let data_size = size.saturating_sub(HEADER_SIZE);
+    let chunk_size = (data_size / chunks as u64 + data_size % chunks as u64).next_multiple_of(8).max(8);
+    for chunk in 0..chunks {
+        let start = HEADER_SIZE + chunk as u64 * chunk_size;
+        if start >= size {
+            break;
+        }
+        let end = (start + chunk_size).min(size);
+        checksums.push(ChunkChecksum {
+            name: match MONTHS.get(chunk) {
+                Some(month) if chunks == MONTHS.len() => month.to_string(),
+                _ => (chunk + 1).to_string(),
+            },
+            offset: start,
+            length: end - start,
+            sum: stream_sum(&mut reader, end - start)?,
+        });
+    }
+    Ok(checksums)
+}
+
+/// 读取 `length` 字节并累加；缓冲区长度为8的倍数，各段按字对齐
+fn stream_sum<R: Read>(reader: &mut R, length: u64) -> io::Result<i64> {
+    let mut buf = vec![0u8; READ_BUFFER_SIZE.min(length as usize)];
+    let mut remaining = length;
+    let mut sum = 0i64;
+    while remaining > 0 {
+        let len = remaining.min(READ_BUFFER_SIZE as u64) as usize;
+        reader.read_exact(&mut buf[..len])?;
+        sum = sum.wrapping_add(buffer_sum(&buf[..len]));
+        remaining -= len as u64;
+    }
+    Ok(sum)
+}
+
+fn buffer_sum(bytes: &[u8]) -> i64 {
+    bytes.chunks(8).fold(0i64, |sum, word| {
+        let mut padded = [0u8; 8];
+        padded[..word.len()].copy_from_slice(word);
+        sum.wrapping_add(i64::from_le_bytes(padded))
+    })
+}
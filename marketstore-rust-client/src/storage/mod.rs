@@ -0,0 +1,15 @@
+mod byte_reader;
+pub mod catalog;
+pub mod data_file;
+pub mod data_writer;
+pub mod integrity;
+pub mod transaction_group;
+pub mod wal_file;
+
+pub(crate) use byte_reader::ByteReader;
+pub use catalog::*;
+pub use data_file::*;
+pub use data_writer::*;
+pub use integrity::*;
+pub use transaction_group::*;
+pub use wal_file::*;
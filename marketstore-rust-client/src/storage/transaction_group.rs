@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::{
+    error::{MarketStoreError, Result},
+    models::{DataShape, StreamData},
+    storage::ByteReader,
+    utils::{column_type_width, decode_column_value, element_type_code, element_type_name, split_bucket_key, Timeframe, INTERVAL_TICKS_LEN},
+};
+
+/// MD5校验和长度
+pub const TG_CHECKSUM_LEN: usize = 16;
+
+
+/// 写入记录类型，对应服务器 `io.EnumRecordType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordType {
+    /// 定长记录，每个index一行
+    Fixed,
+    /// 可变长记录，同一index可有多行，每行以interval ticks结尾
+    Variable,
+}
+
+impl RecordType {
+    pub fn from_code(code: i8) -> Result<Self> {
+        match code {
+            0 => Ok(RecordType::Fixed),
+            1 => Ok(RecordType::Variable),
+            other => Err(MarketStoreError::InvalidData(format!("Unknown record type: {}", other))),
+        }
+    }
+
+    pub fn code(self) -> i8 {
+        match self {
+            RecordType::Fixed => 0,
+            RecordType::Variable => 1,
+        }
+    }
+}
+
+/// 事务组中对单个数据文件、单个index的一次写入（服务器的 `wal.WTSet`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteTransactionSet {
+    pub record_type: RecordType,
+    /// 相对数据目录的年份文件路径，如 `AAPL/1Min/OHLCV/2024.bin`
+    pub wal_key_path: String,
+    /// 可变长记录的单行长度（不含Epoch，含interval ticks），定长记录为0
+    pub variable_record_length: i32,
+    /// 写入数据文件的偏移量
+    pub offset: i64,
+    /// 年份文件中的index
+    pub index: i64,
+    /// 不含Epoch列的行数据
+    pub payload: Vec<u8>,
+    /// 含Epoch列的数据结构，类型为numpy类型字符串
+    pub data_shapes: Vec<DataShape>,
+}
+
+impl WriteTransactionSet {
+    /// bucket key，如 `AAPL/1Min/OHLCV`
+    pub fn key(&self) -> Result<String> {
+        self.split_path().map(|(key, _)| key.to_string())
+    }
+
+    /// 年份文件对应的年份
+    pub fn year(&self) -> Result<i32> {
+        let (_, year) = self.split_path()?;
+        year.parse()
+            .map_err(|_| MarketStoreError::InvalidData(format!("Invalid year in WAL key path: {}", self.wal_key_path)))
+    }
+
+    pub fn timeframe(&self) -> Result<Timeframe> {
+        let key = self.key()?;
+        let (_, timeframe, _) = split_bucket_key(&key)?;
+        Timeframe::parse(timeframe)
+    }
+
+    /// index对应的epoch秒；可变长记录各行的实际时间为该值加上interval ticks
+    pub fn epoch(&self) -> Result<i64> {
+        Ok(self.timeframe()?.index_to_epoch(self.index, self.year()?))
+    }
+
+    /// 解码为行，每行含 `Epoch` 列，可变长记录另含 `Nanoseconds` 列
+    pub fn rows(&self) -> Result<Vec<StreamData>> {
+        let epoch = self.epoch()?;
+        let columns = self.columns()?;
+        let columns_len: usize = columns.iter().map(|(_, _, width)| width).sum();
+
+        let row_len = match self.record_type {
+            RecordType::Fixed => columns_len,
+            RecordType::Variable => {
+                if self.variable_record_length as usize != columns_len + INTERVAL_TICKS_LEN {
+                    return Err(MarketStoreError::InvalidData(format!(
+                        "Variable record length {} does not match data shapes of {}",
+                        self.variable_record_length, self.wal_key_path
+                    )));
+                }
+                self.variable_record_length as usize
+            }
+        };
+        if row_len == 0 || !self.payload.len().is_multiple_of(row_len) {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Payload of {} bytes is not a multiple of row length {} for {}",
+                self.payload.len(), row_len, self.wal_key_path
+            )));
+        }
+
+        let timeframe = self.timeframe()?;
+        self.payload
+            .chunks_exact(row_len)
+            .map(|record| {
+                let mut row = StreamData::with_capacity(columns.len() + 2);
+                let mut pos = 0;
+                for (name, column_type, width) in &columns {
+                    row.insert(name.to_string(), decode_column_value(column_type, &record[pos..pos + width])?);
+                    pos += width;
+                }
+                match self.record_type {
+                    RecordType::Fixed => {
+                        row.insert("Epoch".to_string(), serde_json::Value::from(epoch));
+                    }
+                    RecordType::Variable => {
+                        let ticks = u32::from_le_bytes(record[pos..pos + INTERVAL_TICKS_LEN].try_into().unwrap());
+                        let (seconds, nanoseconds) = timeframe.ticks_to_time(epoch, ticks);
+                        row.insert("Epoch".to_string(), serde_json::Value::from(seconds));
+                        row.insert("Nanoseconds".to_string(), serde_json::Value::from(nanoseconds));
+                    }
+                }
+                Ok(row)
+            })
+            .collect()
+    }
+
+    /// `AAPL/1Min/OHLCV/2024.bin` 拆分为 key 与年份
+    fn split_path(&self) -> Result<(&str, &str)> {
+        self.wal_key_path
+            .strip_suffix(".bin")
+            .and_then(|path| path.rsplit_once('/'))
+            .filter(|(key, year)| split_bucket_key(key).is_ok() && !year.is_empty())
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("Invalid WAL key path: {}", self.wal_key_path)))
+    }
+
+    /// 除Epoch外各列的名称、类型与宽度
+    fn columns(&self) -> Result<Vec<(&str, &str, usize)>> {
+        if self.data_shapes.is_empty() {
+            return Err(MarketStoreError::InvalidData(format!("No data shapes for {}", self.wal_key_path)));
+        }
+        self.data_shapes
+            .iter()
+            .filter(|shape| shape.name != "Epoch")
+            .map(|shape| Ok((shape.name.as_str(), shape.data_type.as_str(), column_type_width(&shape.data_type)?)))
+            .collect()
+    }
+}
+
+/// 一个事务组（服务器的Transaction Group），即一次WAL提交的所有写入
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionGroup {
+    pub transaction_group_id: i64,
+    pub write_sets: Vec<WriteTransactionSet>,
+}
+
+impl TransactionGroup {
+    /// 解码序列化的事务组，即复制流中的 `transaction_group`（不含长度与校验和）
+    ///
+    /// 布局与服务器 `serializeTG`/`ParseTGData` 一致：TGID、WT数量，之后每个WT为记录类型、
+    /// 路径长度与路径、数据长度、可变长记录长度、offset、index、数据以及数据结构。
+    pub fn decode(tg_serialized: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(tg_serialized, "Transaction group");
+        let transaction_group_id = reader.i64()?;
+        let count = reader.i64()?;
+        if count < 0 {
+            return Err(MarketStoreError::InvalidData(format!("Invalid write transaction count: {}", count)));
+        }
+
+        let mut write_sets = Vec::new();
+        for _ in 0..count {
+            let record_type = RecordType::from_code(reader.i8()?)?;
+            let path_len = reader.i16()?.max(0) as usize;
+            let wal_key_path = reader.string(path_len)?;
+            let data_len = reader.i32()?.max(0) as usize;
+            let variable_record_length = reader.i32()?;
+            let offset = reader.i64()?;
+            let index = reader.i64()?;
+            let payload = reader.take(data_len)?.to_vec();
+            let data_shapes = read_data_shapes(&mut reader)?;
+
+            write_sets.push(WriteTransactionSet {
+                record_type,
+                wal_key_path,
+                variable_record_length,
+                offset,
+                index,
+                payload,
+                data_shapes,
+            });
+        }
+
+        Ok(Self {
+            transaction_group_id,
+            write_sets,
+        })
+    }
+
+    /// 解码WAL文件中带长度前缀与MD5校验和的事务组，返回事务组与消耗的字节数
+    pub fn decode_framed(buf: &[u8]) -> Result<(Self, usize)> {
+        let mut reader = ByteReader::new(buf, "Transaction group");
+        let tg_len_bytes = reader.take(8)?;
+        let tg_len = i64::from_le_bytes(tg_len_bytes.try_into().unwrap());
+        if tg_len < 0 {
+            return Err(MarketStoreError::InvalidData(format!("Invalid transaction group length: {}", tg_len)));
+        }
+        let tg_serialized = reader.take(tg_len as usize)?;
+        let checksum = reader.take(TG_CHECKSUM_LEN)?;
+
+        let computed = tg_checksum(tg_serialized);
+        if computed.as_slice() != checksum {
+            return Err(MarketStoreError::ChecksumMismatch(format!(
+                "transaction group of {} bytes: expected {}, computed {}",
+                tg_len, hex(checksum), hex(&computed)
+            )));
+        }
+
+        Ok((Self::decode(tg_serialized)?, reader.position()))
+    }
+
+    /// 按服务器 `serializeTG` 的布局序列化
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&self.transaction_group_id.to_le_bytes());
+        buf.extend_from_slice(&(self.write_sets.len() as i64).to_le_bytes());
+        for set in &self.write_sets {
+            buf.push(set.record_type.code() as u8);
+            buf.extend_from_slice(&(set.wal_key_path.len() as i16).to_le_bytes());
+            buf.extend_from_slice(set.wal_key_path.as_bytes());
+            buf.extend_from_slice(&(set.payload.len() as i32).to_le_bytes());
+            buf.extend_from_slice(&set.variable_record_length.to_le_bytes());
+            buf.extend_from_slice(&set.offset.to_le_bytes());
+            buf.extend_from_slice(&set.index.to_le_bytes());
+            buf.extend_from_slice(&set.payload);
+            write_data_shapes(&mut buf, &set.data_shapes)?;
+        }
+        Ok(buf)
+    }
+
+    /// 序列化为WAL文件中的形式：长度、事务组数据与MD5校验和
+    pub fn encode_framed(&self) -> Result<Vec<u8>> {
+        let tg_serialized = self.encode()?;
+        let mut buf = Vec::with_capacity(8 + tg_serialized.len() + TG_CHECKSUM_LEN);
+        buf.extend_from_slice(&(tg_serialized.len() as i64).to_le_bytes());
+        buf.extend_from_slice(&tg_serialized);
+        buf.extend_from_slice(&tg_checksum(&tg_serialized));
+        Ok(buf)
+    }
+
+    /// 按bucket key汇总各写入的行，同一key内保持写入顺序
+    pub fn rows_by_key(&self) -> Result<BTreeMap<String, Vec<StreamData>>> {
+        let mut rows: BTreeMap<String, Vec<StreamData>> = BTreeMap::new();
+        for set in &self.write_sets {
+            rows.entry(set.key()?).or_default().extend(set.rows()?);
+        }
+        Ok(rows)
+    }
+}
+
+/// 事务组校验和：对长度（int64）与序列化数据计算MD5
+pub fn tg_checksum(tg_serialized: &[u8]) -> [u8; TG_CHECKSUM_LEN] {
+    let mut context = md5::Context::new();
+    context.consume((tg_serialized.len() as i64).to_le_bytes());
+    context.consume(tg_serialized);
+    context.compute().0
+}
+
+/// 服务器 `io.DSVFromBytes`：uint8数量，之后每列为uint8名称长度、名称与类型编码
+fn read_data_shapes(reader: &mut ByteReader) -> Result<Vec<DataShape>> {
+    // 数据结构为空时服务器不写入任何字节
+    if reader.remaining() == 0 {
+        return Ok(Vec::new());
+    }
+    let count = reader.u8()?;
+    (0..count)
+        .map(|_| {
+            let name_len = reader.u8()? as usize;
+            let name = reader.string(name_len)?;
+            let data_type = element_type_name(reader.u8()?)?.to_string();
+            Ok(DataShape { name, data_type })
+        })
+        .collect()
+}
+
+fn write_data_shapes(buf: &mut Vec<u8>, shapes: &[DataShape]) -> Result<()> {
+    if shapes.is_empty() {
+        return Ok(());
+    }
+    buf.push(shapes.len() as u8);
+    for shape in shapes {
+        buf.push(shape.name.len() as u8);
+        buf.extend_from_slice(shape.name.as_bytes());
+        buf.push(element_type_code(&shape.data_type)?);
+    }
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::{
+    error::{MarketStoreError, Result},
+    storage::{ByteReader, TransactionGroup, TG_CHECKSUM_LEN},
+};
+
+/// 消息ID，对应服务器 `executor.MIDEnum`
+const MID_TG_DATA: u8 = 0;
+const MID_TXN_INFO: u8 = 1;
+const MID_STATUS: u8 = 2;
+
+/// 文件状态，对应服务器 `wal.FileStatusEnum`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalFileStatus {
+    Invalid,
+    Open,
+    Closed,
+}
+
+/// 回放状态，对应服务器 `wal.ReplayStateEnum`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayState {
+    Invalid,
+    NotReplayed,
+    Replayed,
+    ReplayInProcess,
+}
+
+/// 事务信息记录的目标，对应服务器 `executor.DestEnum`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxnDestination {
+    Wal,
+    Checkpoint,
+}
+
+/// 事务状态，对应服务器 `executor.TxnStatusEnum`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxnStatus {
+    Preparing,
+    CommitIntended,
+    CommitComplete,
+}
+
+/// WAL文件头：文件状态、回放状态与持有该文件的进程ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalHeader {
+    pub file_status: WalFileStatus,
+    pub replay_state: ReplayState,
+    pub owning_instance_id: i64,
+}
+
+impl WalHeader {
+    /// 与服务器 `NeedsReplay` 一致：未回放或回放中断的文件需要回放
+    pub fn needs_replay(&self) -> bool {
+        matches!(self.replay_state, ReplayState::NotReplayed | ReplayState::ReplayInProcess)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionInfo {
+    pub transaction_group_id: i64,
+    pub destination: TxnDestination,
+    pub status: TxnStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalRecord {
+    Status(WalHeader),
+    TransactionInfo(TransactionInfo),
+    TransactionGroup(TransactionGroup),
+}
+
+/// WAL文件中的一条消息及其在文件中的偏移量
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalEntry {
+    pub offset: usize,
+    pub record: WalRecord,
+}
+
+/// 读取过程中遇到的问题；校验和错误的事务组会被跳过并继续读取，其余问题会结束读取
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalIssue {
+    pub offset: usize,
+    pub message: String,
+}
+
+/// 离线读取的WAL文件
+#[derive(Debug, Clone)]
+pub struct WalFile {
+    pub header: WalHeader,
+    pub entries: Vec<WalEntry>,
+    pub issues: Vec<WalIssue>,
+}
+
+impl WalFile {
+    /// 读取整个WAL文件，文件头损坏时返回错误
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// 按服务器 `Replay` 的读取顺序解析：文件头之后依次为事务组数据、事务信息与状态消息
+    pub fn parse(buf: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(buf, "WAL file");
+        if reader.u8()? != MID_STATUS {
+            return Err(MarketStoreError::InvalidData("WAL file does not start with a status message".to_string()));
+        }
+        let header = read_status(&mut reader)?;
+
+        let mut entries = Vec::new();
+        let mut issues = Vec::new();
+        while reader.remaining() > 0 {
+            let offset = reader.position();
+            let record = match reader.u8()? {
+                MID_TG_DATA => match TransactionGroup::decode_framed(&buf[reader.position()..]) {
+                    Ok((group, consumed)) => {
+                        reader.take(consumed)?;
+                        WalRecord::TransactionGroup(group)
+                    }
+                    Err(MarketStoreError::ChecksumMismatch(message)) => {
+                        // 长度完整、仅内容损坏，跳过该事务组继续读取
+                        let tg_len = reader.i64()?;
+                        reader.take(tg_len as usize + TG_CHECKSUM_LEN)?;
+                        issues.push(WalIssue { offset, message: format!("Checksum mismatch in {}", message) });
+                        continue;
+                    }
+                    Err(e) => {
+                        issues.push(WalIssue { offset, message: e.to_string() });
+                        break;
+                    }
+                },
+                MID_TXN_INFO => match read_transaction_info(&mut reader) {
+                    Ok(info) => WalRecord::TransactionInfo(info),
+                    Err(e) => {
+                        issues.push(WalIssue { offset, message: e.to_string() });
+                        break;
+                    }
+                },
+                MID_STATUS => match read_status(&mut reader) {
+                    Ok(status) => WalRecord::Status(status),
+                    Err(e) => {
+                        issues.push(WalIssue { offset, message: e.to_string() });
+                        break;
+                    }
+                },
+                other => {
+                    issues.push(WalIssue { offset, message: format!("Unknown message id {}", other) });
+                    break;
+                }
+            };
+            entries.push(WalEntry { offset, record });
+        }
+
+        Ok(Self { header, entries, issues })
+    }
+
+    pub fn transaction_groups(&self) -> impl Iterator<Item = &TransactionGroup> {
+        self.entries.iter().filter_map(|entry| match &entry.record {
+            WalRecord::TransactionGroup(group) => Some(group),
+            _ => None,
+        })
+    }
+
+    pub fn transaction_infos(&self) -> impl Iterator<Item = &TransactionInfo> {
+        self.entries.iter().filter_map(|entry| match &entry.record {
+            WalRecord::TransactionInfo(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    /// 回放时仍需写入主存储的事务组，按TGID升序
+    ///
+    /// 与服务器 `Replay` 一致：检查点COMMITCOMPLETE记录出现时，若其TGID的事务组数据已读到，
+    /// 则丢弃此前所有TGID不大于它的事务组。
+    pub fn pending_transaction_groups(&self) -> Vec<&TransactionGroup> {
+        let mut pending: BTreeMap<i64, &TransactionGroup> = BTreeMap::new();
+        for entry in &self.entries {
+            match &entry.record {
+                WalRecord::TransactionGroup(group) => {
+                    pending.insert(group.transaction_group_id, group);
+                }
+                WalRecord::TransactionInfo(info)
+                    if info.destination == TxnDestination::Checkpoint
+                        && info.status == TxnStatus::CommitComplete
+                        && pending.contains_key(&info.transaction_group_id) =>
+                {
+                    pending.retain(|id, _| *id > info.transaction_group_id);
+                }
+                _ => {}
+            }
+        }
+        pending.into_values().collect()
+    }
+}
+
+fn read_status(reader: &mut ByteReader) -> Result<WalHeader> {
+    let file_status = match reader.i8()? {
+        0 => WalFileStatus::Invalid,
+        1 => WalFileStatus::Open,
+        2 => WalFileStatus::Closed,
+        other => return Err(MarketStoreError::InvalidData(format!("Invalid WAL file status: {}", other))),
+    };
+    let replay_state = match reader.i8()? {
+        0 => ReplayState::Invalid,
+        1 => ReplayState::NotReplayed,
+        2 => ReplayState::Replayed,
+        3 => ReplayState::ReplayInProcess,
+        other => return Err(MarketStoreError::InvalidData(format!("Invalid WAL replay state: {}", other))),
+    };
+    Ok(WalHeader {
+        file_status,
+        replay_state,
+        owning_instance_id: reader.i64()?,
+    })
+}
+
+fn read_transaction_info(reader: &mut ByteReader) -> Result<TransactionInfo> {
+    let transaction_group_id = reader.i64()?;
+    let destination = match reader.i8()? {
+        0 => TxnDestination::Wal,
+        1 => TxnDestination::Checkpoint,
+        other => return Err(MarketStoreError::InvalidData(format!("Invalid destination ID: {}", other))),
+    };
+    let status = match reader.i8()? {
+        0 => TxnStatus::Preparing,
+        1 => TxnStatus::CommitIntended,
+        2 => TxnStatus::CommitComplete,
+        other => return Err(MarketStoreError::InvalidData(format!("Invalid transaction status: {}", other))),
+    };
+    Ok(TransactionInfo {
+        transaction_group_id,
+        destination,
+        status,
+    })
+}
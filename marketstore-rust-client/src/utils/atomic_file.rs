@@ -0,0 +1,22 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use crate::error::Result;
+
+/// 先写同目录下的 `.tmp` 文件并fsync，再重命名覆盖 `path` 并fsync所在目录，
+/// 断电后只会看到旧内容或完整的新内容
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(&tmp, path)?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use crate::error::{MarketStoreError, Result};
+
+/// 拼接 `symbol/timeframe/attr_group` 形式的bucket key
+pub fn bucket_key(symbol: &str, timeframe: &str, attr_group: &str) -> String {
+    format!("{}/{}/{}", symbol, timeframe, attr_group)
+}
+
+/// 将 `AAPL/1Min/OHLCV` 拆分为 (symbol, timeframe, attr_group)，三部分都不能为空
+pub fn split_bucket_key(key: &str) -> Result<(&str, &str, &str)> {
+    match key.split('/').collect::<Vec<_>>().as_slice() {
+        [symbol, timeframe, attr_group] if !symbol.is_empty() && !timeframe.is_empty() && !attr_group.is_empty() => {
+            Ok((symbol, timeframe, attr_group))
+        }
+        _ => Err(MarketStoreError::InvalidData(format!("Invalid bucket key: {}", key))),
+    }
+}
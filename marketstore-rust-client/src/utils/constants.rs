@@ -0,0 +1,5 @@
+/// 一天的秒数
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// 可变长记录每行末尾interval ticks的字节数，与服务器 `io.Variable` 布局一致
+pub const INTERVAL_TICKS_LEN: usize = 4;
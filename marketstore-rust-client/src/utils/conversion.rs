@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use crate::error::{MarketStoreError, Result};
 use crate::models::{OHLCVData, NumpyDataset};
 
 /// 将OHLCV数据转换为字节数组
@@ -6,27 +8,27 @@ pub fn ohlcv_to_bytes(data: &[OHLCVData]) -> Vec<Vec<u8>> {
     
     // Epoch列
     let epochs: Vec<i64> = data.iter().map(|d| d.epoch).collect();
-    result.push(epochs.into_iter().map(|e| e.to_le_bytes()).flatten().collect());
+    result.push(epochs.into_iter().flat_map(|e| e.to_le_bytes()).collect());
     
     // Open列
     let opens: Vec<f32> = data.iter().map(|d| d.open).collect();
-    result.push(opens.into_iter().map(|o| o.to_le_bytes()).flatten().collect());
+    result.push(opens.into_iter().flat_map(|o| o.to_le_bytes()).collect());
     
     // High列
     let highs: Vec<f32> = data.iter().map(|d| d.high).collect();
-    result.push(highs.into_iter().map(|h| h.to_le_bytes()).flatten().collect());
+    result.push(highs.into_iter().flat_map(|h| h.to_le_bytes()).collect());
     
     // Low列
     let lows: Vec<f32> = data.iter().map(|d| d.low).collect();
-    result.push(lows.into_iter().map(|l| l.to_le_bytes()).flatten().collect());
+    result.push(lows.into_iter().flat_map(|l| l.to_le_bytes()).collect());
     
     // Close列
     let closes: Vec<f32> = data.iter().map(|d| d.close).collect();
-    result.push(closes.into_iter().map(|c| c.to_le_bytes()).flatten().collect());
+    result.push(closes.into_iter().flat_map(|c| c.to_le_bytes()).collect());
     
     // Volume列
     let volumes: Vec<f32> = data.iter().map(|d| d.volume).collect();
-    result.push(volumes.into_iter().map(|v| v.to_le_bytes()).flatten().collect());
+    result.push(volumes.into_iter().flat_map(|v| v.to_le_bytes()).collect());
     
     result
 }
@@ -43,4 +45,138 @@ pub fn create_numpy_dataset_from_ohlcv(data: &[OHLCVData]) -> NumpyDataset {
         column_data,
         length: data.len() as i32,
     }
-} 
\ No newline at end of file
+} 
+/// 将NumpyDataset按行解码为 列名 -> 值 的记录，与WebSocket推送的StreamPayload.data格式一致
+pub fn numpy_dataset_to_records(dataset: &NumpyDataset) -> Result<Vec<HashMap<String, serde_json::Value>>> {
+    let length = dataset.length.max(0) as usize;
+    let mut records = vec![HashMap::with_capacity(dataset.column_names.len()); length];
+    
+    for (i, name) in dataset.column_names.iter().enumerate() {
+        let column_type = dataset.column_types.get(i).ok_or_else(|| {
+            MarketStoreError::InvalidData(format!("Missing type for column {}", name))
+        })?;
+        let bytes = dataset.column_data.get(i).ok_or_else(|| {
+            MarketStoreError::InvalidData(format!("Missing data for column {}", name))
+        })?;
+        let width = column_type_width(column_type)?;
+        if bytes.len() < width * length {
+            return Err(MarketStoreError::InvalidData(format!(
+                "Column {} has {} bytes, expected {}", name, bytes.len(), width * length
+            )));
+        }
+        
+        for (row, record) in records.iter_mut().enumerate() {
+            let value = decode_column_value(column_type, &bytes[row * width..(row + 1) * width])?;
+            record.insert(name.clone(), value);
+        }
+    }
+    
+    Ok(records)
+}
+
+/// 将查询结果解码为OHLCV bar，需含Epoch、Open、High、Low、Close、Volume列，其余列被忽略
+pub fn numpy_dataset_to_ohlcv(dataset: &NumpyDataset) -> Result<Vec<OHLCVData>> {
+    let records = numpy_dataset_to_records(dataset)?;
+    let field = |record: &HashMap<String, serde_json::Value>, name: &str| {
+        record
+            .get(name)
+            .and_then(|value| value.as_f64())
+            .ok_or_else(|| MarketStoreError::InvalidData(format!("Missing numeric column {}", name)))
+    };
+
+    records
+        .iter()
+        .map(|record| {
+            Ok(OHLCVData {
+                epoch: record.get("Epoch").and_then(|value| value.as_i64()).ok_or_else(|| {
+                    MarketStoreError::InvalidData("Missing column Epoch".to_string())
+                })?,
+                open: field(record, "Open")? as f32,
+                high: field(record, "High")? as f32,
+                low: field(record, "Low")? as f32,
+                close: field(record, "Close")? as f32,
+                volume: field(record, "Volume")? as f32,
+            })
+        })
+        .collect()
+}
+
+/// numpy类型字符串对应的字节宽度
+pub fn column_type_width(column_type: &str) -> Result<usize> {
+    match column_type {
+        "i1" | "u1" | "b1" | "bool" => Ok(1),
+        "i2" | "u2" => Ok(2),
+        "i4" | "u4" | "f4" => Ok(4),
+        "i8" | "u8" | "f8" => Ok(8),
+        // 服务器的STRING16，16个UTF-32字符
+        "U16" => Ok(64),
+        other => Err(MarketStoreError::InvalidData(format!("Unsupported column type: {}", other))),
+    }
+}
+
+/// 按numpy类型解码单个小端值
+pub(crate) fn decode_column_value(column_type: &str, bytes: &[u8]) -> Result<serde_json::Value> {
+    let value = match column_type {
+        "i1" => serde_json::Value::from(bytes[0] as i8),
+        "u1" => serde_json::Value::from(bytes[0]),
+        "b1" | "bool" => serde_json::Value::from(bytes[0] != 0),
+        "i2" => serde_json::Value::from(i16::from_le_bytes(bytes.try_into().unwrap())),
+        "u2" => serde_json::Value::from(u16::from_le_bytes(bytes.try_into().unwrap())),
+        "i4" => serde_json::Value::from(i32::from_le_bytes(bytes.try_into().unwrap())),
+        "u4" => serde_json::Value::from(u32::from_le_bytes(bytes.try_into().unwrap())),
+        "i8" => serde_json::Value::from(i64::from_le_bytes(bytes.try_into().unwrap())),
+        "u8" => serde_json::Value::from(u64::from_le_bytes(bytes.try_into().unwrap())),
+        "f4" => serde_json::Value::from(f32::from_le_bytes(bytes.try_into().unwrap())),
+        "f8" => serde_json::Value::from(f64::from_le_bytes(bytes.try_into().unwrap())),
+        "U16" => serde_json::Value::from(
+            bytes
+                .chunks_exact(4)
+                .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                .take_while(|c| *c != 0)
+                .filter_map(char::from_u32)
+                .collect::<String>(),
+        ),
+        other => return Err(MarketStoreError::InvalidData(format!("Unsupported column type: {}", other))),
+    };
+    Ok(value)
+}
+
+/// 服务器 `io.EnumElementType` 编码对应的numpy类型字符串（`EPOCH` 按 `i8` 处理）
+pub fn element_type_name(code: u8) -> Result<&'static str> {
+    let name = match code {
+        0 => "f4",
+        1 => "i4",
+        2 => "f8",
+        3 | 4 => "i8",
+        5 => "i1",
+        6 => "b1",
+        9 => "i2",
+        10 => "u1",
+        11 => "u2",
+        12 => "u4",
+        13 => "u8",
+        14 => "U16",
+        other => return Err(MarketStoreError::InvalidData(format!("Unsupported element type: {}", other))),
+    };
+    Ok(name)
+}
+
+/// numpy类型字符串对应的服务器 `io.EnumElementType` 编码
+pub fn element_type_code(column_type: &str) -> Result<u8> {
+    let code = match column_type {
+        "f4" => 0,
+        "i4" => 1,
+        "f8" => 2,
+        "i8" => 3,
+        "i1" => 5,
+        "b1" | "bool" => 6,
+        "i2" => 9,
+        "u1" => 10,
+        "u2" => 11,
+        "u4" => 12,
+        "u8" => 13,
+        "U16" => 14,
+        other => return Err(MarketStoreError::InvalidData(format!("Unsupported column type: {}", other))),
+    };
+    Ok(code)
+}
@@ -1,3 +1,11 @@
+pub mod atomic_file;
+pub mod bucket_key;
+pub mod constants;
 pub mod conversion;
+pub mod timeframe;
 
-pub use conversion::*; 
\ No newline at end of file
+pub use atomic_file::*;
+pub use bucket_key::*;
+pub use constants::*;
+pub use conversion::*;
+pub use timeframe::*;
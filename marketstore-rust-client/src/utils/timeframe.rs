@@ -0,0 +1,108 @@
+use std::time::Duration;
+use chrono::NaiveDate;
+use crate::{
+    error::{MarketStoreError, Result},
+    utils::SECONDS_PER_DAY,
+};
+
+/// 与服务器 `utils.timeframeDefs` 顺序一致，按顺序匹配第一个出现的单位
+const TIMEFRAME_UNITS: [(&str, u64); 8] = [
+    ("S", 1),
+    ("Sec", 1),
+    ("T", 60),
+    ("Min", 60),
+    ("H", 60 * 60),
+    ("D", SECONDS_PER_DAY as u64),
+    ("W", 7 * SECONDS_PER_DAY as u64),
+    ("Y", 365 * SECONDS_PER_DAY as u64),
+];
+
+/// `(2^32 - 1) / 86400`，与服务器计算interval ticks时使用的常量一致
+const TICKS_PER_INTERVAL_DIV_SECS_PER_DAY: f64 = 49710.269629629629629629629629629;
+
+/// bucket key中的时间周期，如 `1Min`、`4H`、`1D`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeframe {
+    pub name: String,
+    pub duration: Duration,
+}
+
+impl Timeframe {
+    /// 按服务器 `TimeframeFromString` 的规则解析
+    pub fn parse(timeframe: &str) -> Result<Self> {
+        for (unit, seconds) in TIMEFRAME_UNITS {
+            if let Some(position) = timeframe.find(unit) {
+                let count: u64 = timeframe[..position]
+                    .parse()
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| MarketStoreError::InvalidData(format!("Invalid timeframe: {}", timeframe)))?;
+                return Ok(Self {
+                    name: timeframe.to_string(),
+                    duration: Duration::from_secs(count * seconds),
+                });
+            }
+        }
+        Err(MarketStoreError::InvalidData(format!("Invalid timeframe: {}", timeframe)))
+    }
+
+    /// 每天的周期数，即数据文件中的intervals
+    pub fn intervals_per_day(&self) -> i64 {
+        SECONDS_PER_DAY / self.duration.as_secs().max(1) as i64
+    }
+
+    /// 数据文件中的index转换为epoch秒；与服务器 `IndexToTime` 一致，1D从0开始，其余从1开始
+    pub fn index_to_epoch(&self, index: i64, year: i32) -> i64 {
+        let year_start = year_start_epoch(year);
+        if self.duration.as_secs() as i64 == SECONDS_PER_DAY {
+            year_start + index * SECONDS_PER_DAY
+        } else {
+            year_start + (index - 1) * self.duration.as_secs() as i64
+        }
+    }
+
+    /// epoch秒转换为所在年份与该年数据文件中的index
+    pub fn epoch_to_index(&self, epoch: i64) -> (i32, i64) {
+        let year = chrono::DateTime::from_timestamp(epoch, 0)
+            .map(|time| chrono::Datelike::year(&time))
+            .unwrap_or(1970);
+        let elapsed = epoch - year_start_epoch(year);
+        if self.duration.as_secs() as i64 == SECONDS_PER_DAY {
+            (year, elapsed / SECONDS_PER_DAY)
+        } else {
+            (year, 1 + elapsed / self.duration.as_secs() as i64)
+        }
+    }
+
+    /// 可变长记录中的interval ticks还原为epoch秒与纳秒，与服务器 `GetTimeFromTicks` 一致
+    pub fn ticks_to_time(&self, interval_start: i64, ticks: u32) -> (i64, i32) {
+        const NANOSECOND: f64 = 1_000_000_000.0;
+        const SUBNANOSECOND: f64 = 100_000_000.0;
+
+        let mut fractional_seconds =
+            ticks as f64 / (self.intervals_per_day() as f64 * TICKS_PER_INTERVAL_DIV_SECS_PER_DAY);
+        let mut subseconds = NANOSECOND * (fractional_seconds - fractional_seconds.floor());
+        if subseconds >= NANOSECOND {
+            subseconds -= NANOSECOND;
+            fractional_seconds += 1.0;
+        }
+
+        let seconds = ((fractional_seconds * SUBNANOSECOND).round() / SUBNANOSECOND) as i64;
+        (interval_start + seconds, (subseconds + 0.5) as i32)
+    }
+
+    /// epoch秒与纳秒转换为相对所在interval起点的ticks，与服务器 `GetIntervalTicks32Bit` 一致
+    pub fn time_to_ticks(&self, interval_start: i64, epoch: i64, nanoseconds: i32) -> u32 {
+        let seconds = (epoch - interval_start) as f64 + nanoseconds as f64 / 1_000_000_000.0;
+        let ticks_per_second = self.intervals_per_day() as f64 * TICKS_PER_INTERVAL_DIV_SECS_PER_DAY;
+        (ticks_per_second * seconds) as u32
+    }
+}
+
+/// 指定年份1月1日零点（UTC）的epoch秒
+pub fn year_start_epoch(year: i32) -> i64 {
+    NaiveDate::from_ymd_opt(year, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc().timestamp())
+        .unwrap_or_default()
+}
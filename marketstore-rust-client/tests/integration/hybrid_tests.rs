@@ -5,7 +5,6 @@ mod tests {
         models::{QueryRequest, OHLCVData, SymbolFormat, StreamSubscription, DataShape},
         error::Result,
     };
-    use tokio::sync::oneshot;
 
     #[tokio::test]
     async fn test_hybrid_client_creation() {
@@ -130,23 +129,14 @@ mod tests {
         let subscription = StreamSubscription::new()
             .add_stream("BTCUSDT/1Min/OHLCV");
             
-        let mut received_count = 0;
-        let handler = |payload| {
-            received_count += 1;
-            println!("Received: {:?}", payload);
-            Ok(())
-        };
-        
-        let (tx, rx) = oneshot::channel();
-        
-        let handle = client.subscribe_realtime_with_cancel(subscription, handler, rx).await;
+        let stream = client.subscribe_stream(subscription, 16).await;
         
         // 这个测试需要真实的MarketStore服务器运行
         // 在实际测试中，应该使用mock服务器
-        assert!(handle.is_ok() || handle.is_err()); // 至少能处理订阅结果
+        assert!(stream.is_ok() || stream.is_err()); // 至少能处理订阅结果
         
-        // 发送取消信号
-        let _ = tx.send(());
+        // drop即取消订阅
+        drop(stream);
     }
 
     #[tokio::test]
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::MockGrpcClient;
+    use marketstore_rust_client::{
+        adjustment::{ActionKind, AdjustMode, Adjuster, CorporateAction, CorporateActions},
+        models::{NumpyDataset, OHLCVData},
+        utils::{create_numpy_dataset_from_ohlcv, numpy_dataset_to_ohlcv},
+    };
+
+    /// (Epoch, Status, TextNumber, UpdateTextNumber, DeleteTextNumber, NotificationType, ExpirationDate, Rate)
+    type ActionRow = (i64, u8, i64, i64, i64, u8, i64, f64);
+
+    fn bar(epoch: i64, close: f32, volume: f32) -> OHLCVData {
+        OHLCVData { epoch, open: close, high: close + 1.0, low: close - 1.0, close, volume }
+    }
+
+    #[test]
+    fn test_backward_and_forward_split_adjustment() {
+        let bars = vec![bar(100, 100.0, 10.0), bar(200, 50.0, 20.0), bar(300, 52.0, 30.0)];
+        let split = [CorporateAction { epoch: 200, kind: ActionKind::Split, value: 2.0 }];
+
+        // 前复权：除权日前的价格减半、成交量加倍，之后不变
+        let backward = Adjuster::new(&split).adjust_bars(&bars).unwrap();
+        assert_eq!(backward[0], OHLCVData { epoch: 100, open: 50.0, high: 50.5, low: 49.5, close: 50.0, volume: 20.0 });
+        assert_eq!(&backward[1..], &bars[1..]);
+
+        // 后复权：除权日及之后的价格加倍、成交量减半
+        let forward = Adjuster::new(&split).with_mode(AdjustMode::Forward).adjust_bars(&bars).unwrap();
+        assert_eq!(forward[0], bars[0]);
+        assert_eq!((forward[1].close, forward[1].volume), (100.0, 10.0));
+        assert_eq!((forward[2].close, forward[2].volume), (104.0, 15.0));
+
+        assert_eq!(Adjuster::new(&split).with_mode(AdjustMode::Raw).adjust_bars(&bars).unwrap(), bars);
+        assert_eq!(Adjuster::new(&split).with_splits(false).adjust_bars(&bars).unwrap(), bars);
+        let invalid = [CorporateAction { epoch: 200, kind: ActionKind::Split, value: 0.0 }];
+        assert!(Adjuster::new(&invalid).adjust_bars(&bars).is_err());
+    }
+
+    #[test]
+    fn test_cash_dividend_adjusts_dataset_prices_only() {
+        let bars = vec![bar(100, 10.0, 1000.0), bar(200, 9.5, 2000.0)];
+        let dataset = create_numpy_dataset_from_ohlcv(&bars);
+        let mut actions = CorporateActions::new();
+        actions.add("AAPL", CorporateAction { epoch: 200, kind: ActionKind::CashDividend, value: 0.5 });
+
+        // 因子为 10 / (10 - 0.5)，除权日前的价格乘以 0.95
+        let adjuster = Adjuster::new(actions.get("AAPL")).with_previous_close(200, 10.0);
+        let adjusted = adjuster.adjust_dataset(&dataset).unwrap();
+        assert_eq!(adjusted.column_names, dataset.column_names);
+        let adjusted = numpy_dataset_to_ohlcv(&adjusted).unwrap();
+        assert_eq!(adjusted[0], OHLCVData { epoch: 100, open: 9.5, high: 10.45, low: 8.55, close: 9.5, volume: 1000.0 });
+        assert_eq!(adjusted[1], bars[1]);
+
+        let forward = adjuster.with_mode(AdjustMode::Forward).adjust_dataset(&dataset).unwrap();
+        let forward = numpy_dataset_to_ohlcv(&forward).unwrap();
+        assert_eq!((forward[1].close, forward[1].volume), (10.0, 2000.0));
+
+        let json = r#"{"AAPL": [{"epoch": 200, "kind": "cash_dividend", "value": 0.5}]}"#;
+        assert_eq!(CorporateActions::from_json(json).unwrap(), actions);
+        assert!(actions.get("MSFT").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cash_dividend_uses_close_of_day_before_ex_date() {
+        let dividend = [CorporateAction { epoch: 3 * 86400, kind: ActionKind::CashDividend, value: 0.5 }];
+        // 查询窗口内除权日前只有第一天的bar，不能用它的收盘价计算因子
+        let bars = vec![bar(0, 8.0, 100.0), bar(3 * 86400, 9.5, 100.0)];
+        assert!(Adjuster::new(&dividend).adjust_bars(&bars).is_err());
+        assert_eq!(Adjuster::new(&dividend).with_dividends(false).adjust_bars(&bars).unwrap(), bars);
+
+        let mut daily = MockGrpcClient::with_bars(vec![bar(0, 8.0, 1.0), bar(2 * 86400, 10.0, 1.0), bar(3 * 86400, 9.5, 1.0)]);
+        let adjuster = Adjuster::new(&dividend).resolve_previous_closes(&mut daily, "AAPL").await.unwrap();
+        let queried: Vec<(String, Option<i64>)> =
+            daily.queries.lock().unwrap().iter().map(|query| (query.destination.clone(), query.epoch_end)).collect();
+        assert_eq!(queried, vec![("AAPL/1D/OHLCV".to_string(), Some(3 * 86400 - 1))]);
+        assert_eq!(adjuster.adjust_bars(&bars).unwrap()[0].close, 7.6);
+
+        // 已提供收盘价的分红不再查询；日线中没有除权日前的数据时返回错误
+        let mut empty = MockGrpcClient::default();
+        assert!(adjuster.resolve_previous_closes(&mut empty, "AAPL").await.unwrap().adjust_bars(&bars).is_ok());
+        assert_eq!(empty.query_count(), 0);
+        assert!(Adjuster::new(&dividend).resolve_previous_closes(&mut empty, "AAPL").await.is_err());
+    }
+
+    #[test]
+    fn test_actions_bucket_applies_updates_and_deletes() {
+        let rows: [ActionRow; 6] = [
+            (1, b'N', 1, 0, 0, b'7', 1000, 2.0),
+            (2, b'U', 0, 1, 0, b'7', 1000, 3.0),
+            (3, b'N', 2, 0, 0, b'/', 2000, 1.1),
+            (4, b'D', 0, 0, 2, 0, 0, 0.0),
+            (5, b'N', 3, 0, 0, b'X', 3000, 1.5),
+            (6, b'N', 4, 0, 0, b'+', 500, 0.5),
+        ];
+        let i8_column = |f: fn(&ActionRow) -> i64| {
+            rows.iter().flat_map(|row| f(row).to_le_bytes()).collect::<Vec<u8>>()
+        };
+        let dataset = NumpyDataset {
+            column_names: ["Epoch", "TextNumber", "UpdateTextNumber", "DeleteTextNumber", "ExpirationDate", "Status", "NotificationType", "Rate"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            column_types: ["i8", "i8", "i8", "i8", "i8", "u1", "u1", "f8"].iter().map(|t| t.to_string()).collect(),
+            column_data: vec![
+                i8_column(|row| row.0),
+                i8_column(|row| row.2),
+                i8_column(|row| row.3),
+                i8_column(|row| row.4),
+                i8_column(|row| row.6),
+                rows.iter().map(|row| row.1).collect(),
+                rows.iter().map(|row| row.5).collect(),
+                rows.iter().flat_map(|row| row.7.to_le_bytes()).collect(),
+            ],
+            length: rows.len() as i32,
+        };
+
+        let actions = CorporateActions::from_actions_dataset("AAPL", &dataset).unwrap();
+        assert_eq!(
+            actions.get("AAPL"),
+            &[
+                CorporateAction { epoch: 500, kind: ActionKind::Split, value: 0.5 },
+                CorporateAction { epoch: 1000, kind: ActionKind::Split, value: 3.0 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::MockGrpcClient;
+    use async_trait::async_trait;
+    use marketstore_rust_client::{
+        aggregation::Session,
+        calendar::TradingCalendar,
+        client::{Backfill, Gap, HistoricalProvider},
+        error::{MarketStoreError, Result},
+        models::OHLCVData,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    const KEY: &str = "AAPL/1Min/OHLCV";
+
+    fn bar(epoch: i64) -> OHLCVData {
+        OHLCVData { epoch, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 }
+    }
+
+    fn store(epochs: &[i64]) -> MockGrpcClient {
+        MockGrpcClient::with_bars(epochs.iter().map(|&epoch| bar(epoch)).collect())
+    }
+
+    /// 返回范围内每分钟一根bar；`fail_on` 次调用（从1计）返回错误
+    #[derive(Default)]
+    struct MinuteProvider {
+        calls: AtomicUsize,
+        fail_on: Option<usize>,
+    }
+
+    #[async_trait]
+    impl HistoricalProvider for MinuteProvider {
+        async fn fetch(&self, _: &str, start: i64, end: i64) -> Result<Vec<OHLCVData>> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.fail_on == Some(call) {
+                return Err(MarketStoreError::Connection("provider unavailable".to_string()));
+            }
+            // 多返回范围外的一根，应被过滤
+            Ok((start / 60..=end / 60 + 1).map(|minute| bar(minute * 60)).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paginated_scan_and_dry_run_report() {
+        let store = store(&[0, 60, 240, 300, 540]);
+        let mut backfill = Backfill::new(store.clone(), MinuteProvider::default()).with_page_size(2).with_dry_run(true);
+
+        let report = backfill.run(KEY, 0, 600).await.unwrap();
+        assert_eq!(
+            report.gaps,
+            vec![
+                Gap { start: 120, end: 180, missing: 2 },
+                Gap { start: 360, end: 480, missing: 3 },
+                Gap { start: 600, end: 600, missing: 1 },
+            ]
+        );
+        assert_eq!(report.missing_bars, 6);
+        assert!(report.dry_run && report.filled.is_empty());
+        // 5行按每页2行需要3次查询
+        assert_eq!(store.query_count(), 3);
+        assert_eq!(store.rows.lock().unwrap().len(), 5);
+
+        // 起点未对齐时从下一根bar开始检查
+        assert_eq!(backfill.scan(KEY, 30, 120).await.unwrap(), vec![Gap { start: 120, end: 120, missing: 1 }]);
+    }
+
+    #[tokio::test]
+    async fn test_market_hours_skip_closed_bars() {
+        // 每天 09:00 - 11:00 UTC 开市，第一天只有09:00的bar，第二天10:00有bar
+        let session = Session::new(Duration::from_secs(9 * 3600), Duration::from_secs(11 * 3600), chrono_tz::Tz::UTC);
+        let day = 86_400;
+        let store = store(&[9 * 3600, day + 10 * 3600]);
+        let mut backfill = Backfill::new(store, MinuteProvider::default()).with_market_hours(session);
+
+        let gaps = backfill.scan("AAPL/1H/OHLCV", 0, 2 * day - 1).await.unwrap();
+        // 第一天10:00与第二天09:00之间的闭市时间不打断缺口
+        assert_eq!(
+            gaps,
+            vec![Gap { start: 10 * 3600, end: day + 9 * 3600, missing: 2 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calendar_checks_whole_bar_for_daily_and_hourly_keys() {
+        // 2024-03-11（周一）00:00 UTC；日线bar的起点在纽约仍是前一天晚上
+        let monday = 1_710_115_200;
+        let day = 86_400;
+        let store = store(&[monday, monday + 2 * day, monday + 7 * day]);
+        let mut backfill = Backfill::new(store, MinuteProvider::default()).with_market_hours(TradingCalendar::nasdaq());
+
+        // 周二、周四、周五缺失，周末不算缺失也不打断缺口
+        let gaps = backfill.scan("AAPL/1D/OHLCV", monday - 2 * day, monday + 7 * day).await.unwrap();
+        assert_eq!(
+            gaps,
+            vec![
+                Gap { start: monday + day, end: monday + day, missing: 1 },
+                Gap { start: monday + 3 * day, end: monday + 4 * day, missing: 2 },
+            ]
+        );
+
+        // 09:00 EDT（13:00 UTC）的1H bar包含09:30开盘，15:00 EDT的bar到收盘为止
+        let mut backfill = Backfill::new(MockGrpcClient::default(), MinuteProvider::default())
+            .with_market_hours(TradingCalendar::nasdaq());
+        let tuesday = monday + day;
+        let gaps = backfill.scan("AAPL/1H/OHLCV", tuesday, tuesday + day - 1).await.unwrap();
+        assert_eq!(gaps, vec![Gap { start: tuesday + 13 * 3600, end: tuesday + 19 * 3600, missing: 7 }]);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_backfill_resumes_from_state_file() {
+        let state = std::env::temp_dir().join(format!("marketstore-backfill-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&state);
+        let store = store(&[0, 120, 300]);
+
+        let provider = MinuteProvider { fail_on: Some(2), ..Default::default() };
+        let mut backfill = Backfill::new(store.clone(), provider).with_state_file(&state);
+        assert!(backfill.run(KEY, 0, 300).await.is_err());
+        assert!(state.exists());
+        assert_eq!(store.rows.lock().unwrap().keys().copied().collect::<Vec<_>>(), vec![0, 60, 120, 300]);
+
+        let queries = store.query_count();
+        let mut backfill = Backfill::new(store.clone(), MinuteProvider::default()).with_state_file(&state);
+        let report = backfill.run(KEY, 0, 300).await.unwrap();
+        assert!(report.resumed);
+        assert_eq!(store.query_count(), queries);
+        assert_eq!(report.filled.iter().map(|filled| (filled.gap.start, filled.rows)).collect::<Vec<_>>(), vec![(60, 1), (180, 2)]);
+        assert!(!state.exists());
+        assert!(backfill.scan(KEY, 0, 300).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        aggregation::{BarBuilder, Trade},
+        models::OHLCVData,
+        utils::Timeframe,
+    };
+    use std::time::Duration;
+
+    fn trade(symbol: &str, price: f64, epoch: i64, nanoseconds: i32) -> Trade {
+        Trade { symbol: symbol.to_string(), price, size: 1.0, epoch, nanoseconds }
+    }
+
+    #[test]
+    fn test_bars_close_on_interval_boundary() {
+        let mut builder = BarBuilder::new(Timeframe::parse("1Min").unwrap());
+        assert!(builder.push(&trade("AAPL", 10.0, 60, 500)).unwrap().is_empty());
+        // 同一分钟内乱序的成交按时间确定Open/Close
+        assert!(builder.push(&trade("AAPL", 12.0, 61, 0)).unwrap().is_empty());
+        assert!(builder.push(&trade("AAPL", 9.0, 60, 100)).unwrap().is_empty());
+        assert!(builder.push(&trade("MSFT", 50.0, 90, 0)).unwrap().is_empty());
+
+        let closed = builder.push(&trade("AAPL", 11.0, 120, 0)).unwrap();
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[0].symbol, "AAPL");
+        assert_eq!(closed[0].bar, OHLCVData { epoch: 60, open: 9.0, high: 12.0, low: 9.0, close: 12.0, volume: 3.0 });
+        assert_eq!(closed[0].trades, 3);
+        assert_eq!(closed[1].symbol, "MSFT");
+        assert_eq!(builder.open_bars(), 1);
+
+        // 没有成交时由时钟推进水位
+        let closed = builder.advance(180);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].bar.epoch, 120);
+        assert_eq!(builder.open_bars(), 0);
+    }
+
+    #[test]
+    fn test_late_trades_within_grace_period_are_kept() {
+        let mut builder = BarBuilder::new(Timeframe::parse("1Sec").unwrap()).with_grace_period(Duration::from_millis(500));
+        builder.push(&trade("AAPL", 10.0, 100, 0)).unwrap();
+        // 水位到达101.2秒，bar 100仍在宽限期内
+        assert!(builder.push(&trade("AAPL", 11.0, 101, 200_000_000)).unwrap().is_empty());
+        assert!(builder.push(&trade("AAPL", 9.0, 100, 900_000_000)).unwrap().is_empty());
+
+        let closed = builder.push(&trade("AAPL", 11.5, 101, 600_000_000)).unwrap();
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].bar.low, closed[0].bar.close, closed[0].trades), (9.0, 9.0, 2));
+
+        // 超过宽限期的成交被丢弃
+        assert!(builder.push(&trade("AAPL", 1.0, 100, 950_000_000)).unwrap().is_empty());
+        assert_eq!(builder.late_trades(), 1);
+        assert!(builder.push(&trade("AAPL", 1.0, 102, 1_000_000_000)).is_err());
+
+        let flushed = builder.flush();
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0].bar.epoch, 101);
+    }
+
+    #[test]
+    fn test_write_requests_group_bars_by_symbol() {
+        let mut builder = BarBuilder::new(Timeframe::parse("1Min").unwrap()).with_attr_group("BARS");
+        let mut bars = Vec::new();
+        for (symbol, epoch) in [("AAPL", 0), ("MSFT", 10), ("AAPL", 60)] {
+            bars.extend(builder.push(&trade(symbol, 1.0, epoch, 0)).unwrap());
+        }
+        bars.extend(builder.flush());
+        let requests = builder.write_requests(bars);
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[0].symbol.as_str(), requests[0].timeframe.as_str(), requests[0].attr_group.as_str()), ("AAPL", "1Min", "BARS"));
+        assert_eq!(requests[0].data.iter().map(|bar| bar.epoch).collect::<Vec<_>>(), vec![0, 60]);
+        assert_eq!(requests[1].symbol, "MSFT");
+    }
+}
@@ -0,0 +1,183 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{BufferedWriter, BufferedWriterConfig, GrpcClient},
+        client::grpc_client::proto::{
+            self,
+            marketstore_server::{Marketstore, MarketstoreServer},
+        },
+        models::OHLCVData,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tonic::{Request, Response, Status};
+
+    /// 记录收到的写入请求；`unavailable` 为true时模拟节点不可用，`BAD` 开头的key被服务器拒绝
+    struct MockServer {
+        writes: mpsc::UnboundedSender<proto::MultiWriteRequest>,
+        unavailable: Arc<AtomicBool>,
+    }
+
+    #[tonic::async_trait]
+    impl Marketstore for MockServer {
+        async fn query(&self, _: Request<proto::MultiQueryRequest>) -> Result<Response<proto::MultiQueryResponse>, Status> {
+            Err(Status::unimplemented("query"))
+        }
+
+        async fn create(&self, _: Request<proto::MultiCreateRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Ok(Response::new(proto::MultiServerResponse::default()))
+        }
+
+        async fn write(&self, request: Request<proto::MultiWriteRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            if self.unavailable.load(Ordering::SeqCst) {
+                return Err(Status::unavailable("node down"));
+            }
+            let request = request.into_inner();
+            let responses = epochs(&request)
+                .iter()
+                .map(|(key, _)| proto::ServerResponse {
+                    error: if key.starts_with("BAD") { "schema mismatch".to_string() } else { String::new() },
+                    version: String::new(),
+                })
+                .collect();
+            self.writes.send(request).unwrap();
+            Ok(Response::new(proto::MultiServerResponse { responses }))
+        }
+
+        async fn destroy(&self, _: Request<proto::MultiKeyRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Ok(Response::new(proto::MultiServerResponse::default()))
+        }
+
+        async fn list_symbols(&self, _: Request<proto::ListSymbolsRequest>) -> Result<Response<proto::ListSymbolsResponse>, Status> {
+            Ok(Response::new(proto::ListSymbolsResponse::default()))
+        }
+
+        async fn server_version(&self, _: Request<proto::ServerVersionRequest>) -> Result<Response<proto::ServerVersionResponse>, Status> {
+            Ok(Response::new(proto::ServerVersionResponse::default()))
+        }
+    }
+
+    async fn spawn_server(unavailable: Arc<AtomicBool>) -> (GrpcClient, mpsc::UnboundedReceiver<proto::MultiWriteRequest>) {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(MarketstoreServer::new(MockServer { writes: tx, unavailable }))
+                .serve(addr)
+                .await
+                .unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        (GrpcClient::connect(format!("http://{}", addr)).await.unwrap(), rx)
+    }
+
+    fn bar(epoch: i64) -> OHLCVData {
+        OHLCVData { epoch, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 }
+    }
+
+    /// 每个key对应的Epoch列
+    fn epochs(request: &proto::MultiWriteRequest) -> Vec<(String, Vec<i64>)> {
+        request
+            .requests
+            .iter()
+            .map(|write| {
+                let dataset = write.data.as_ref().unwrap();
+                let key = dataset.lengths.keys().next().unwrap().clone();
+                let column = &dataset.data.as_ref().unwrap().column_data[0];
+                (key, column.chunks(8).map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap())).collect())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_size_threshold_flushes_all_keys_in_one_request() {
+        let (client, mut writes) = spawn_server(Arc::new(AtomicBool::new(false))).await;
+        let config = BufferedWriterConfig {
+            max_batch_rows: 4,
+            flush_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let writer = BufferedWriter::new(client, config);
+
+        writer.write("AAPL/1Min/OHLCV", vec![bar(180), bar(60)]).unwrap();
+        writer.write("MSFT/1Min/OHLCV", vec![bar(60)]).unwrap();
+        assert!(writer.write("AAPL/1Min", vec![bar(0)]).is_err());
+        writer.write("AAPL/1Min/OHLCV", vec![bar(120)]).unwrap();
+
+        // 全部key合并为一个批量请求
+        let request = tokio::time::timeout(Duration::from_secs(2), writes.recv()).await.unwrap().unwrap();
+        assert_eq!(
+            epochs(&request),
+            vec![
+                ("AAPL/1Min/OHLCV".to_string(), vec![60, 120, 180]),
+                ("MSFT/1Min/OHLCV".to_string(), vec![60]),
+            ]
+        );
+        assert!(writes.try_recv().is_err());
+
+        writer.flush().await.unwrap();
+        let metrics = writer.metrics();
+        assert_eq!((metrics.queued_rows, metrics.queued_keys, metrics.flushes, metrics.rows_written), (0, 0, 1, 4));
+        assert!(metrics.last_flush_latency.is_some());
+        writer.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interval_flush_and_close_drains_buffer() {
+        let (client, mut writes) = spawn_server(Arc::new(AtomicBool::new(false))).await;
+        let config = BufferedWriterConfig {
+            max_batch_rows: 1000,
+            flush_interval: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let writer = BufferedWriter::new(client, config);
+
+        writer.write("AAPL/1Min/OHLCV", vec![bar(60)]).unwrap();
+        let request = tokio::time::timeout(Duration::from_secs(2), writes.recv()).await.unwrap().unwrap();
+        assert_eq!(epochs(&request)[0].1, vec![60]);
+
+        writer.write("AAPL/1Min/OHLCV", vec![bar(120)]).unwrap();
+        writer.close().await.unwrap();
+        let request = writes.try_recv().unwrap();
+        assert_eq!(epochs(&request)[0].1, vec![120]);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_node_keeps_rows_and_rejected_keys_are_dropped() {
+        let unavailable = Arc::new(AtomicBool::new(true));
+        let (client, mut writes) = spawn_server(unavailable.clone()).await;
+        let config = BufferedWriterConfig {
+            max_batch_rows: 1000,
+            flush_interval: Duration::from_secs(60),
+            max_buffered_rows: 3,
+            ..Default::default()
+        };
+        let writer = BufferedWriter::new(client, config);
+
+        writer.write("AAPL/1Min/OHLCV", vec![bar(120)]).unwrap();
+        writer.write("BAD/1Min/OHLCV", vec![bar(60)]).unwrap();
+        assert!(writer.flush().await.is_err());
+        let metrics = writer.metrics();
+        assert_eq!((metrics.queued_rows, metrics.failed_flushes, metrics.flushes), (2, 1, 0));
+
+        // 超过内存上限的写入被拒绝
+        assert!(writer.write("AAPL/1Min/OHLCV", vec![bar(60), bar(180)]).is_err());
+        writer.write("AAPL/1Min/OHLCV", vec![bar(60)]).unwrap();
+
+        // 节点恢复后批量请求被拒绝，逐个key重发：被拒绝的key丢弃，其余key正常写入
+        unavailable.store(false, Ordering::SeqCst);
+        assert!(writer.flush().await.is_err());
+        let keys = |request: &proto::MultiWriteRequest| epochs(request).into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys(&writes.try_recv().unwrap()), vec!["AAPL/1Min/OHLCV", "BAD/1Min/OHLCV"]);
+        assert_eq!(epochs(&writes.try_recv().unwrap()), vec![("AAPL/1Min/OHLCV".to_string(), vec![60, 120])]);
+        assert_eq!(keys(&writes.try_recv().unwrap()), vec!["BAD/1Min/OHLCV"]);
+        let metrics = writer.metrics();
+        assert_eq!((metrics.queued_rows, metrics.rows_written, metrics.dropped_rows), (0, 2, 1));
+
+        writer.flush().await.unwrap();
+        assert!(writes.try_recv().is_err());
+        writer.close().await.unwrap();
+    }
+}
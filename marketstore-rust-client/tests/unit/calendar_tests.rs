@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use marketstore_rust_client::{
+        calendar::{MarketHours, TradingCalendar},
+        models::{OHLCVData, QueryRequest},
+        utils::{create_numpy_dataset_from_ohlcv, numpy_dataset_to_ohlcv, Timeframe},
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_nasdaq_sessions_follow_dst_holidays_and_early_closes() {
+        let nasdaq = TradingCalendar::nasdaq();
+        // 夏令时 13:30 - 20:00 UTC，冬令时 14:30 - 21:00 UTC
+        assert_eq!(nasdaq.session(date(2024, 7, 1)), Some((1_719_840_600, 1_719_864_000)));
+        assert_eq!(nasdaq.session(date(2024, 1, 2)), Some((1_704_205_800, 1_704_229_200)));
+        assert_eq!(nasdaq.session(date(2024, 3, 11)).unwrap().0, 1_710_163_800);
+        // 独立日休市，前一天13:00提前收盘
+        assert_eq!(nasdaq.session(date(2024, 7, 4)), None);
+        assert_eq!(nasdaq.session(date(2024, 7, 3)).unwrap().1, 1_720_026_000);
+        assert_eq!(nasdaq.session(date(2024, 7, 6)), None);
+
+        assert!(nasdaq.is_open(1_719_840_600));
+        assert!(!nasdaq.is_open(1_719_840_599));
+        assert!(!nasdaq.is_open(1_719_864_000));
+        assert_eq!(TradingCalendar::builtin("nyse").unwrap().session(date(2024, 7, 1)), nasdaq.session(date(2024, 7, 1)));
+        assert!(TradingCalendar::builtin("LSE").is_err());
+
+        let crypto = TradingCalendar::builtin("crypto").unwrap();
+        assert!(crypto.is_open(1_720_224_000) && MarketHours::is_open(&crypto, 0));
+    }
+
+    #[test]
+    fn test_last_trading_days_range_for_query_builder() {
+        let nasdaq = TradingCalendar::nasdaq();
+        // 2024-07-08 周一 15:00 UTC，已开盘：7月8日、5日、3日（4日休市）
+        let now = 1_720_450_800;
+        assert_eq!(nasdaq.last_trading_days(3, now), Some((1_720_013_400, now)));
+        // 12:00 UTC 尚未开盘：7月5日、3日、2日，截止到5日收盘
+        let before_open = 1_720_440_000;
+        assert_eq!(nasdaq.last_trading_days(3, before_open), Some((1_719_927_000, 1_720_209_599)));
+        assert_eq!(nasdaq.last_trading_days(0, now), None);
+
+        let request: QueryRequest = QueryRequest::builder()
+            .symbol("AAPL")
+            .timeframe("1Min")
+            .attr_group("OHLCV")
+            .last_trading_days(&nasdaq, 3, now)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!((request.epoch_start, request.epoch_end), (Some(1_720_013_400), Some(now)));
+    }
+
+    #[test]
+    fn test_json_calendar_filters_query_results() {
+        let json = r#"{
+            "timezone": "+08:00",
+            "open_time": "09:30:00",
+            "close_time": "15:00:00",
+            "non_trading_days": ["2024-02-12"]
+        }"#;
+        let calendar = TradingCalendar::from_json("SSE", json).unwrap();
+        // 2024-02-09 周五 01:30 UTC 开盘，07:00 UTC 收盘
+        assert_eq!(calendar.session(date(2024, 2, 9)), Some((1_707_442_200, 1_707_462_000)));
+        assert_eq!(calendar.session(date(2024, 2, 12)), None);
+
+        let bar = |epoch| OHLCVData { epoch, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 };
+        let bars = vec![bar(1_707_442_140), bar(1_707_442_200), bar(1_707_461_940), bar(1_707_462_000)];
+        let minute = Timeframe::parse("1Min").unwrap();
+        let filtered = calendar.filter_dataset(&create_numpy_dataset_from_ohlcv(&bars), &minute).unwrap();
+        assert_eq!(filtered.length, 2);
+        let epochs: Vec<i64> = numpy_dataset_to_ohlcv(&filtered).unwrap().iter().map(|bar| bar.epoch).collect();
+        assert_eq!(epochs, vec![1_707_442_200, 1_707_461_940]);
+        assert_eq!(calendar.filter_bars(&bars, &minute).len(), 2);
+
+        assert!(TradingCalendar::from_json("bad", r#"{"timezone": "Mars/Olympus", "open_time": "09:30:00", "close_time": "16:00:00"}"#).is_err());
+
+        // 任意IANA时区按时区数据库处理夏令时：伦敦夏季为UTC+1，冬季为UTC
+        let lse = r#"{"timezone": "Europe/London", "open_time": "08:00:00", "close_time": "16:30:00"}"#;
+        let lse = TradingCalendar::from_json("LSE", lse).unwrap();
+        assert_eq!(lse.session(date(2024, 7, 1)), Some((1_719_817_200, 1_719_847_800)));
+        assert_eq!(lse.session(date(2024, 1, 2)), Some((1_704_182_400, 1_704_213_000)));
+    }
+
+    #[test]
+    fn test_filters_keep_bars_overlapping_a_session() {
+        let nasdaq = TradingCalendar::nasdaq();
+        let bar = |epoch| OHLCVData { epoch, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 };
+        let day = 86_400;
+
+        // 日线bar位于UTC零点，在纽约仍是前一天晚上；按bar日期是否为交易日保留
+        let monday = 1_710_115_200;
+        let daily: Vec<OHLCVData> = (-2..5).map(|i| bar(monday + i * day)).collect();
+        let one_day = Timeframe::parse("1D").unwrap();
+        let filtered = nasdaq.filter_dataset(&create_numpy_dataset_from_ohlcv(&daily), &one_day).unwrap();
+        let epochs: Vec<i64> = numpy_dataset_to_ohlcv(&filtered).unwrap().iter().map(|bar| bar.epoch).collect();
+        assert_eq!(epochs, (0..5).map(|i| monday + i * day).collect::<Vec<_>>());
+        // 2024-07-04 独立日休市
+        assert_eq!(nasdaq.filter_bars(&[bar(1_720_051_200)], &one_day).len(), 0);
+        assert!(!nasdaq.is_open(monday) && nasdaq.is_open_between(monday, monday + day));
+
+        // 09:00 EDT（13:00 UTC）开始的1H bar包含09:30开盘；20:00 UTC 收盘后的bar被丢弃
+        let one_hour = Timeframe::parse("1H").unwrap();
+        let hourly: Vec<OHLCVData> = (12..21).map(|hour| bar(monday + hour * 3600)).collect();
+        let kept: Vec<i64> = nasdaq.filter_bars(&hourly, &one_hour).iter().map(|bar| bar.epoch).collect();
+        assert_eq!(kept, (13..20).map(|hour| monday + hour * 3600).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,167 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{ChangeBatch, ChangeDataCapture, CheckpointStore, FileCheckpoint, ReconnectPolicy, SchemaResolver},
+        client::grpc_client::proto::{
+            replication_server::{Replication, ReplicationServer},
+            GetWalStreamRequest, GetWalStreamResponse,
+        },
+        error::{MarketStoreError, Result},
+        models::DataShape,
+        storage::{RecordType, TransactionGroup, WriteTransactionSet},
+    };
+    use futures::StreamExt;
+    use std::path::PathBuf;
+    use std::pin::Pin;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tonic::{Request, Response, Status};
+
+    fn shape(name: &str, data_type: &str) -> DataShape {
+        DataShape { name: name.to_string(), data_type: data_type.to_string() }
+    }
+
+    /// 2024-01-01 00:00 起第 `tgid` 分钟的一行Close；`with_shapes` 为false时模拟不带数据结构的写入
+    fn group(tgid: i64, with_shapes: bool) -> TransactionGroup {
+        TransactionGroup {
+            transaction_group_id: tgid,
+            write_sets: vec![WriteTransactionSet {
+                record_type: RecordType::Fixed,
+                wal_key_path: "AAPL/1Min/OHLCV/2024.bin".to_string(),
+                variable_record_length: 0,
+                offset: 0,
+                index: tgid + 1,
+                payload: (tgid as f32).to_le_bytes().to_vec(),
+                data_shapes: if with_shapes { vec![shape("Epoch", "i8"), shape("Close", "f4")] } else { vec![] },
+            }],
+        }
+    }
+
+    type WalStream = Pin<Box<dyn futures::Stream<Item = std::result::Result<GetWalStreamResponse, Status>> + Send>>;
+
+    struct MockPrimary {
+        groups: Vec<TransactionGroup>,
+    }
+
+    #[tonic::async_trait]
+    impl Replication for MockPrimary {
+        type GetWALStreamStream = WalStream;
+
+        async fn get_wal_stream(&self, _: Request<GetWalStreamRequest>) -> std::result::Result<Response<WalStream>, Status> {
+            let messages: Vec<_> = self
+                .groups
+                .iter()
+                .map(|group| Ok(GetWalStreamResponse { transaction_group: group.encode().unwrap() }))
+                .collect();
+            Ok(Response::new(Box::pin(futures::stream::iter(messages).chain(futures::stream::pending()))))
+        }
+    }
+
+    async fn spawn_primary(groups: Vec<TransactionGroup>) -> String {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(ReplicationServer::new(MockPrimary { groups }))
+                .serve(addr)
+                .await
+                .unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        format!("http://{}", addr)
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("marketstore-cdc-{}-{}.checkpoint", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn fast_policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
+
+    /// 在后台运行CDC，将handler收到的批次转发到channel；第一次收到 `fail_tgid` 时返回错误
+    fn spawn_cdc(cdc: ChangeDataCapture, fail_tgid: Option<i64>) -> mpsc::UnboundedReceiver<ChangeBatch> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut failed = false;
+            let _ = cdc
+                .run(move |batch: ChangeBatch| {
+                    let fail = fail_tgid == Some(batch.transaction_group_id) && !failed;
+                    failed |= fail;
+                    let _ = sender.send(batch);
+                    async move {
+                        if fail {
+                            Err(MarketStoreError::Connection("downstream unavailable".to_string()))
+                        } else {
+                            Ok(())
+                        }
+                    }
+                })
+                .await;
+        });
+        receiver
+    }
+
+    async fn next_batch(receiver: &mut mpsc::UnboundedReceiver<ChangeBatch>) -> ChangeBatch {
+        tokio::time::timeout(Duration::from_secs(2), receiver.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failed_batches_are_redelivered_before_checkpoint() {
+        let url = spawn_primary(vec![group(1, true), group(2, true)]).await;
+        let path = checkpoint_path("redeliver");
+        let mut batches = spawn_cdc(ChangeDataCapture::new(url, fast_policy(), FileCheckpoint::new(&path)), Some(1));
+
+        let first = next_batch(&mut batches).await;
+        assert_eq!(first.transaction_group_id, 1);
+        assert_eq!(first.changes[0].key, "AAPL/1Min/OHLCV");
+        assert_eq!(first.changes[0].schema, vec![shape("Epoch", "i8"), shape("Close", "f4")]);
+        assert_eq!(first.changes[0].rows[0]["Epoch"].as_i64().unwrap(), 1704067260);
+        assert_eq!(first.changes[0].rows[0]["Close"].as_f64().unwrap(), 1.0);
+
+        // 失败后重试同一批次
+        assert_eq!(next_batch(&mut batches).await, first);
+        assert_eq!(next_batch(&mut batches).await.transaction_group_id, 2);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(FileCheckpoint::new(&path).load().unwrap(), Some(2));
+        assert!(!path.with_extension("tmp").exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resumes_after_checkpoint() {
+        let url = spawn_primary(vec![group(1, true), group(2, true), group(3, true)]).await;
+        let path = checkpoint_path("resume");
+        FileCheckpoint::new(&path).save(2).unwrap();
+
+        let mut batches = spawn_cdc(ChangeDataCapture::new(url, fast_policy(), FileCheckpoint::new(&path)), None);
+        assert_eq!(next_batch(&mut batches).await.transaction_group_id, 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    struct FixedSchema;
+
+    #[tonic::async_trait]
+    impl SchemaResolver for FixedSchema {
+        async fn resolve(&self, key: &str) -> Result<Vec<DataShape>> {
+            assert_eq!(key, "AAPL/1Min/OHLCV");
+            Ok(vec![shape("Epoch", "i8"), shape("Close", "f4")])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolves_schema_when_group_has_no_shapes() {
+        let url = spawn_primary(vec![group(5, false)]).await;
+        let path = checkpoint_path("schema");
+
+        let cdc = ChangeDataCapture::new(url, fast_policy(), FileCheckpoint::new(&path)).with_schema_resolver(FixedSchema);
+        let batch = next_batch(&mut spawn_cdc(cdc, None)).await;
+        assert_eq!(batch.changes[0].rows[0]["Close"].as_f64().unwrap(), 5.0);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,113 @@
+// 各测试文件只用到其中一部分
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use marketstore_rust_client::{
+    client::GrpcClientTrait,
+    error::{MarketStoreError, Result},
+    models::{DataShape, NumpyMultiDataset, OHLCVData, QueryRequest, SymbolFormat, WriteRequest},
+    utils::{bucket_key, create_numpy_dataset_from_ohlcv},
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// 内存中的 `GrpcClientTrait` 实现，所有key共用一个按Epoch存放的bucket，克隆共享同一份状态
+///
+/// 记录每次查询与写入；`down` 为true时所有请求返回连接错误，symbol以 `BAD` 开头的写入被服务器拒绝，
+/// 其余不支持的接口返回错误。
+#[derive(Clone, Default)]
+pub struct MockGrpcClient {
+    pub rows: Arc<Mutex<BTreeMap<i64, OHLCVData>>>,
+    pub queries: Arc<Mutex<Vec<QueryRequest>>>,
+    /// 写入的 (key, 首行Epoch)
+    pub written: Arc<Mutex<Vec<(String, i64)>>>,
+    pub down: Arc<Mutex<bool>>,
+}
+
+impl MockGrpcClient {
+    pub fn with_bars(bars: Vec<OHLCVData>) -> Self {
+        let client = Self::default();
+        client.rows.lock().unwrap().extend(bars.into_iter().map(|bar| (bar.epoch, bar)));
+        client
+    }
+
+    pub fn query_count(&self) -> usize {
+        self.queries.lock().unwrap().len()
+    }
+
+    fn check(&self) -> Result<()> {
+        if *self.down.lock().unwrap() {
+            Err(MarketStoreError::Connection("connection refused".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn unsupported(method: &str) -> MarketStoreError {
+        MarketStoreError::InvalidData(format!("MockGrpcClient does not support {}", method))
+    }
+}
+
+#[async_trait]
+impl GrpcClientTrait for MockGrpcClient {
+    /// 返回 `[epoch_start, epoch_end]` 内的行，按 `limit_from_start` 从头或从尾取 `limit_record_count` 行
+    async fn query(&mut self, request: QueryRequest) -> Result<NumpyMultiDataset> {
+        self.check()?;
+        let start = request.epoch_start.unwrap_or(i64::MIN);
+        let end = request.epoch_end.unwrap_or(i64::MAX);
+        let limit = request.limit_record_count.map_or(usize::MAX, |limit| limit.max(0) as usize);
+        let rows: Vec<OHLCVData> = {
+            let stored = self.rows.lock().unwrap();
+            let range = stored.range(start..=end).map(|(_, row)| row.clone());
+            if request.limit_from_start {
+                range.take(limit).collect()
+            } else {
+                let mut rows: Vec<OHLCVData> = range.rev().take(limit).collect();
+                rows.reverse();
+                rows
+            }
+        };
+        self.queries.lock().unwrap().push(request);
+        Ok(NumpyMultiDataset {
+            data: Some(create_numpy_dataset_from_ohlcv(&rows)),
+            start_index: HashMap::new(),
+            lengths: HashMap::new(),
+        })
+    }
+
+    async fn write(&mut self, symbol: &str, timeframe: &str, attr_group: &str, data: Vec<OHLCVData>) -> Result<()> {
+        self.write_batch(vec![WriteRequest::new(symbol, timeframe, attr_group, data)]).await
+    }
+
+    async fn write_batch(&mut self, requests: Vec<WriteRequest>) -> Result<()> {
+        self.check()?;
+        if requests.iter().any(|request| request.symbol.starts_with("BAD")) {
+            return Err(MarketStoreError::InvalidData("Write failed: schema mismatch".to_string()));
+        }
+        let mut written = self.written.lock().unwrap();
+        let mut rows = self.rows.lock().unwrap();
+        for request in requests {
+            let key = bucket_key(&request.symbol, &request.timeframe, &request.attr_group);
+            written.push((key, request.data.first().map_or(0, |row| row.epoch)));
+            rows.extend(request.data.into_iter().map(|row| (row.epoch, row)));
+        }
+        Ok(())
+    }
+
+    async fn list_symbols(&mut self, _: SymbolFormat) -> Result<Vec<String>> {
+        Err(Self::unsupported("list_symbols"))
+    }
+
+    async fn create_bucket(&mut self, _: &str, _: &str, _: &str, _: Vec<DataShape>) -> Result<()> {
+        Err(Self::unsupported("create_bucket"))
+    }
+
+    async fn destroy_bucket(&mut self, _: &str, _: &str, _: &str) -> Result<()> {
+        Err(Self::unsupported("destroy_bucket"))
+    }
+
+    async fn server_version(&mut self) -> Result<String> {
+        self.check()?;
+        Ok("mock".to_string())
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        models::{DataShape, QueryRequest},
+        storage::{Catalog, DataFile, DataFileHeader, RecordType, DATA_FILE_HEADER_SIZE},
+        utils::{numpy_dataset_to_records, year_start_epoch, Timeframe},
+    };
+    use std::path::{Path, PathBuf};
+
+    const MINUTE_NS: i64 = 60_000_000_000;
+
+    /// 按服务器 `io.Header` 布局构造文件头
+    fn header(year: i64, record_type: i64, record_length: i64, elements: &[(&str, u8)]) -> Vec<u8> {
+        let mut buf = vec![0u8; DATA_FILE_HEADER_SIZE];
+        buf[0..8].copy_from_slice(&2i64.to_le_bytes());
+        buf[8..8 + 11].copy_from_slice(b"test bucket");
+        for (offset, value) in [(264, year), (272, MINUTE_NS), (280, record_type), (288, elements.len() as i64), (296, record_length)] {
+            buf[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        for (i, (name, element_type)) in elements.iter().enumerate() {
+            buf[312 + i * 32..312 + i * 32 + name.len()].copy_from_slice(name.as_bytes());
+            buf[33080 + i] = *element_type;
+        }
+        buf
+    }
+
+    /// 定长1Min文件：Open/Close两列f4，记录长度为8字节index加8字节列数据
+    fn fixed_file(year: i32, rows: &[(i64, f32, f32)]) -> Vec<u8> {
+        let mut buf = header(year as i64, 0, 16, &[("Open", 0), ("Close", 0)]);
+        let records = rows.iter().map(|(index, _, _)| *index).max().unwrap_or(0) as usize;
+        buf.resize(DATA_FILE_HEADER_SIZE + records * 16, 0);
+        for (index, open, close) in rows {
+            let offset = DATA_FILE_HEADER_SIZE + (*index as usize - 1) * 16;
+            buf[offset..offset + 8].copy_from_slice(&index.to_le_bytes());
+            buf[offset + 8..offset + 12].copy_from_slice(&open.to_le_bytes());
+            buf[offset + 12..offset + 16].copy_from_slice(&close.to_le_bytes());
+        }
+        buf
+    }
+
+    fn write_catalog(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("marketstore-catalog-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let bucket = root.join("AAPL/1Min/OHLCV");
+        std::fs::create_dir_all(&bucket).unwrap();
+        std::fs::create_dir_all(root.join("lost+found")).unwrap();
+        for (dir, category) in [("", "Symbol"), ("AAPL", "Timeframe"), ("AAPL/1Min", "AttributeGroup"), ("AAPL/1Min/OHLCV", "Year")] {
+            std::fs::write(root.join(dir).join("category_name"), category).unwrap();
+        }
+        std::fs::write(bucket.join("2023.bin"), fixed_file(2023, &[(525600, 1.0, 1.5)])).unwrap();
+        std::fs::write(bucket.join("2024.bin"), fixed_file(2024, &[(1, 2.0, 2.5), (3, 3.0, 3.5), (4, 4.0, 4.5)])).unwrap();
+        root
+    }
+
+    fn request(start: Option<i64>, end: Option<i64>) -> QueryRequest {
+        QueryRequest {
+            destination: "AAPL/1Min/OHLCV".to_string(),
+            epoch_start: start,
+            epoch_end: end,
+            limit_record_count: None,
+            limit_from_start: false,
+            columns: Vec::new(),
+        }
+    }
+
+    fn epochs(root: &Path, request: &QueryRequest) -> Vec<i64> {
+        let dataset = Catalog::open(root).unwrap().query(request).unwrap();
+        numpy_dataset_to_records(dataset.data.as_ref().unwrap())
+            .unwrap()
+            .iter()
+            .map(|row| row["Epoch"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_catalog_reads_fixed_records_across_years() {
+        let root = write_catalog("fixed");
+        let catalog = Catalog::open(&root).unwrap();
+        assert_eq!(catalog.categories(), ["Symbol", "Timeframe", "AttributeGroup", "Year"]);
+        assert_eq!(catalog.keys().collect::<Vec<_>>(), vec!["AAPL/1Min/OHLCV"]);
+        assert_eq!(catalog.years("AAPL/1Min/OHLCV"), vec![2023, 2024]);
+
+        let header = catalog.data_file("AAPL/1Min/OHLCV", 2024).unwrap().header().clone();
+        assert_eq!(header.description, "test bucket");
+        assert_eq!(header.record_type, RecordType::Fixed);
+        assert_eq!(header.intervals(), 1440);
+        assert_eq!(header.data_shapes[1], DataShape { name: "Close".to_string(), data_type: "f4".to_string() });
+
+        let jan1 = year_start_epoch(2024);
+        let dataset = catalog.query(&request(None, None)).unwrap();
+        assert_eq!(dataset.lengths["AAPL/1Min/OHLCV"], 4);
+        let rows = numpy_dataset_to_records(dataset.data.as_ref().unwrap()).unwrap();
+        assert_eq!(rows[0]["Epoch"].as_i64().unwrap(), jan1 - 60);
+        assert_eq!(rows[1]["Epoch"].as_i64().unwrap(), jan1);
+        assert_eq!(rows[3]["Close"].as_f64().unwrap(), 4.5);
+
+        // 范围两端包含，空洞不返回
+        assert_eq!(epochs(&root, &request(Some(jan1), Some(jan1 + 120))), vec![jan1, jan1 + 120]);
+        // 范围落在记录中间或其它年份时只读取对应的位置
+        assert_eq!(epochs(&root, &request(Some(jan1 + 90), None)), vec![jan1 + 120, jan1 + 180]);
+        assert_eq!(epochs(&root, &request(Some(jan1 - 60), Some(jan1 + 59))), vec![jan1 - 60, jan1]);
+        let file = catalog.data_file("AAPL/1Min/OHLCV", 2024).unwrap();
+        assert_eq!(file.read(Some(year_start_epoch(2025)), None).unwrap().length, 0);
+        assert_eq!(file.read(None, Some(jan1 - 1)).unwrap().length, 0);
+        assert_eq!(file.read(Some(jan1 + 240), Some(year_start_epoch(2025))).unwrap().length, 0);
+
+        // 与服务器一致，limit为0表示不限制
+        let mut unlimited = request(None, None);
+        unlimited.limit_record_count = Some(0);
+        assert_eq!(epochs(&root, &unlimited).len(), 4);
+
+        let mut last_two = request(None, None);
+        last_two.limit_record_count = Some(2);
+        last_two.columns = vec!["Close".to_string()];
+        let dataset = catalog.query(&last_two).unwrap().data.unwrap();
+        assert_eq!(dataset.column_names, vec!["Epoch", "Close"]);
+        assert_eq!(numpy_dataset_to_records(&dataset).unwrap()[0]["Epoch"].as_i64().unwrap(), jan1 + 120);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reads_compressed_variable_records() {
+        let timeframe = Timeframe::parse("1Min").unwrap();
+        let interval_start = year_start_epoch(2024) + 60;
+        let ticks = timeframe.time_to_ticks(interval_start, interval_start + 30, 250_000_000);
+
+        // 一个interval内两行：Bid(f4) + interval ticks
+        let mut rows = Vec::new();
+        for (bid, ticks) in [(10.5f32, 0u32), (11.0, ticks)] {
+            rows.extend_from_slice(&bid.to_le_bytes());
+            rows.extend_from_slice(&ticks.to_le_bytes());
+        }
+        let compressed = snap::raw::Encoder::new().compress_vec(&rows).unwrap();
+
+        let mut buf = header(2024, 1, 24, &[("Bid", 0)]);
+        buf.resize(DATA_FILE_HEADER_SIZE + 2 * 24, 0);
+        let data_offset = buf.len() as i64;
+        let primary = DATA_FILE_HEADER_SIZE + 24;
+        for (i, value) in [2, data_offset, compressed.len() as i64].into_iter().enumerate() {
+            buf[primary + i * 8..primary + i * 8 + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        buf.extend_from_slice(&compressed);
+
+        let path = std::env::temp_dir().join(format!("marketstore-variable-{}.bin", std::process::id()));
+        std::fs::write(&path, buf).unwrap();
+        let file = DataFile::open(&path).unwrap();
+        assert_eq!(file.header().variable_record_length().unwrap(), 8);
+
+        let dataset = file.read(None, None).unwrap();
+        assert_eq!(dataset.column_names, vec!["Epoch", "Bid", "Nanoseconds"]);
+        let rows = numpy_dataset_to_records(&dataset).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["Epoch"].as_i64().unwrap(), interval_start);
+        assert_eq!(rows[0]["Nanoseconds"].as_i64().unwrap(), 0);
+        let (seconds, nanoseconds) = timeframe.ticks_to_time(interval_start, ticks);
+        assert_eq!(rows[1]["Epoch"].as_i64().unwrap(), seconds);
+        assert_eq!(rows[1]["Nanoseconds"].as_i64().unwrap(), nanoseconds as i64);
+        assert_eq!(rows[1]["Bid"].as_f64().unwrap(), 11.0);
+
+        // 未压缩配置下按原始字节读取会失败
+        assert!(file.with_variable_compression(false).read(None, None).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_truncated_header() {
+        assert!(DataFileHeader::parse(&header(2024, 0, 16, &[])[..1024]).is_err());
+        assert!(DataFileHeader::parse(&header(2024, 5, 16, &[])).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        models::{DataShape, NumpyDataset, OHLCVData, QueryRequest},
+        storage::{Catalog, DataFileHeader, DataFileWriter, DATA_FILE_HEADER_SIZE},
+        utils::{numpy_dataset_to_records, year_start_epoch},
+    };
+    use std::path::PathBuf;
+
+    const KEY: &str = "AAPL/1Min/OHLCV";
+
+    fn ohlcv_shapes() -> Vec<DataShape> {
+        ["Epoch", "Open", "High", "Low", "Close", "Volume"]
+            .iter()
+            .map(|name| DataShape {
+                name: name.to_string(),
+                data_type: if *name == "Epoch" { "i8" } else { "f4" }.to_string(),
+            })
+            .collect()
+    }
+
+    fn bar(epoch: i64, close: f32) -> OHLCVData {
+        OHLCVData { epoch, open: close, high: close, low: close, close, volume: 100.0 }
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("marketstore-writer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    #[test]
+    fn test_written_catalog_reads_back() {
+        let root = temp_root("roundtrip");
+        let jan1 = year_start_epoch(2024);
+
+        let mut writer = DataFileWriter::create(&root, KEY, &ohlcv_shapes()).unwrap();
+        let bars = vec![bar(jan1 - 60, 1.0), bar(jan1, 2.0), bar(jan1 + 60, 3.0), bar(jan1 + 600, 4.0)];
+        assert_eq!(writer.write_ohlcv(&bars).unwrap(), 4);
+        // 同一分钟再次写入覆盖原记录
+        writer.write_ohlcv(&[bar(jan1 + 60, 3.5)]).unwrap();
+        writer.finish().unwrap();
+
+        let bucket = root.join(KEY);
+        assert_eq!(std::fs::read_to_string(root.join("category_name")).unwrap(), "Symbol");
+        assert_eq!(std::fs::read_to_string(bucket.join("category_name")).unwrap(), "Year");
+
+        // 5列f4共20字节，对齐到24字节再加8字节index；2024年为闰年
+        let header = DataFileHeader::parse(&std::fs::read(bucket.join("2024.bin")).unwrap()[..DATA_FILE_HEADER_SIZE]).unwrap();
+        assert_eq!(header.record_length, 32);
+        assert_eq!(header.description, "Default");
+        assert_eq!(std::fs::metadata(bucket.join("2024.bin")).unwrap().len(), 37024 + 366 * 1440 * 32);
+        assert_eq!(std::fs::metadata(bucket.join("2023.bin")).unwrap().len(), 37024 + 365 * 1440 * 32);
+
+        let request = QueryRequest {
+            destination: KEY.to_string(),
+            epoch_start: None,
+            epoch_end: None,
+            limit_record_count: None,
+            limit_from_start: true,
+            columns: Vec::new(),
+        };
+        let dataset = Catalog::open(&root).unwrap().query(&request).unwrap();
+        let rows = numpy_dataset_to_records(dataset.data.as_ref().unwrap()).unwrap();
+        let closes: Vec<(i64, f64)> = rows
+            .iter()
+            .map(|row| (row["Epoch"].as_i64().unwrap(), row["Close"].as_f64().unwrap()))
+            .collect();
+        assert_eq!(closes, vec![(jan1 - 60, 1.0), (jan1, 2.0), (jan1 + 60, 3.5), (jan1 + 600, 4.0)]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_mismatched_existing_file_and_unslotted_epochs() {
+        let root = temp_root("mismatch");
+        let jan1 = year_start_epoch(2024);
+        let mut writer = DataFileWriter::create(&root, KEY, &ohlcv_shapes()).unwrap();
+        writer.write_ohlcv(&[bar(jan1, 1.0)]).unwrap();
+        writer.finish().unwrap();
+
+        let close_only = vec![DataShape { name: "Close".to_string(), data_type: "f8".to_string() }];
+        let mut other = DataFileWriter::create(&root, KEY, &close_only).unwrap();
+        let dataset = NumpyDataset {
+            column_types: vec!["i8".to_string(), "f8".to_string()],
+            column_names: vec!["Epoch".to_string(), "Close".to_string()],
+            column_data: vec![jan1.to_le_bytes().to_vec(), 1.0f64.to_le_bytes().to_vec()],
+            length: 1,
+        };
+        assert!(other.write(&dataset).is_err());
+
+        // 1D周期的1月1日index为0，没有记录位置
+        let mut daily = DataFileWriter::create(&root, "AAPL/1D/OHLCV", &ohlcv_shapes()).unwrap();
+        assert!(daily.write_ohlcv(&[bar(jan1, 1.0)]).is_err());
+        assert!(DataFileWriter::create(&root, "AAPL/1Min", &ohlcv_shapes()).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{HeartbeatConfig, WebSocketClient},
+        error::MarketStoreError,
+        models::StreamSubscription,
+    };
+    use futures::{SinkExt, StreamExt};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    /// 回显订阅后，`keep_reading` 为 false 时停止读取，模拟半开连接（不再回复pong）
+    async fn spawn_server(keep_reading: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            if let Some(Ok(subscribe)) = ws.next().await {
+                ws.send(subscribe).await.unwrap();
+            }
+            if keep_reading {
+                // tungstenite在读取时自动回复pong
+                while let Some(Ok(_)) = ws.next().await {}
+            } else {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    fn subscription() -> StreamSubscription {
+        StreamSubscription::new().add_stream("TEST/1Min/OHLCV")
+    }
+
+    #[tokio::test]
+    async fn test_missing_pong_declares_connection_dead() {
+        let url = spawn_server(false).await;
+        let heartbeat = HeartbeatConfig {
+            ping_interval: Some(Duration::from_millis(50)),
+            pong_timeout: Duration::from_millis(100),
+            idle_timeout: None,
+        };
+
+        let client = WebSocketClient::connect(&url).await.unwrap().with_heartbeat(heartbeat);
+        let mut stream = client.subscribe_stream(subscription(), 4).await.unwrap();
+
+        let next = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap();
+        assert!(matches!(next, Some(Err(MarketStoreError::Timeout(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_without_pings() {
+        let url = spawn_server(false).await;
+        let heartbeat = HeartbeatConfig {
+            ping_interval: None,
+            pong_timeout: Duration::from_millis(100),
+            idle_timeout: Some(Duration::from_millis(150)),
+        };
+        // 读空闲超时默认关闭，需显式开启
+        assert_eq!(HeartbeatConfig::default().idle_timeout, None);
+        let opted_in = HeartbeatConfig { pong_timeout: Duration::from_millis(100), ..HeartbeatConfig::disabled() };
+        assert_eq!(opted_in.with_idle_timeout(Duration::from_millis(150)), heartbeat);
+
+        let client = WebSocketClient::connect(&url).await.unwrap().with_heartbeat(heartbeat);
+        let mut stream = client.subscribe_stream(subscription(), 4).await.unwrap();
+
+        let next = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap();
+        assert!(matches!(next, Some(Err(MarketStoreError::Timeout(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_pongs_keep_connection_alive() {
+        let url = spawn_server(true).await;
+        let heartbeat = HeartbeatConfig {
+            ping_interval: Some(Duration::from_millis(50)),
+            pong_timeout: Duration::from_millis(100),
+            idle_timeout: Some(Duration::from_millis(150)),
+        };
+
+        let client = WebSocketClient::connect(&url).await.unwrap().with_heartbeat(heartbeat);
+        let mut stream = client.subscribe_stream(subscription(), 4).await.unwrap();
+
+        // 多个ping周期内既没有推送也不应判定失效
+        let next = tokio::time::timeout(Duration::from_millis(600), stream.next()).await;
+        assert!(next.is_err());
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        models::{DataShape, NumpyDataset, OHLCVData},
+        storage::{Catalog, DataFileHeader, DataFileWriter, IntegrityChecker, IssueKind, RecordType, DATA_FILE_HEADER_SIZE},
+        utils::{year_start_epoch, Timeframe},
+    };
+    use std::path::PathBuf;
+
+    fn ohlcv_shapes() -> Vec<DataShape> {
+        ["Open", "High", "Low", "Close", "Volume"]
+            .iter()
+            .map(|name| DataShape { name: name.to_string(), data_type: "f4".to_string() })
+            .collect()
+    }
+
+    fn bar(epoch: i64) -> OHLCVData {
+        OHLCVData { epoch, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 }
+    }
+
+    /// AAPL两年、MSFT一年，每个文件前三分钟有数据
+    fn seeded_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("marketstore-integrity-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (key, years) in [("AAPL/1Min/OHLCV", vec![2023, 2024]), ("MSFT/1Min/OHLCV", vec![2024])] {
+            let mut writer = DataFileWriter::create(&root, key, &ohlcv_shapes()).unwrap();
+            for year in years {
+                let jan1 = year_start_epoch(year);
+                writer.write_ohlcv(&[bar(jan1), bar(jan1 + 60), bar(jan1 + 120)]).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        root
+    }
+
+    fn patch(path: &PathBuf, offset: usize, bytes: &[u8]) {
+        let mut buf = std::fs::read(path).unwrap();
+        buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+        std::fs::write(path, buf).unwrap();
+    }
+
+    #[test]
+    fn test_clean_catalog_passes_with_checksums() {
+        let root = seeded_root("clean");
+        let report = IntegrityChecker::new().with_checksums(12).check(&Catalog::open(&root).unwrap());
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.files.len(), 3);
+        assert!(report.files.iter().all(|file| file.records == 3));
+
+        let checksums = report.files[0].checksums.as_ref().unwrap();
+        assert_eq!(checksums.len(), 13);
+        assert_eq!(checksums[0].name, "Hdr");
+        assert_eq!(checksums[12].name, "Dec");
+        // 只有1月的块含数据
+        assert_ne!(checksums[1].sum, 0);
+        assert_eq!(checksums[2].sum, 0);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["files"][0]["path"], "AAPL/1Min/OHLCV/2023.bin");
+        assert_eq!(json["files"][0]["record_type"], "fixed");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reports_misaligned_records_size_and_header_problems() {
+        let root = seeded_root("corrupt");
+        let aapl = root.join("AAPL/1Min/OHLCV");
+
+        // 第3个位置写入index 2：既不递增也不在对应位置
+        patch(&aapl.join("2024.bin"), DATA_FILE_HEADER_SIZE + 2 * 32, &2i64.to_le_bytes());
+        // 截断文件
+        let file = std::fs::OpenOptions::new().write(true).open(aapl.join("2023.bin")).unwrap();
+        file.set_len(DATA_FILE_HEADER_SIZE as u64 + 10 * 32).unwrap();
+        // 文件头年份与文件名不符
+        patch(&root.join("MSFT/1Min/OHLCV/2024.bin"), 264, &2025i64.to_le_bytes());
+
+        let report = IntegrityChecker::new().check(&Catalog::open(&root).unwrap());
+        assert!(!report.is_ok());
+        let kinds = |path: &str| -> Vec<IssueKind> {
+            report.files.iter().find(|file| file.path == path).unwrap().issues.iter().map(|issue| issue.kind).collect()
+        };
+        assert_eq!(kinds("AAPL/1Min/OHLCV/2023.bin"), vec![IssueKind::FileSize]);
+        assert_eq!(kinds("AAPL/1Min/OHLCV/2024.bin"), vec![IssueKind::EpochOrder]);
+        assert!(kinds("MSFT/1Min/OHLCV/2024.bin").contains(&IssueKind::Header));
+
+        let misplaced = &report.files.iter().find(|file| file.path == "AAPL/1Min/OHLCV/2024.bin").unwrap().issues[0];
+        assert_eq!(misplaced.offset, Some(DATA_FILE_HEADER_SIZE as u64 + 64));
+        assert_eq!(report.issue_count(), report.files.iter().map(|file| file.issues.len()).sum::<usize>());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_header_year_is_reported_without_scanning_records() {
+        let root = seeded_root("year");
+        // 年份接近i32上限时按文件头计算文件大小会溢出
+        patch(&root.join("MSFT/1Min/OHLCV/2024.bin"), 264, &(i32::MAX as i64).to_le_bytes());
+
+        let report = IntegrityChecker::new().with_checksums(12).check(&Catalog::open(&root).unwrap());
+        let file = report.files.iter().find(|file| file.key == "MSFT/1Min/OHLCV").unwrap();
+        assert_eq!(file.issues.iter().map(|issue| issue.kind).collect::<Vec<_>>(), vec![IssueKind::Header]);
+        assert_eq!(file.records, 0);
+        assert_eq!(file.checksums.as_ref().unwrap().len(), 13);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reports_schema_drift_between_years() {
+        let root = seeded_root("schema");
+        // 2024年文件改为不含Volume的数据结构
+        std::fs::remove_file(root.join("AAPL/1Min/OHLCV/2024.bin")).unwrap();
+        let mut writer = DataFileWriter::create(&root, "AAPL/1Min/OHLCV", &ohlcv_shapes()[..4]).unwrap();
+        let dataset = NumpyDataset {
+            column_types: vec!["i8", "f4", "f4", "f4", "f4"].into_iter().map(String::from).collect(),
+            column_names: vec!["Epoch", "Open", "High", "Low", "Close"].into_iter().map(String::from).collect(),
+            column_data: vec![year_start_epoch(2024).to_le_bytes().to_vec(), vec![0; 4], vec![0; 4], vec![0; 4], vec![0; 4]],
+            length: 1,
+        };
+        writer.write(&dataset).unwrap();
+        writer.finish().unwrap();
+
+        let report = IntegrityChecker::new().check(&Catalog::open(&root).unwrap());
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::Schema);
+        assert!(report.files.iter().all(|file| file.is_ok()));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reports_invalid_variable_record_pointers() {
+        let root = seeded_root("variable");
+        let bucket = root.join("BTC/1Min/TICK");
+        std::fs::create_dir_all(&bucket).unwrap();
+        for (dir, category) in [("BTC", "Timeframe"), ("BTC/1Min", "AttributeGroup"), ("BTC/1Min/TICK", "Year")] {
+            std::fs::write(root.join(dir).join("category_name"), category).unwrap();
+        }
+
+        let mut header = DataFileHeader::new(2024, &Timeframe::parse("1Min").unwrap(), &ohlcv_shapes()[3..4], "Default").unwrap();
+        header.record_type = RecordType::Variable;
+        header.record_length = 24;
+        let mut buf = header.encode().unwrap();
+        buf.resize(header.file_size() as usize, 0);
+
+        // index 1指向文件末尾的两行数据，index 2指向主区内部
+        let data_offset = buf.len() as i64;
+        let mut rows = Vec::new();
+        for ticks in [0u32, 100] {
+            rows.extend_from_slice(&1.0f32.to_le_bytes());
+            rows.extend_from_slice(&ticks.to_le_bytes());
+        }
+        let compressed = snap::raw::Encoder::new().compress_vec(&rows).unwrap();
+        for (slot, pointer) in [[1, data_offset, compressed.len() as i64], [2, 64, 16]].iter().enumerate() {
+            for (i, value) in pointer.iter().enumerate() {
+                let offset = DATA_FILE_HEADER_SIZE + slot * 24 + i * 8;
+                buf[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        buf.extend_from_slice(&compressed);
+        std::fs::write(bucket.join("2024.bin"), buf).unwrap();
+
+        let report = IntegrityChecker::new().check(&Catalog::open(&root).unwrap());
+        let file = report.files.iter().find(|file| file.key == "BTC/1Min/TICK").unwrap();
+        assert_eq!(file.records, 2);
+        assert_eq!(file.issues.len(), 1);
+        assert_eq!(file.issues[0].kind, IssueKind::IndexPointer);
+        assert_eq!(file.issues[0].offset, Some(DATA_FILE_HEADER_SIZE as u64 + 24));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{ConnectionState, GrpcClient, ReconnectPolicy, ReconnectingSubscription},
+        client::grpc_client::proto::{
+            self,
+            marketstore_server::{Marketstore, MarketstoreServer},
+        },
+        models::{StreamPayload, StreamSubscription, SubscribeMessage},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use futures::{SinkExt, StreamExt};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+    use tokio_tungstenite::tungstenite::Message;
+    use tonic::{Request, Response, Status};
+
+    fn payload_frame(key: &str, epoch: i64) -> Message {
+        let mut data = HashMap::new();
+        data.insert("Epoch".to_string(), serde_json::json!(epoch));
+        let payload = StreamPayload { key: key.to_string(), data };
+        Message::Binary(rmp_serde::to_vec_named(&payload).unwrap().into())
+    }
+
+    /// 每个连接：读取订阅、推送一条数据；第一个连接随后主动关闭
+    async fn spawn_flaky_server(subscriptions: Arc<Mutex<Vec<Vec<String>>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connection = 0i64;
+            while let Ok((stream, _)) = listener.accept().await {
+                connection += 1;
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                if let Some(Ok(Message::Binary(data))) = ws.next().await {
+                    let msg: SubscribeMessage = rmp_serde::from_slice(&data).unwrap();
+                    subscriptions.lock().unwrap().push(msg.streams);
+                    ws.send(Message::Binary(data)).await.unwrap();
+                }
+                ws.send(payload_frame("TEST/1Min/OHLCV", connection * 60)).await.unwrap();
+                if connection == 1 {
+                    let _ = ws.close(None).await;
+                } else {
+                    tokio::spawn(async move { while ws.next().await.is_some() {} });
+                }
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    /// 断线期间写入的 Epoch 120..=360，按 `epoch_start` 与 `limit_record_count` 从前往后分页返回
+    struct GapNode {
+        queries: Arc<AtomicUsize>,
+    }
+
+    #[tonic::async_trait]
+    impl Marketstore for GapNode {
+        async fn query(&self, request: Request<proto::MultiQueryRequest>) -> Result<Response<proto::MultiQueryResponse>, Status> {
+            self.queries.fetch_add(1, Ordering::SeqCst);
+            let query = &request.get_ref().requests[0];
+            let epochs: Vec<i64> = (2..=6)
+                .map(|minute| minute * 60)
+                .filter(|epoch| *epoch >= query.epoch_start)
+                .take(query.limit_record_count as usize)
+                .collect();
+            let dataset = proto::NumpyMultiDataset {
+                data: Some(proto::NumpyDataset {
+                    column_types: vec!["i8".to_string()],
+                    column_names: vec!["Epoch".to_string()],
+                    column_data: vec![epochs.iter().flat_map(|epoch| epoch.to_le_bytes()).collect()],
+                    length: epochs.len() as i32,
+                    data_shapes: vec![],
+                }),
+                ..Default::default()
+            };
+            Ok(Response::new(proto::MultiQueryResponse {
+                responses: vec![proto::QueryResponse { result: Some(dataset) }],
+                ..Default::default()
+            }))
+        }
+
+        async fn create(&self, _: Request<proto::MultiCreateRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Err(Status::unimplemented("create"))
+        }
+
+        async fn write(&self, _: Request<proto::MultiWriteRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Err(Status::unimplemented("write"))
+        }
+
+        async fn destroy(&self, _: Request<proto::MultiKeyRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Err(Status::unimplemented("destroy"))
+        }
+
+        async fn list_symbols(&self, _: Request<proto::ListSymbolsRequest>) -> Result<Response<proto::ListSymbolsResponse>, Status> {
+            Err(Status::unimplemented("list_symbols"))
+        }
+
+        async fn server_version(&self, _: Request<proto::ServerVersionRequest>) -> Result<Response<proto::ServerVersionResponse>, Status> {
+            Ok(Response::new(proto::ServerVersionResponse { version: "test".to_string() }))
+        }
+    }
+
+    async fn spawn_gap_node(queries: Arc<AtomicUsize>) -> String {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(MarketstoreServer::new(GapNode { queries }))
+                .serve(addr)
+                .await
+                .unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        format!("http://{}", addr)
+    }
+
+    /// 第一个连接推送 Epoch 60 后关闭；第二个连接推送已被补齐的 300 与新的 420
+    async fn spawn_gap_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connection = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                connection += 1;
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                if let Some(Ok(subscribe)) = ws.next().await {
+                    ws.send(subscribe).await.unwrap();
+                }
+                if connection == 1 {
+                    ws.send(payload_frame("TEST/1Min/OHLCV", 60)).await.unwrap();
+                    let _ = ws.close(None).await;
+                } else {
+                    for epoch in [300, 420] {
+                        ws.send(payload_frame("TEST/1Min/OHLCV", epoch)).await.unwrap();
+                    }
+                    tokio::spawn(async move { while ws.next().await.is_some() {} });
+                }
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+            multiplier: 2.0,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(350));
+        assert_eq!(policy.backoff(100), Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn test_resubscribes_after_server_close() {
+        let subscriptions = Arc::new(Mutex::new(Vec::new()));
+        let url = spawn_flaky_server(subscriptions.clone()).await;
+
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let subscription = StreamSubscription::new().add_stream("TEST/1Min/OHLCV");
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = received.clone();
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let states_clone = states.clone();
+
+        let (tx, rx) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            ReconnectingSubscription::new(&url, subscription, policy)
+                .run(
+                    move |payload: StreamPayload| {
+                        received_clone.lock().unwrap().push(payload.key);
+                        Ok(())
+                    },
+                    move |state| states_clone.lock().unwrap().push(state),
+                    rx,
+                )
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let _ = tx.send(());
+        handle.await.unwrap().unwrap();
+
+        assert_eq!(received.lock().unwrap().len(), 2);
+        assert_eq!(subscriptions.lock().unwrap().len(), 2);
+        assert!(subscriptions.lock().unwrap().iter().all(|s| s == &vec!["TEST/1Min/OHLCV".to_string()]));
+
+        let states = states.lock().unwrap();
+        assert_eq!(states.first(), Some(&ConnectionState::Connecting));
+        assert!(states.iter().any(|s| matches!(s, ConnectionState::Reconnecting { attempt: 1, .. })));
+        assert_eq!(states.last(), Some(&ConnectionState::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_backfill_paginates_gap_and_drops_replayed_live_rows() {
+        let queries = Arc::new(AtomicUsize::new(0));
+        let grpc_client = GrpcClient::connect(spawn_gap_node(queries.clone()).await).await.unwrap();
+        let url = spawn_gap_server().await;
+
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(10),
+            backfill: true,
+            backfill_limit: 2,
+            ..Default::default()
+        };
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = received.clone();
+
+        let (tx, rx) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            ReconnectingSubscription::new(&url, StreamSubscription::new().add_stream("TEST/1Min/OHLCV"), policy)
+                .with_backfill_client(Arc::new(tokio::sync::Mutex::new(grpc_client)))
+                .run(
+                    move |payload: StreamPayload| {
+                        received_clone.lock().unwrap().push(payload.data["Epoch"].as_i64().unwrap());
+                        Ok(())
+                    },
+                    |_| {},
+                    rx,
+                )
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let _ = tx.send(());
+        handle.await.unwrap().unwrap();
+
+        // 每页2行，分3页补齐5行；重连后重复推送的300被丢弃
+        assert_eq!(*received.lock().unwrap(), vec![60, 120, 180, 240, 300, 360, 420]);
+        assert_eq!(queries.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_retries: Some(2),
+            ..Default::default()
+        };
+
+        let (_tx, rx) = oneshot::channel();
+        let result = ReconnectingSubscription::new(&format!("ws://{}", addr), StreamSubscription::new(), policy)
+            .run(|_| Ok(()), |_| {}, rx)
+            .await;
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{read_recording, RecordedFrame, ReplaySpeed, StreamEvent, StreamRecorder, StreamReplayer, WebSocketClient},
+        models::{StreamPayload, StreamSubscription},
+    };
+    use futures::{SinkExt, StreamExt};
+    use std::collections::HashMap;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("marketstore-{}-{}.rec", name, std::process::id()))
+    }
+
+    fn payload_bytes(key: &str, epoch: i64) -> Vec<u8> {
+        let mut data = HashMap::new();
+        data.insert("Epoch".to_string(), serde_json::json!(epoch));
+        rmp_serde::to_vec_named(&StreamPayload { key: key.to_string(), data }).unwrap()
+    }
+
+    /// 回显订阅后推送 AAPL 与 MSFT 交替的四条数据
+    async fn spawn_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            if let Some(Ok(subscribe)) = ws.next().await {
+                ws.send(subscribe).await.unwrap();
+            }
+            for (i, key) in ["AAPL/1Min/OHLCV", "MSFT/1Min/OHLCV", "AAPL/1Min/OHLCV", "MSFT/1Min/OHLCV"].iter().enumerate() {
+                ws.send(Message::Binary(payload_bytes(key, i as i64).into())).await.unwrap();
+            }
+            while let Some(Ok(_)) = ws.next().await {}
+        });
+        format!("ws://{}", addr)
+    }
+
+    async fn collect_epochs(url: &str, pattern: &str) -> Vec<i64> {
+        let client = WebSocketClient::connect(url).await.unwrap();
+        let mut stream = client
+            .subscribe_stream(StreamSubscription::new().add_stream(pattern), 16)
+            .await
+            .unwrap();
+        let mut epochs = Vec::new();
+        while let Some(event) = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap() {
+            match event.unwrap() {
+                StreamEvent::Payload(payload) => epochs.push(payload.data["Epoch"].as_i64().unwrap()),
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        epochs
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_through_subscription_api() {
+        let path = temp_path("roundtrip");
+        let recorder = StreamRecorder::create(&path).unwrap();
+
+        let url = spawn_server().await;
+        let client = WebSocketClient::connect(&url).await.unwrap().with_recorder(recorder.clone());
+        let mut stream = client
+            .subscribe_stream(StreamSubscription::new().add_stream("*/1Min/OHLCV"), 16)
+            .await
+            .unwrap();
+        for _ in 0..4 {
+            tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap().unwrap().unwrap();
+        }
+        recorder.flush().unwrap();
+
+        // 订阅回显加四条推送
+        let frames = read_recording(&path).unwrap();
+        assert_eq!(frames.len(), 5);
+        assert!(frames.windows(2).all(|pair| pair[0].received_at <= pair[1].received_at));
+
+        let replayer = StreamReplayer::open(&path, ReplaySpeed::AsFastAsPossible).await.unwrap();
+        assert_eq!(collect_epochs(replayer.url(), "AAPL/*/*").await, vec![0, 2]);
+        // 每条连接都从头回放
+        assert_eq!(collect_epochs(replayer.url(), "*/*/*").await, vec![0, 1, 2, 3]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_accelerated_replay_scales_gaps() {
+        let frames = (0..3)
+            .map(|i| RecordedFrame {
+                received_at: i * 200_000_000,
+                data: payload_bytes("AAPL/1Min/OHLCV", i),
+            })
+            .collect();
+        let replayer = StreamReplayer::from_frames(frames, ReplaySpeed::Accelerated(2.0)).await.unwrap();
+
+        let started = Instant::now();
+        assert_eq!(collect_epochs(replayer.url(), "AAPL/1Min/OHLCV").await, vec![0, 1, 2]);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(180), "replay too fast: {:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(400), "replay too slow: {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_frames_reach_file_without_explicit_flush() {
+        let path = temp_path("autoflush");
+        let recorder = StreamRecorder::create(&path).unwrap();
+        recorder.record(&payload_bytes("AAPL/1Min/OHLCV", 1));
+
+        // 写入线程写完一批帧后自动刷到文件
+        let started = Instant::now();
+        while read_recording(&path).unwrap().is_empty() {
+            assert!(started.elapsed() < Duration::from_secs(2), "frame was not flushed");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        drop(recorder);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_recording_keeps_complete_frames() {
+        let path = temp_path("truncated");
+        let recorder = StreamRecorder::create(&path).unwrap();
+        recorder.record(&payload_bytes("AAPL/1Min/OHLCV", 1));
+        recorder.flush().unwrap();
+        drop(recorder);
+
+        // 模拟写到一半的帧
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[1, 2, 3, 4, 5]).unwrap();
+
+        let frames = read_recording(&path).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data, payload_bytes("AAPL/1Min/OHLCV", 1));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{ConnectionState, ReconnectPolicy, ReplicationClient, ReplicationEvent},
+        client::grpc_client::proto::{
+            replication_server::{Replication, ReplicationServer},
+            GetWalStreamRequest, GetWalStreamResponse,
+        },
+    };
+    use futures::StreamExt;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tonic::{Request, Response, Status};
+
+    /// 第一次连接推送TG 1..=3后断开，之后的连接推送TG 3..=4并保持
+    struct MockPrimary {
+        connections: Arc<AtomicUsize>,
+    }
+
+    type WalStream = Pin<Box<dyn futures::Stream<Item = Result<GetWalStreamResponse, Status>> + Send>>;
+
+    fn tg(id: i64) -> Result<GetWalStreamResponse, Status> {
+        let mut transaction_group = id.to_le_bytes().to_vec();
+        transaction_group.extend_from_slice(&0i64.to_le_bytes());
+        Ok(GetWalStreamResponse { transaction_group })
+    }
+
+    #[tonic::async_trait]
+    impl Replication for MockPrimary {
+        type GetWALStreamStream = WalStream;
+
+        async fn get_wal_stream(&self, _: Request<GetWalStreamRequest>) -> Result<Response<WalStream>, Status> {
+            let stream: WalStream = if self.connections.fetch_add(1, Ordering::SeqCst) == 0 {
+                // 延迟后再返回错误，否则tonic会丢弃同一批次中尚未发出的消息
+                let disconnect = futures::stream::once(async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Err(Status::unavailable("primary restarting"))
+                });
+                Box::pin(futures::stream::iter(vec![tg(1), tg(2), tg(3)]).chain(disconnect))
+            } else {
+                Box::pin(futures::stream::iter(vec![tg(3), tg(4)]).chain(futures::stream::pending()))
+            };
+            Ok(Response::new(stream))
+        }
+    }
+
+    async fn spawn_primary(connections: Arc<AtomicUsize>) -> String {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(ReplicationServer::new(MockPrimary { connections }))
+                .serve(addr)
+                .await
+                .unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        format!("http://{}", addr)
+    }
+
+    fn fast_policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_millis(20),
+            ..Default::default()
+        }
+    }
+
+    async fn next_tgids(stream: &mut marketstore_rust_client::client::ReplicationStream, count: usize) -> Vec<i64> {
+        let mut ids = Vec::new();
+        while ids.len() < count {
+            let event = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap().unwrap().unwrap();
+            if let ReplicationEvent::TransactionGroup(message) = event {
+                ids.push(message.transaction_group_id);
+            }
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_and_skips_already_delivered_groups() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let url = spawn_primary(connections.clone()).await;
+
+        let mut stream = ReplicationClient::new(url, fast_policy()).stream(16);
+        assert_eq!(next_tgids(&mut stream, 4).await, vec![1, 2, 3, 4]);
+
+        let position = stream.position();
+        assert_eq!(position.last_transaction_group_id, Some(4));
+        assert_eq!(position.delivered, 4);
+        assert_eq!(position.skipped, 1);
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        stream.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_position_tracks_consumed_groups_not_buffered_ones() {
+        let url = spawn_primary(Arc::new(AtomicUsize::new(0))).await;
+
+        let mut stream = ReplicationClient::new(url, fast_policy()).stream(16);
+        assert_eq!(next_tgids(&mut stream, 1).await, vec![1]);
+        // 等待后台任务把后续事务组放入缓冲区
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        let position = stream.position();
+        assert_eq!(position.last_transaction_group_id, Some(1));
+        assert_eq!(position.delivered, 1);
+        stream.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resumes_from_checkpoint() {
+        let url = spawn_primary(Arc::new(AtomicUsize::new(0))).await;
+
+        let mut stream = ReplicationClient::new(url, fast_policy()).with_position(2).stream(16);
+        assert_eq!(next_tgids(&mut stream, 2).await, vec![3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_reports_connection_states() {
+        let url = spawn_primary(Arc::new(AtomicUsize::new(0))).await;
+
+        let mut stream = ReplicationClient::new(url, fast_policy()).stream(16);
+        let mut states = Vec::new();
+        while states.len() < 4 {
+            let event = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap().unwrap().unwrap();
+            if let ReplicationEvent::State(state) = event {
+                states.push(std::mem::discriminant(&state));
+            }
+        }
+        assert_eq!(states[0], std::mem::discriminant(&ConnectionState::Connecting));
+        assert_eq!(states[1], std::mem::discriminant(&ConnectionState::Connected));
+        assert_eq!(states[2], std::mem::discriminant(&ConnectionState::Disconnected { reason: String::new() }));
+        assert_eq!(
+            states[3],
+            std::mem::discriminant(&ConnectionState::Reconnecting { attempt: 0, delay: Duration::ZERO })
+        );
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        aggregation::{PartialBars, Resampler, Session},
+        models::OHLCVData,
+        utils::{create_numpy_dataset_from_ohlcv, numpy_dataset_to_ohlcv, Timeframe},
+    };
+    use chrono::NaiveDate;
+    use chrono_tz::{America::New_York, Tz};
+    use std::time::Duration;
+
+    fn bar(epoch: i64, open: f32, high: f32, low: f32, close: f32) -> OHLCVData {
+        OHLCVData { epoch, open, high, low, close, volume: 10.0 }
+    }
+
+    fn minute_bars(start: i64, count: i64) -> Vec<OHLCVData> {
+        (0..count)
+            .map(|i| {
+                let price = 100.0 + i as f32;
+                bar(start + i * 60, price, price + 0.5, price - 0.5, price + 0.25)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resample_ohlcv_semantics_and_partial_trailing_bar() {
+        let one_min = Timeframe::parse("1Min").unwrap();
+        // 7根1分钟bar，乱序输入，结果为一根完整与一根未完成的5分钟bar
+        let mut bars = minute_bars(1_700_000_100, 7);
+        bars.reverse();
+        let dataset = create_numpy_dataset_from_ohlcv(&bars);
+        let decoded = numpy_dataset_to_ohlcv(&dataset).unwrap();
+
+        let resampler = Resampler::new(Timeframe::parse("5Min").unwrap());
+        let candles = resampler.resample(&one_min, &decoded).unwrap();
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].bar, OHLCVData { epoch: 1_700_000_100, open: 100.0, high: 104.5, low: 99.5, close: 104.25, volume: 50.0 });
+        assert_eq!(candles[0].count, 5);
+        assert!(candles[0].complete);
+        assert_eq!(candles[1].bar.epoch, 1_700_000_400);
+        assert_eq!(candles[1].count, 2);
+        assert!(!candles[1].complete);
+
+        let candles = resampler.with_partial_bars(PartialBars::Exclude).resample(&one_min, &decoded).unwrap();
+        assert_eq!(candles.len(), 1);
+
+        // 目标周期不是源周期的整数倍
+        let seven = Resampler::new(Timeframe::parse("7Min").unwrap());
+        assert!(seven.resample(&Timeframe::parse("5Min").unwrap(), &decoded).is_err());
+    }
+
+    #[test]
+    fn test_alignment_offset_shifts_bar_starts() {
+        let hour = Resampler::new(Timeframe::parse("1H").unwrap()).with_offset(Duration::from_secs(30 * 60));
+        assert_eq!(hour.bar_bounds(3600 * 10 + 60 * 20), Some((3600 * 9 + 1800, 3600 * 10 + 1800)));
+        assert_eq!(hour.bar_bounds(3600 * 10 + 60 * 40), Some((3600 * 10 + 1800, 3600 * 11 + 1800)));
+
+        // 10:00 - 11:59 的1分钟bar按30分对齐为3根1H bar，首尾两根各只有半小时数据
+        let candles = hour.resample(&Timeframe::parse("1Min").unwrap(), &minute_bars(3600 * 10, 120)).unwrap();
+        let summary: Vec<(i64, usize, bool)> = candles.iter().map(|c| (c.bar.epoch, c.count, c.complete)).collect();
+        assert_eq!(summary, vec![(3600 * 9 + 1800, 30, true), (3600 * 10 + 1800, 60, true), (3600 * 11 + 1800, 30, false)]);
+    }
+
+    #[test]
+    fn test_session_anchors_bars_at_open_and_truncates_at_close() {
+        // 美东 09:30 - 16:00，夏令时UTC-4；2024-07-01 13:30 UTC 开盘
+        let session = Session::new(Duration::from_secs(9 * 3600 + 1800), Duration::from_secs(16 * 3600), New_York);
+        let open = 1_719_840_600;
+        assert_eq!(session.bounds(open + 60), Some((open, open + 390 * 60)));
+        assert_eq!(session.bounds(open - 60), None);
+
+        // 盘前10分钟到收盘后10分钟的1分钟bar
+        let bars = minute_bars(open - 600, 410);
+        let resampler = Resampler::new(Timeframe::parse("1H").unwrap()).with_session(session);
+        let candles = resampler.resample(&Timeframe::parse("1Min").unwrap(), &bars).unwrap();
+        assert_eq!(candles.len(), 7);
+        assert_eq!(candles[0].bar.epoch, open);
+        assert_eq!(candles[0].bar.open, 110.0);
+        assert_eq!(candles[6].bar.epoch, open + 6 * 3600);
+        // 最后一根在16:00截断，只有30分钟且已完成
+        assert_eq!(candles[6].count, 30);
+        assert!(candles.iter().all(|candle| candle.complete));
+        assert_eq!(candles.iter().map(|candle| candle.count).sum::<usize>(), 390);
+
+        // 跨越午夜的时段
+        let overnight = Session::new(Duration::from_secs(22 * 3600), Duration::from_secs(2 * 3600), Tz::UTC);
+        assert_eq!(overnight.bounds(86400 + 3600), Some((86400 - 7200, 86400 + 7200)));
+        assert_eq!(overnight.bounds(86400 + 3 * 3600), None);
+    }
+
+    #[test]
+    fn test_session_follows_daylight_saving_change() {
+        let session = Session::new(Duration::from_secs(9 * 3600 + 1800), Duration::from_secs(16 * 3600), New_York);
+        // 2024-03-08 周五为EST（14:30 UTC开盘），2024-03-10 切换夏令时，2024-03-11 周一为EDT（13:30 UTC开盘）
+        let friday_open = 1_709_908_200;
+        let monday_open = 1_710_163_800;
+        assert_eq!(session.on(NaiveDate::from_ymd_opt(2024, 3, 8).unwrap()), (friday_open, friday_open + 390 * 60));
+        assert_eq!(session.on(NaiveDate::from_ymd_opt(2024, 3, 11).unwrap()), (monday_open, monday_open + 390 * 60));
+        assert_eq!(session.bounds(monday_open - 60), None);
+        assert_eq!(session.bounds(friday_open + 390 * 60 - 60), Some((friday_open, friday_open + 390 * 60)));
+
+        let mut bars = minute_bars(friday_open - 3600, 390 + 7200 / 60);
+        bars.extend(minute_bars(monday_open - 3600, 390 + 7200 / 60));
+        let resampler = Resampler::new(Timeframe::parse("1H").unwrap()).with_session(session);
+        let candles = resampler.resample(&Timeframe::parse("1Min").unwrap(), &bars).unwrap();
+        let starts: Vec<i64> = candles.iter().map(|candle| candle.bar.epoch).collect();
+        let expected: Vec<i64> = (0..7)
+            .map(|hour| friday_open + hour * 3600)
+            .chain((0..7).map(|hour| monday_open + hour * 3600))
+            .collect();
+        assert_eq!(starts, expected);
+        assert!(candles.iter().all(|candle| candle.count == 60 || candle.count == 30));
+    }
+}
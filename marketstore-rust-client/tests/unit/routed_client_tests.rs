@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{GrpcClientTrait, ReadStrategy, RoutedClient, RoutingConfig},
+        client::grpc_client::proto::{
+            self,
+            marketstore_server::{Marketstore, MarketstoreServer},
+        },
+        models::{QueryRequest, SymbolFormat},
+    };
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tonic::{Request, Response, Status};
+
+    /// 用 ListSymbols 返回节点名，便于断言请求被路由到了哪个节点
+    struct MockNode {
+        name: String,
+    }
+
+    #[tonic::async_trait]
+    impl Marketstore for MockNode {
+        async fn query(&self, _: Request<proto::MultiQueryRequest>) -> Result<Response<proto::MultiQueryResponse>, Status> {
+            Err(Status::unimplemented("query"))
+        }
+
+        async fn create(&self, _: Request<proto::MultiCreateRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Ok(Response::new(proto::MultiServerResponse::default()))
+        }
+
+        async fn write(&self, _: Request<proto::MultiWriteRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Ok(Response::new(proto::MultiServerResponse::default()))
+        }
+
+        async fn destroy(&self, _: Request<proto::MultiKeyRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Ok(Response::new(proto::MultiServerResponse::default()))
+        }
+
+        async fn list_symbols(&self, _: Request<proto::ListSymbolsRequest>) -> Result<Response<proto::ListSymbolsResponse>, Status> {
+            Ok(Response::new(proto::ListSymbolsResponse { results: vec![self.name.clone()] }))
+        }
+
+        async fn server_version(&self, _: Request<proto::ServerVersionRequest>) -> Result<Response<proto::ServerVersionResponse>, Status> {
+            Ok(Response::new(proto::ServerVersionResponse { version: self.name.clone() }))
+        }
+    }
+
+    async fn spawn_node(name: &str) -> SocketAddr {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        spawn_node_at(addr, name).await
+    }
+
+    async fn spawn_node_at(addr: SocketAddr, name: &str) -> SocketAddr {
+        let node = MockNode { name: name.to_string() };
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(MarketstoreServer::new(node))
+                .serve(addr)
+                .await
+                .unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        addr
+    }
+
+    fn unreachable_url() -> String {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        format!("http://{}", addr)
+    }
+
+    fn test_config() -> RoutingConfig {
+        RoutingConfig {
+            failure_threshold: 1,
+            recovery_threshold: 1,
+            probe_timeout: Duration::from_millis(500),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_routing_config_default() {
+        let config = RoutingConfig::default();
+        assert_eq!(config.read_strategy, ReadStrategy::RoundRobin);
+        assert!(config.failure_threshold > 0);
+        assert!(config.fallback_to_primary);
+    }
+
+    #[tokio::test]
+    async fn test_reads_round_robin_across_replicas() {
+        let primary = spawn_node("primary").await;
+        let replica_a = spawn_node("replica-a").await;
+        let replica_b = spawn_node("replica-b").await;
+
+        let mut client = RoutedClient::connect(
+            format!("http://{}", primary),
+            vec![format!("http://{}", replica_a), format!("http://{}", replica_b)],
+            test_config(),
+        ).await.unwrap();
+
+        let first = client.list_symbols(SymbolFormat::Symbol).await.unwrap();
+        let second = client.list_symbols(SymbolFormat::Symbol).await.unwrap();
+        let mut seen = vec![first[0].clone(), second[0].clone()];
+        seen.sort();
+        assert_eq!(seen, vec!["replica-a".to_string(), "replica-b".to_string()]);
+
+        // 版本查询与写入走主节点
+        assert_eq!(client.server_version().await.unwrap(), "primary");
+    }
+
+    #[tokio::test]
+    async fn test_failed_replica_is_ejected_and_reads_fall_back() {
+        let primary = spawn_node("primary").await;
+        let replica = spawn_node("replica").await;
+
+        let mut client = RoutedClient::connect(
+            format!("http://{}", primary),
+            vec![unreachable_url(), format!("http://{}", replica)],
+            test_config(),
+        ).await.unwrap();
+
+        let status = client.replica_status();
+        assert!(!status[0].healthy);
+        assert!(status[1].healthy);
+
+        for _ in 0..3 {
+            let result = client.list_symbols(SymbolFormat::Symbol).await.unwrap();
+            assert_eq!(result, vec!["replica".to_string()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_healthy_replica_without_fallback() {
+        let primary = spawn_node("primary").await;
+        let config = RoutingConfig {
+            fallback_to_primary: false,
+            ..test_config()
+        };
+
+        let mut client = RoutedClient::connect(
+            format!("http://{}", primary),
+            vec![unreachable_url()],
+            config,
+        ).await.unwrap();
+
+        let request = QueryRequest::builder()
+            .symbol("BTCUSDT")
+            .timeframe("1Min")
+            .attr_group("OHLCV")
+            .build()
+            .unwrap();
+        assert!(client.query(request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ejected_replica_is_reprobed_from_read_path() {
+        let primary = spawn_node("primary").await;
+        let replica = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let config = RoutingConfig {
+            probe_interval: Duration::from_millis(50),
+            ..test_config()
+        };
+
+        let mut client = RoutedClient::connect(
+            format!("http://{}", primary),
+            vec![format!("http://{}", replica)],
+            config,
+        ).await.unwrap();
+        assert!(!client.replica_status()[0].healthy);
+
+        // 副本上线后，读请求先回落到主节点并在后台触发重新探测
+        spawn_node_at(replica, "replica").await;
+        assert_eq!(client.list_symbols(SymbolFormat::Symbol).await.unwrap(), vec!["primary".to_string()]);
+        let mut readmitted = false;
+        for _ in 0..20 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if client.list_symbols(SymbolFormat::Symbol).await.unwrap() == vec!["replica".to_string()] {
+                readmitted = true;
+                break;
+            }
+        }
+        assert!(readmitted);
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{MarketStoreClient, SnapshotEvent, StreamEvent},
+        client::grpc_client::proto::{
+            self,
+            marketstore_server::{Marketstore, MarketstoreServer},
+        },
+        models::StreamPayload,
+    };
+    use futures::{SinkExt, StreamExt};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+    use tonic::{Request, Response, Status};
+
+    /// 查询返回 Epoch 100/160 两行，并延迟响应以便实时推送在快照期间到达
+    struct SnapshotNode;
+
+    #[tonic::async_trait]
+    impl Marketstore for SnapshotNode {
+        async fn query(&self, _: Request<proto::MultiQueryRequest>) -> Result<Response<proto::MultiQueryResponse>, Status> {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let epochs: Vec<u8> = [100i64, 160].iter().flat_map(|e| e.to_le_bytes()).collect();
+            let closes: Vec<u8> = [1.0f32, 1.5].iter().flat_map(|c| c.to_le_bytes()).collect();
+            let dataset = proto::NumpyMultiDataset {
+                data: Some(proto::NumpyDataset {
+                    column_types: vec!["i8".to_string(), "f4".to_string()],
+                    column_names: vec!["Epoch".to_string(), "Close".to_string()],
+                    column_data: vec![epochs, closes],
+                    length: 2,
+                    data_shapes: vec![],
+                }),
+                ..Default::default()
+            };
+            Ok(Response::new(proto::MultiQueryResponse {
+                responses: vec![proto::QueryResponse { result: Some(dataset) }],
+                ..Default::default()
+            }))
+        }
+
+        async fn create(&self, _: Request<proto::MultiCreateRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Err(Status::unimplemented("create"))
+        }
+
+        async fn write(&self, _: Request<proto::MultiWriteRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Err(Status::unimplemented("write"))
+        }
+
+        async fn destroy(&self, _: Request<proto::MultiKeyRequest>) -> Result<Response<proto::MultiServerResponse>, Status> {
+            Err(Status::unimplemented("destroy"))
+        }
+
+        async fn list_symbols(&self, _: Request<proto::ListSymbolsRequest>) -> Result<Response<proto::ListSymbolsResponse>, Status> {
+            Err(Status::unimplemented("list_symbols"))
+        }
+
+        async fn server_version(&self, _: Request<proto::ServerVersionRequest>) -> Result<Response<proto::ServerVersionResponse>, Status> {
+            Ok(Response::new(proto::ServerVersionResponse { version: "test".to_string() }))
+        }
+    }
+
+    async fn spawn_grpc() -> String {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(MarketstoreServer::new(SnapshotNode))
+                .serve(addr)
+                .await
+                .unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        format!("http://{}", addr)
+    }
+
+    fn payload_frame(epoch: i64, close: f64) -> Message {
+        let mut data = HashMap::new();
+        data.insert("Epoch".to_string(), serde_json::json!(epoch));
+        data.insert("Close".to_string(), serde_json::json!(close));
+        let payload = StreamPayload { key: "AAPL/1Min/OHLCV".to_string(), data };
+        Message::Binary(rmp_serde::to_vec_named(&payload).unwrap().into())
+    }
+
+    /// 快照期间乱序推送（含重复与过期行），快照结束后再推送一行
+    async fn spawn_ws() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            if let Some(Ok(subscribe)) = ws.next().await {
+                ws.send(subscribe).await.unwrap();
+            }
+            for (epoch, close) in [(160, 1.5), (220, 2.5), (100, 1.0), (200, 2.0)] {
+                ws.send(payload_frame(epoch, close)).await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            ws.send(payload_frame(280, 3.0)).await.unwrap();
+            while let Some(Ok(_)) = ws.next().await {}
+        });
+        format!("ws://{}", addr)
+    }
+
+    fn epoch_of(payload: &StreamPayload) -> i64 {
+        payload.data["Epoch"].as_i64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_then_deduplicated_live_rows() {
+        let grpc_url = spawn_grpc().await;
+        let ws_url = spawn_ws().await;
+        let client = MarketStoreClient::new(grpc_url, ws_url).await.unwrap();
+
+        let mut stream = client
+            .subscribe_with_snapshot(vec!["AAPL/1Min/OHLCV".to_string()], 2)
+            .await
+            .unwrap();
+
+        let mut seen = Vec::new();
+        while seen.len() < 6 {
+            let event = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap().unwrap().unwrap();
+            seen.push(match event {
+                SnapshotEvent::Snapshot(payload) => format!("snapshot:{}", epoch_of(&payload)),
+                SnapshotEvent::SnapshotEnd => "end".to_string(),
+                SnapshotEvent::Live(StreamEvent::Payload(payload)) => format!("live:{}", epoch_of(&payload)),
+                SnapshotEvent::Live(other) => panic!("unexpected event: {:?}", other),
+            });
+        }
+
+        assert_eq!(seen, vec!["snapshot:100", "snapshot:160", "end", "live:200", "live:220", "live:280"]);
+        stream.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_rejects_wildcard_keys() {
+        let grpc_url = spawn_grpc().await;
+        let client = MarketStoreClient::new(grpc_url, "ws://127.0.0.1:1".to_string()).await.unwrap();
+
+        assert!(client.subscribe_with_snapshot(vec!["*/1Min/OHLCV".to_string()], 10).await.is_err());
+    }
+}
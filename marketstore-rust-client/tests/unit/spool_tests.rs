@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::MockGrpcClient;
+    use marketstore_rust_client::{
+        client::{GrpcClientTrait, Spool, SpoolConfig, SpooledWrite, SpoolingClient},
+        models::OHLCVData,
+    };
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("marketstore-spool-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn spooled(key: &str, epoch: i64) -> SpooledWrite {
+        SpooledWrite {
+            key: key.to_string(),
+            rows: vec![OHLCVData { epoch, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 }],
+        }
+    }
+
+    #[test]
+    fn test_reopen_truncates_torn_tail_and_rotates_segments() {
+        let dir = temp_dir("reopen");
+        let config = SpoolConfig { segment_bytes: 1, ..SpoolConfig::new(&dir) };
+        let mut spool = Spool::open(config.clone()).unwrap();
+        spool.append(&[spooled("AAPL/1Min/OHLCV", 60), spooled("MSFT/1Min/OHLCV", 60)]).unwrap();
+        spool.append(&[spooled("AAPL/1Min/OHLCV", 120)]).unwrap();
+        drop(spool);
+
+        let segments = Spool::open(config.clone()).unwrap().segments().unwrap();
+        assert_eq!(segments.iter().map(|segment| segment.records).collect::<Vec<_>>(), vec![2, 1]);
+
+        // 模拟写入中途崩溃留下的半条记录
+        let last = &segments[1].path;
+        std::fs::OpenOptions::new().append(true).open(last).unwrap().write_all(&[42, 0, 0, 0, 1, 2]).unwrap();
+        let mut spool = Spool::open(config).unwrap();
+        let segments = spool.segments().unwrap();
+        assert_eq!(segments[1].torn_bytes, 0);
+        assert_eq!(segments[1].bytes, std::fs::metadata(last).unwrap().len());
+
+        spool.append(&[spooled("AAPL/1Min/OHLCV", 180)]).unwrap();
+        let epochs: Vec<i64> = spool.read_all().unwrap().iter().map(|write| write.rows[0].epoch).collect();
+        assert_eq!(epochs, vec![60, 60, 120, 180]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_append_rejected_when_spool_is_full() {
+        let dir = temp_dir("full");
+        let mut spool = Spool::open(SpoolConfig { max_bytes: 200, ..SpoolConfig::new(&dir) }).unwrap();
+        spool.append(&[spooled("AAPL/1Min/OHLCV", 60)]).unwrap();
+        let used = spool.total_bytes();
+        assert!(spool.append(&[spooled("AAPL/1Min/OHLCV", 120), spooled("AAPL/1Min/OHLCV", 180)]).is_err());
+        assert_eq!(spool.total_bytes(), used);
+        assert_eq!(spool.read_all().unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_spooling_client_replays_in_order_when_server_recovers() {
+        let dir = temp_dir("replay");
+        let mock = MockGrpcClient::default();
+        *mock.down.lock().unwrap() = true;
+        let mut client = SpoolingClient::new(mock.clone(), Spool::open(SpoolConfig::new(&dir)).unwrap());
+
+        let bar = |epoch| vec![OHLCVData { epoch, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 }];
+        client.write("AAPL", "1Min", "OHLCV", bar(60)).await.unwrap();
+        client.write("MSFT", "1Min", "OHLCV", bar(60)).await.unwrap();
+        assert_eq!(client.spool().read_all().unwrap().len(), 2);
+        assert!(client.replay().await.is_err());
+
+        *mock.down.lock().unwrap() = false;
+        client.write("AAPL", "1Min", "OHLCV", bar(120)).await.unwrap();
+        assert_eq!(
+            *mock.written.lock().unwrap(),
+            vec![
+                ("AAPL/1Min/OHLCV".to_string(), 60),
+                ("MSFT/1Min/OHLCV".to_string(), 60),
+                ("AAPL/1Min/OHLCV".to_string(), 120),
+            ]
+        );
+        assert!(client.spool().is_empty());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drain_quarantines_corrupt_segments_and_dead_letters_rejected_writes() {
+        let dir = temp_dir("dead-letter");
+        let config = SpoolConfig { segment_bytes: 1, ..SpoolConfig::new(&dir) };
+        let mut spool = Spool::open(config.clone()).unwrap();
+        spool.append(&[spooled("AAPL/1Min/OHLCV", 60), spooled("MSFT/1Min/OHLCV", 60)]).unwrap();
+        spool.append(&[spooled("BAD/1Min/OHLCV", 60)]).unwrap();
+        spool.append(&[spooled("AAPL/1Min/OHLCV", 120)]).unwrap();
+        drop(spool);
+
+        // 损坏第一个段中第二条记录的负载，打开时只截断最后一个段，不会修复它
+        let first = Spool::open(config.clone()).unwrap().segments().unwrap()[0].path.clone();
+        let mut buf = std::fs::read(&first).unwrap();
+        *buf.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&first, buf).unwrap();
+
+        let mut spool = Spool::open(config).unwrap();
+        let mut mock = MockGrpcClient::default();
+        assert_eq!(spool.drain(&mut mock).await.unwrap(), 2);
+        assert_eq!(
+            *mock.written.lock().unwrap(),
+            vec![("AAPL/1Min/OHLCV".to_string(), 60), ("AAPL/1Min/OHLCV".to_string(), 120)]
+        );
+        assert!(spool.is_empty());
+        assert_eq!(spool.dead_letters().unwrap(), vec![spooled("BAD/1Min/OHLCV", 60)]);
+        assert_eq!(spool.quarantined().unwrap().len(), 1);
+        assert!(!first.exists());
+
+        // 死信不再阻塞新的写入
+        let mut client = SpoolingClient::new(mock.clone(), spool);
+        client.write("MSFT", "1Min", "OHLCV", spooled("MSFT/1Min/OHLCV", 180).rows).await.unwrap();
+        assert_eq!(mock.written.lock().unwrap().len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::models::{
+        OHLCVData, StreamColumn, StreamColumns, StreamPayload, StreamValue,
+    };
+    use std::collections::HashMap;
+
+    /// 按Go服务器 `stream.Push` 的方式编码一行OHLCV：float32、int32与int64保持原宽度
+    fn go_single_row_frame() -> Vec<u8> {
+        let mut buf = Vec::new();
+        rmp::encode::write_map_len(&mut buf, 2).unwrap();
+        rmp::encode::write_str(&mut buf, "key").unwrap();
+        rmp::encode::write_str(&mut buf, "AAPL/1Min/OHLCV").unwrap();
+        rmp::encode::write_str(&mut buf, "data").unwrap();
+        rmp::encode::write_map_len(&mut buf, 6).unwrap();
+        rmp::encode::write_str(&mut buf, "Epoch").unwrap();
+        rmp::encode::write_i64(&mut buf, 123456789).unwrap();
+        for (name, value) in [("Open", 1.0f32), ("High", 2.0), ("Low", 0.5), ("Close", 1.5)] {
+            rmp::encode::write_str(&mut buf, name).unwrap();
+            rmp::encode::write_f32(&mut buf, value).unwrap();
+        }
+        rmp::encode::write_str(&mut buf, "Volume").unwrap();
+        rmp::encode::write_i32(&mut buf, 10).unwrap();
+        buf
+    }
+
+    fn go_multi_row_frame() -> Vec<u8> {
+        let mut buf = Vec::new();
+        rmp::encode::write_map_len(&mut buf, 2).unwrap();
+        rmp::encode::write_str(&mut buf, "key").unwrap();
+        rmp::encode::write_str(&mut buf, "AAPL/1Min/OHLCV").unwrap();
+        rmp::encode::write_str(&mut buf, "data").unwrap();
+        rmp::encode::write_map_len(&mut buf, 2).unwrap();
+        rmp::encode::write_str(&mut buf, "Epoch").unwrap();
+        rmp::encode::write_array_len(&mut buf, 2).unwrap();
+        rmp::encode::write_i64(&mut buf, 60).unwrap();
+        rmp::encode::write_i64(&mut buf, 120).unwrap();
+        rmp::encode::write_str(&mut buf, "Close").unwrap();
+        rmp::encode::write_array_len(&mut buf, 2).unwrap();
+        rmp::encode::write_f32(&mut buf, 1.5).unwrap();
+        rmp::encode::write_f64(&mut buf, 2.5).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_stream_value_preserves_msgpack_widths() {
+        let payload: StreamPayload<HashMap<String, StreamValue>> =
+            rmp_serde::from_slice(&go_single_row_frame()).unwrap();
+
+        assert_eq!(payload.key, "AAPL/1Min/OHLCV");
+        assert_eq!(payload.data["Epoch"], StreamValue::Int64(123456789));
+        assert_eq!(payload.data["Open"], StreamValue::Float32(1.0));
+        assert_eq!(payload.data["Volume"], StreamValue::Int32(10));
+        assert_eq!(payload.data["Volume"].as_f64(), Some(10.0));
+    }
+
+    #[test]
+    fn test_compact_integers_decode_as_smallest_width() {
+        let mut buf = Vec::new();
+        rmp::encode::write_sint(&mut buf, 5).unwrap();
+        let value: StreamValue = rmp_serde::from_slice(&buf).unwrap();
+        assert_eq!(value, StreamValue::UInt8(5));
+        assert_eq!(value.as_i64(), Some(5));
+    }
+
+    #[test]
+    fn test_typed_payload_decodes_user_record() {
+        let payload: StreamPayload<OHLCVData> = rmp_serde::from_slice(&go_single_row_frame()).unwrap();
+
+        assert_eq!(payload.data, OHLCVData {
+            epoch: 123456789,
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume: 10.0,
+        });
+    }
+
+    #[test]
+    fn test_default_payload_still_decodes() {
+        let payload: StreamPayload = rmp_serde::from_slice(&go_single_row_frame()).unwrap();
+        assert_eq!(payload.data["Epoch"], serde_json::json!(123456789));
+    }
+
+    #[test]
+    fn test_columnar_payload_single_row() {
+        let payload: StreamPayload<StreamColumns> = rmp_serde::from_slice(&go_single_row_frame()).unwrap();
+
+        assert_eq!(payload.data.len(), 1);
+        assert_eq!(payload.data.column("Epoch"), Some(&StreamColumn::Int64(vec![123456789])));
+        assert_eq!(payload.data.column("Open"), Some(&StreamColumn::Float32(vec![1.0])));
+        assert_eq!(payload.data.column("Volume"), Some(&StreamColumn::Int32(vec![10])));
+    }
+
+    #[test]
+    fn test_columnar_payload_multi_row_promotes_widths() {
+        let payload: StreamPayload<StreamColumns> = rmp_serde::from_slice(&go_multi_row_frame()).unwrap();
+
+        assert_eq!(payload.data.len(), 2);
+        assert_eq!(payload.data.column("Epoch"), Some(&StreamColumn::Int64(vec![60, 120])));
+        assert_eq!(payload.data.column("Close"), Some(&StreamColumn::Float64(vec![1.5, 2.5])));
+    }
+
+    #[test]
+    fn test_columnar_payload_rejects_ragged_columns() {
+        let mut data = HashMap::new();
+        data.insert("Epoch".to_string(), StreamValue::Array(vec![StreamValue::Int64(1), StreamValue::Int64(2)]));
+        data.insert("Close".to_string(), StreamValue::Float32(1.0));
+
+        assert!(StreamColumns::from_map(data).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{BackpressurePolicy, HeartbeatConfig, HubSubscription, StreamEvent, StreamHub, WebSocketClient},
+        models::{StreamPayload, SubscribeMessage},
+    };
+    use futures::{SinkExt, StreamExt};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::tungstenite::Message;
+
+    struct TestServer {
+        url: String,
+        subscriptions: mpsc::UnboundedReceiver<Vec<String>>,
+        push: mpsc::UnboundedSender<(String, i64)>,
+    }
+
+    /// 只接受一条连接：回显并记录订阅消息，按 `push` 推送数据
+    async fn spawn_server() -> TestServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sub_tx, subscriptions) = mpsc::unbounded_channel();
+        let (push, mut push_rx) = mpsc::unbounded_channel::<(String, i64)>();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            loop {
+                tokio::select! {
+                    msg = ws.next() => match msg {
+                        Some(Ok(Message::Binary(data))) => {
+                            let msg: SubscribeMessage = rmp_serde::from_slice(&data).unwrap();
+                            let _ = sub_tx.send(msg.streams);
+                            ws.send(Message::Binary(data)).await.unwrap();
+                        }
+                        Some(Ok(_)) => {}
+                        _ => break,
+                    },
+                    Some((key, epoch)) = push_rx.recv() => {
+                        let mut data = HashMap::new();
+                        data.insert("Epoch".to_string(), serde_json::json!(epoch));
+                        let payload = StreamPayload { key, data };
+                        ws.send(Message::Binary(rmp_serde::to_vec_named(&payload).unwrap().into())).await.unwrap();
+                    }
+                }
+            }
+        });
+        TestServer { url: format!("ws://{}", addr), subscriptions, push }
+    }
+
+    fn streams(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    async fn next_subscription(server: &mut TestServer) -> Vec<String> {
+        tokio::time::timeout(Duration::from_secs(2), server.subscriptions.recv()).await.unwrap().unwrap()
+    }
+
+    /// 收集在短时间内到达的全部事件
+    async fn drain(consumer: &mut HubSubscription) -> (Vec<(String, i64)>, u64) {
+        let mut payloads = Vec::new();
+        let mut dropped = 0;
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(200), consumer.next()).await {
+            match event.unwrap() {
+                StreamEvent::Payload(payload) => {
+                    payloads.push((payload.key, payload.data["Epoch"].as_i64().unwrap()));
+                }
+                StreamEvent::Lagged { dropped: n } => dropped += n,
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        (payloads, dropped)
+    }
+
+    #[tokio::test]
+    async fn test_hub_routes_payloads_to_matching_consumers() {
+        let mut server = spawn_server().await;
+        let hub: StreamHub = StreamHub::connect(&server.url).await.unwrap();
+
+        let mut aapl = hub
+            .subscribe(streams(&["AAPL/*/*"]), 16, BackpressurePolicy::Block)
+            .await
+            .unwrap();
+        assert_eq!(next_subscription(&mut server).await, streams(&["AAPL/*/*"]));
+        let mut minute = hub
+            .subscribe(streams(&["*/1Min/OHLCV"]), 16, BackpressurePolicy::Block)
+            .await
+            .unwrap();
+        assert_eq!(next_subscription(&mut server).await, streams(&["AAPL/*/*", "*/1Min/OHLCV"]));
+
+        server.push.send(("AAPL/1D/OHLCV".to_string(), 1)).unwrap();
+        server.push.send(("MSFT/1Min/OHLCV".to_string(), 2)).unwrap();
+        server.push.send(("AAPL/1Min/OHLCV".to_string(), 3)).unwrap();
+
+        assert_eq!(
+            drain(&mut aapl).await,
+            (vec![("AAPL/1D/OHLCV".to_string(), 1), ("AAPL/1Min/OHLCV".to_string(), 3)], 0)
+        );
+        assert_eq!(
+            drain(&mut minute).await,
+            (vec![("MSFT/1Min/OHLCV".to_string(), 2), ("AAPL/1Min/OHLCV".to_string(), 3)], 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropping_consumer_shrinks_union() {
+        let mut server = spawn_server().await;
+        let hub: StreamHub = StreamHub::connect(&server.url).await.unwrap();
+
+        let aapl = hub
+            .subscribe(streams(&["AAPL/1Min/OHLCV"]), 16, BackpressurePolicy::default())
+            .await
+            .unwrap();
+        next_subscription(&mut server).await;
+        let _msft = hub
+            .subscribe(streams(&["MSFT/1Min/OHLCV"]), 16, BackpressurePolicy::default())
+            .await
+            .unwrap();
+        next_subscription(&mut server).await;
+
+        drop(aapl);
+        assert_eq!(next_subscription(&mut server).await, streams(&["MSFT/1Min/OHLCV"]));
+        assert_eq!(hub.consumer_count(), 1);
+        // 收缩在后台确认，确认后才更新
+        let mut acked = false;
+        for _ in 0..20 {
+            if hub.streams() == streams(&["MSFT/1Min/OHLCV"]) {
+                acked = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(acked);
+    }
+
+    #[tokio::test]
+    async fn test_backpressure_policies() {
+        let mut server = spawn_server().await;
+        let hub: StreamHub = StreamHub::connect(&server.url).await.unwrap();
+
+        let mut oldest = hub
+            .subscribe(streams(&["TEST/1Min/OHLCV"]), 2, BackpressurePolicy::DropOldest)
+            .await
+            .unwrap();
+        let mut newest = hub
+            .subscribe(streams(&["TEST/1Min/OHLCV"]), 2, BackpressurePolicy::DropNewest)
+            .await
+            .unwrap();
+        next_subscription(&mut server).await;
+
+        for epoch in 0..5 {
+            server.push.send(("TEST/1Min/OHLCV".to_string(), epoch)).unwrap();
+        }
+        // 让分发先填满缓冲区
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let key = "TEST/1Min/OHLCV".to_string();
+        assert_eq!(drain(&mut oldest).await, (vec![(key.clone(), 3), (key.clone(), 4)], 3));
+        assert_eq!(drain(&mut newest).await, (vec![(key.clone(), 0), (key, 1)], 3));
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_delivers_everything() {
+        let mut server = spawn_server().await;
+        let hub: StreamHub = StreamHub::connect(&server.url).await.unwrap();
+
+        let mut consumer = hub
+            .subscribe(streams(&["TEST/1Min/OHLCV"]), 1, BackpressurePolicy::Block)
+            .await
+            .unwrap();
+        next_subscription(&mut server).await;
+
+        for epoch in 0..5 {
+            server.push.send(("TEST/1Min/OHLCV".to_string(), epoch)).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let (payloads, dropped) = drain(&mut consumer).await;
+        assert_eq!(payloads.iter().map(|(_, epoch)| *epoch).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_consumer_does_not_trip_heartbeat() {
+        let mut server = spawn_server().await;
+        let heartbeat = HeartbeatConfig {
+            ping_interval: Some(Duration::from_millis(50)),
+            pong_timeout: Duration::from_millis(100),
+            idle_timeout: None,
+        };
+        let client = WebSocketClient::connect(&server.url).await.unwrap().with_heartbeat(heartbeat);
+        let hub: StreamHub = StreamHub::from_client(client);
+
+        let mut consumer = hub
+            .subscribe(streams(&["TEST/1Min/OHLCV"]), 1, BackpressurePolicy::Block)
+            .await
+            .unwrap();
+        next_subscription(&mut server).await;
+
+        // 超过hub的帧缓冲上限，连接停止读取；消费者停顿时间远大于 ping_interval + pong_timeout
+        let total = 1100;
+        for epoch in 0..total {
+            server.push.send(("TEST/1Min/OHLCV".to_string(), epoch)).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(600)).await;
+
+        let (payloads, dropped) = drain(&mut consumer).await;
+        assert_eq!(payloads.len(), total as usize);
+        assert_eq!(dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_closing_hub_ends_consumers() {
+        let mut server = spawn_server().await;
+        let hub: StreamHub = StreamHub::connect(&server.url).await.unwrap();
+
+        let mut consumer = hub
+            .subscribe(streams(&["TEST/1Min/OHLCV"]), 4, BackpressurePolicy::Block)
+            .await
+            .unwrap();
+        next_subscription(&mut server).await;
+
+        hub.close().await.unwrap();
+        let next = tokio::time::timeout(Duration::from_secs(2), consumer.next()).await.unwrap();
+        assert!(next.is_none());
+    }
+}
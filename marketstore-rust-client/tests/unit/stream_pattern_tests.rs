@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::WebSocketClient,
+        error::MarketStoreError,
+        models::{StreamMatcher, StreamPattern, StreamSubscription},
+    };
+
+    #[test]
+    fn test_valid_stream_shape_matches_server() {
+        for pattern in ["AAPL/1Min/OHLCV", "*/*/*", "*/1Min/OHLCV", "{AAPL,MSFT}/1Min/OHLCV", "//"] {
+            assert!(StreamPattern::parse(pattern).is_ok(), "{} should be valid", pattern);
+        }
+        for pattern in ["AAPL", "AAPL/1Min", "AAPL/1Min/OHLCV/extra", "[AAPL/1Min/OHLCV", "{A,B/1Min/OHLCV"] {
+            assert!(StreamPattern::parse(pattern).is_err(), "{} should be invalid", pattern);
+        }
+    }
+
+    #[test]
+    fn test_star_does_not_cross_separator() {
+        let pattern = StreamPattern::parse("*/1Min/*").unwrap();
+        assert!(pattern.matches("AAPL/1Min/OHLCV"));
+        assert!(!pattern.matches("AAPL/1D/OHLCV"));
+        assert!(!pattern.matches("AAPL/1Min/OHLCV/extra"));
+
+        let super_star = StreamPattern::compile("AAPL/**").unwrap();
+        assert!(super_star.matches("AAPL/1Min/OHLCV"));
+    }
+
+    #[test]
+    fn test_classes_alternatives_and_escapes() {
+        let pattern = StreamPattern::parse("{AAPL,MS?T}/[0-9]Min/[!T]*").unwrap();
+        assert!(pattern.matches("AAPL/1Min/OHLCV"));
+        assert!(pattern.matches("MSFT/5Min/OHLCV"));
+        assert!(!pattern.matches("GOOG/1Min/OHLCV"));
+        assert!(!pattern.matches("AAPL/1Min/TICK"));
+
+        let escaped = StreamPattern::parse("A\\*/1Min/OHLCV").unwrap();
+        assert!(escaped.matches("A*/1Min/OHLCV"));
+        assert!(!escaped.matches("AB/1Min/OHLCV"));
+    }
+
+    #[test]
+    fn test_matcher_routes_key_to_patterns() {
+        let matcher = StreamMatcher::from_patterns(["*/1Min/OHLCV", "AAPL/*/*", "MSFT/1D/OHLCV"]).unwrap();
+
+        assert_eq!(matcher.matching("AAPL/1Min/OHLCV"), vec!["*/1Min/OHLCV", "AAPL/*/*"]);
+        assert_eq!(matcher.matching("MSFT/1D/OHLCV"), vec!["MSFT/1D/OHLCV"]);
+        assert!(!matcher.is_match("GOOG/1D/OHLCV"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_subscription_rejected_before_sending() {
+        let subscription = StreamSubscription::new().add_stream("AAPL/1Min");
+        assert!(matches!(subscription.validate(), Err(MarketStoreError::InvalidData(_))));
+
+        // 校验先于发送，服务器不作任何回复也会立即返回校验错误
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let accept = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio_tungstenite::accept_async(stream).await.unwrap()
+        });
+        let mut client = WebSocketClient::connect(&url).await.unwrap();
+        let _server = accept.await.unwrap();
+
+        assert!(matches!(client.subscribe(subscription).await, Err(MarketStoreError::InvalidData(_))));
+    }
+}
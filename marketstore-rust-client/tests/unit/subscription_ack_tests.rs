@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{StreamEvent, WebSocketClient},
+        error::MarketStoreError,
+        models::{ErrorMessage, StreamSubscription, SubscribeMessage},
+    };
+    use futures::{SinkExt, StreamExt};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// 模拟Go服务器：包含 "bad" 的stream视为非法并返回错误消息，否则回显订阅；
+    /// 首次订阅成功后依次推送 `extra` 中的帧
+    async fn spawn_server(extra: Vec<Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut extra = Some(extra);
+            while let Some(Ok(msg)) = ws.next().await {
+                let Message::Binary(data) = msg else { continue };
+                let subscribe: SubscribeMessage = rmp_serde::from_slice(&data).unwrap();
+                match subscribe.streams.iter().find(|s| s.contains("bad")) {
+                    Some(invalid) => {
+                        let error = ErrorMessage { error: format!("{} is an invalid stream", invalid) };
+                        ws.send(Message::Binary(rmp_serde::to_vec_named(&error).unwrap().into())).await.unwrap();
+                    }
+                    None => {
+                        ws.send(Message::Binary(data)).await.unwrap();
+                        for frame in extra.take().unwrap_or_default() {
+                            ws.send(Message::Binary(frame.into())).await.unwrap();
+                        }
+                    }
+                }
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_returns_rejection() {
+        let url = spawn_server(vec![]).await;
+
+        let mut client = WebSocketClient::connect(&url).await.unwrap();
+        let result = client
+            .subscribe(StreamSubscription::new().add_stream("bad/1Min/OHLCV"))
+            .await;
+
+        match result {
+            Err(MarketStoreError::SubscriptionRejected(message)) => {
+                assert_eq!(message, "bad/1Min/OHLCV is an invalid stream");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_update_keeps_previous_streams() {
+        let url = spawn_server(vec![]).await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let subscription = StreamSubscription::new().add_stream("AAPL/1Min/OHLCV");
+        let stream = client.subscribe_stream(subscription, 16).await.unwrap();
+        let handle = stream.handle();
+
+        let result = handle.add(vec!["bad/1Min/OHLCV".to_string()]).await;
+        assert!(matches!(result, Err(MarketStoreError::SubscriptionRejected(_))));
+        assert_eq!(handle.streams(), vec!["AAPL/1Min/OHLCV".to_string()]);
+
+        handle.add(vec!["MSFT/1Min/OHLCV".to_string()]).await.unwrap();
+        assert_eq!(handle.streams().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_frames_surface_as_events() {
+        let error = ErrorMessage { error: "server overloaded".to_string() };
+        let url = spawn_server(vec![
+            rmp_serde::to_vec_named(&error).unwrap(),
+            vec![0xc1, 0x00, 0x01],
+        ])
+        .await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let subscription = StreamSubscription::new().add_stream("AAPL/1Min/OHLCV");
+        let mut stream = client.subscribe_stream(subscription, 16).await.unwrap();
+
+        let next = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap();
+        match next {
+            Some(Ok(StreamEvent::ServerError { message })) => assert_eq!(message, "server overloaded"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let next = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap();
+        match next {
+            Some(Ok(StreamEvent::Undecodable { frame, .. })) => assert_eq!(frame, vec![0xc1, 0x00, 0x01]),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
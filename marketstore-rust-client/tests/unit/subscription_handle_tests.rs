@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::WebSocketClient,
+        models::{StreamSubscription, SubscribeMessage},
+    };
+    use futures::{SinkExt, StreamExt};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// 回显收到的每条订阅消息，并将其转发到 `received`
+    async fn spawn_recording_server(received: mpsc::UnboundedSender<Vec<String>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Binary(data) = msg {
+                    let msg: SubscribeMessage = rmp_serde::from_slice(&data).unwrap();
+                    let _ = received.send(msg.streams);
+                    ws.send(Message::Binary(data)).await.unwrap();
+                }
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    async fn next_subscription(received: &mut mpsc::UnboundedReceiver<Vec<String>>) -> Vec<String> {
+        tokio::time::timeout(Duration::from_secs(2), received.recv()).await.unwrap().unwrap()
+    }
+
+    fn streams(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_handle_sends_merged_stream_set() {
+        let (tx, mut received) = mpsc::unbounded_channel();
+        let url = spawn_recording_server(tx).await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let subscription = StreamSubscription::new().add_stream("AAPL/1Min/OHLCV");
+        let stream = client.subscribe_stream(subscription, 16).await.unwrap();
+        let handle = stream.handle();
+        assert_eq!(next_subscription(&mut received).await, streams(&["AAPL/1Min/OHLCV"]));
+
+        handle.add(streams(&["MSFT/1Min/OHLCV", "AAPL/1Min/OHLCV"])).await.unwrap();
+        assert_eq!(
+            next_subscription(&mut received).await,
+            streams(&["AAPL/1Min/OHLCV", "MSFT/1Min/OHLCV"])
+        );
+
+        handle.remove(&streams(&["AAPL/1Min/OHLCV"])).await.unwrap();
+        assert_eq!(next_subscription(&mut received).await, streams(&["MSFT/1Min/OHLCV"]));
+
+        handle.replace(streams(&["*/1D/OHLCV"])).await.unwrap();
+        assert_eq!(next_subscription(&mut received).await, streams(&["*/1D/OHLCV"]));
+        assert_eq!(handle.streams(), streams(&["*/1D/OHLCV"]));
+    }
+
+    #[tokio::test]
+    async fn test_handle_rejects_empty_set() {
+        let (tx, mut received) = mpsc::unbounded_channel();
+        let url = spawn_recording_server(tx).await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let subscription = StreamSubscription::new().add_stream("AAPL/1Min/OHLCV");
+        let stream = client.subscribe_stream(subscription, 16).await.unwrap();
+        let handle = stream.handle();
+        next_subscription(&mut received).await;
+
+        assert!(handle.remove(&streams(&["AAPL/1Min/OHLCV"])).await.is_err());
+        assert_eq!(handle.streams(), streams(&["AAPL/1Min/OHLCV"]));
+    }
+
+    #[tokio::test]
+    async fn test_handle_fails_after_stream_closed() {
+        let (tx, _received) = mpsc::unbounded_channel();
+        let url = spawn_recording_server(tx).await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let subscription = StreamSubscription::new().add_stream("AAPL/1Min/OHLCV");
+        let stream = client.subscribe_stream(subscription, 16).await.unwrap();
+        let handle = stream.handle();
+        stream.close().await.unwrap();
+
+        assert!(handle.add(streams(&["MSFT/1Min/OHLCV"])).await.is_err());
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        client::{StreamEvent, WebSocketClient},
+        models::{StreamPayload, StreamSubscription},
+    };
+    use futures::{SinkExt, StreamExt};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+    use tokio_tungstenite::tungstenite::Message;
+
+    fn payload_frame(key: &str, epoch: i64) -> Message {
+        let mut data = HashMap::new();
+        data.insert("Epoch".to_string(), serde_json::json!(epoch));
+        let payload = StreamPayload { key: key.to_string(), data };
+        Message::Binary(rmp_serde::to_vec_named(&payload).unwrap().into())
+    }
+
+    /// 回显订阅后推送 `count` 条数据，并通过 `closed` 报告是否收到客户端的关闭帧
+    async fn spawn_server(count: i64, closed: oneshot::Sender<bool>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            if let Some(Ok(subscribe)) = ws.next().await {
+                ws.send(subscribe).await.unwrap();
+            }
+            for epoch in 0..count {
+                ws.send(payload_frame("TEST/1Min/OHLCV", epoch)).await.unwrap();
+            }
+            let mut got_close = false;
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Close(_) = msg {
+                    got_close = true;
+                    break;
+                }
+            }
+            let _ = closed.send(got_close);
+        });
+        format!("ws://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_stream_delivers_payloads_in_order() {
+        let (closed_tx, _closed_rx) = oneshot::channel();
+        let url = spawn_server(3, closed_tx).await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let subscription = StreamSubscription::new().add_stream("TEST/1Min/OHLCV");
+        let mut stream = client.subscribe_stream(subscription, 16).await.unwrap();
+
+        for expected in 0..3 {
+            match stream.next().await {
+                Some(Ok(StreamEvent::Payload(payload))) => {
+                    assert_eq!(payload.data["Epoch"], serde_json::json!(expected));
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_reports_lag_when_buffer_overflows() {
+        let (closed_tx, _closed_rx) = oneshot::channel();
+        let url = spawn_server(10, closed_tx).await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let subscription = StreamSubscription::new().add_stream("TEST/1Min/OHLCV");
+        let mut stream = client.subscribe_stream(subscription, 2).await.unwrap();
+
+        // 让推送先填满缓冲区
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let mut payloads = 0;
+        let mut dropped = 0;
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(200), stream.next()).await {
+            match event.unwrap() {
+                StreamEvent::Payload(_) => payloads += 1,
+                StreamEvent::Lagged { dropped: n } => dropped += n,
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+
+        assert_eq!(payloads, 2);
+        assert_eq!(dropped, 8);
+    }
+
+    #[tokio::test]
+    async fn test_dropping_stream_closes_connection() {
+        let (closed_tx, closed_rx) = oneshot::channel();
+        let url = spawn_server(0, closed_tx).await;
+
+        let client = WebSocketClient::connect(&url).await.unwrap();
+        let stream = client
+            .subscribe_stream(StreamSubscription::new().add_stream("TEST/1Min/OHLCV"), 4)
+            .await
+            .unwrap();
+        drop(stream);
+
+        let got_close = tokio::time::timeout(Duration::from_secs(2), closed_rx).await.unwrap().unwrap();
+        assert!(got_close);
+    }
+}
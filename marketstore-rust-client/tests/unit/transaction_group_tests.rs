@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        error::MarketStoreError,
+        models::DataShape,
+        storage::{tg_checksum, RecordType, TransactionGroup, WriteTransactionSet},
+        utils::{year_start_epoch, Timeframe},
+    };
+
+    fn shape(name: &str, data_type: &str) -> DataShape {
+        DataShape { name: name.to_string(), data_type: data_type.to_string() }
+    }
+
+    /// 2024-01-01 00:05 的1分钟OHLCV，以及同一分钟内30.5秒处的一笔可变长成交
+    fn sample_group() -> TransactionGroup {
+        let ohlcv: Vec<u8> = [1.0f32, 2.0, 0.5, 1.5, 100.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+
+        let timeframe = Timeframe::parse("1Min").unwrap();
+        let interval_start = year_start_epoch(2024) + 5 * 60;
+        let ticks = timeframe.time_to_ticks(interval_start, interval_start + 30, 500_000_000);
+        let mut trade = 10.25f64.to_le_bytes().to_vec();
+        trade.extend_from_slice(&3i32.to_le_bytes());
+        trade.extend_from_slice(&ticks.to_le_bytes());
+
+        TransactionGroup {
+            transaction_group_id: 42,
+            write_sets: vec![
+                WriteTransactionSet {
+                    record_type: RecordType::Fixed,
+                    wal_key_path: "AAPL/1Min/OHLCV/2024.bin".to_string(),
+                    variable_record_length: 0,
+                    offset: 37024 + 5 * 28,
+                    index: 6,
+                    payload: ohlcv,
+                    data_shapes: ["Epoch:i8", "Open:f4", "High:f4", "Low:f4", "Close:f4", "Volume:f4"]
+                        .iter()
+                        .map(|s| {
+                            let (name, data_type) = s.split_once(':').unwrap();
+                            shape(name, data_type)
+                        })
+                        .collect(),
+                },
+                WriteTransactionSet {
+                    record_type: RecordType::Variable,
+                    wal_key_path: "AAPL/1Min/TRADE/2024.bin".to_string(),
+                    variable_record_length: 16,
+                    offset: 37024 + 5 * 24,
+                    index: 6,
+                    payload: trade,
+                    data_shapes: vec![shape("Epoch", "i8"), shape("Price", "f8"), shape("Size", "i4")],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_framed_roundtrip_and_rows() {
+        let group = sample_group();
+        let framed = group.encode_framed().unwrap();
+
+        let (decoded, consumed) = TransactionGroup::decode_framed(&framed).unwrap();
+        assert_eq!(consumed, framed.len());
+        assert_eq!(decoded, group);
+        // 复制流中的事务组不含长度与校验和
+        assert_eq!(TransactionGroup::decode(&framed[8..framed.len() - 16]).unwrap(), group);
+
+        let rows = decoded.rows_by_key().unwrap();
+        let bar = &rows["AAPL/1Min/OHLCV"][0];
+        assert_eq!(bar["Epoch"].as_i64().unwrap(), 1704067500);
+        assert_eq!(bar["Close"].as_f64().unwrap(), 1.5);
+        assert_eq!(bar["Volume"].as_f64().unwrap(), 100.0);
+
+        let trade = &rows["AAPL/1Min/TRADE"][0];
+        assert_eq!(trade["Epoch"].as_i64().unwrap(), 1704067530);
+        assert!((trade["Nanoseconds"].as_i64().unwrap() - 500_000_000).abs() < 1_000);
+        assert_eq!(trade["Price"].as_f64().unwrap(), 10.25);
+        assert_eq!(trade["Size"].as_i64().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_checksum_covers_length_prefix() {
+        let empty = TransactionGroup { transaction_group_id: 7, write_sets: vec![] };
+        let tg_serialized = empty.encode().unwrap();
+        let checksum: String = tg_checksum(&tg_serialized).iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(checksum, "7955d19de72e18d082e82643248428ea");
+    }
+
+    #[test]
+    fn test_corrupted_and_truncated_groups_are_rejected() {
+        let mut framed = sample_group().encode_framed().unwrap();
+        framed[30] ^= 0xff;
+        assert!(matches!(TransactionGroup::decode_framed(&framed), Err(MarketStoreError::ChecksumMismatch(_))));
+
+        let framed = sample_group().encode_framed().unwrap();
+        assert!(matches!(
+            TransactionGroup::decode_framed(&framed[..framed.len() - 1]),
+            Err(MarketStoreError::InvalidData(_))
+        ));
+        assert!(matches!(TransactionGroup::decode(&framed[8..40]), Err(MarketStoreError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_timeframe_index_conversion() {
+        let minute = Timeframe::parse("1Min").unwrap();
+        assert_eq!(minute.intervals_per_day(), 1440);
+        assert_eq!(minute.index_to_epoch(1, 2024), 1704067200);
+        assert_eq!(minute.epoch_to_index(1704067500), (2024, 6));
+
+        let day = Timeframe::parse("1D").unwrap();
+        assert_eq!(day.index_to_epoch(0, 2024), 1704067200);
+        assert_eq!(day.epoch_to_index(1704067200 + 86400 * 3), (2024, 3));
+
+        assert_eq!(Timeframe::parse("4H").unwrap().duration.as_secs(), 4 * 3600);
+        assert!(Timeframe::parse("Min").is_err());
+    }
+}
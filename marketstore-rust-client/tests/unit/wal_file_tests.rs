@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use marketstore_rust_client::{
+        models::DataShape,
+        storage::{RecordType, ReplayState, TransactionGroup, TxnStatus, WalFile, WalFileStatus, WriteTransactionSet},
+    };
+
+    fn group(tgid: i64) -> TransactionGroup {
+        TransactionGroup {
+            transaction_group_id: tgid,
+            write_sets: vec![WriteTransactionSet {
+                record_type: RecordType::Fixed,
+                wal_key_path: "AAPL/1Min/OHLCV/2024.bin".to_string(),
+                variable_record_length: 0,
+                offset: 0,
+                index: tgid,
+                payload: (tgid as f32).to_le_bytes().to_vec(),
+                data_shapes: vec![
+                    DataShape { name: "Epoch".to_string(), data_type: "i8".to_string() },
+                    DataShape { name: "Close".to_string(), data_type: "f4".to_string() },
+                ],
+            }],
+        }
+    }
+
+    fn status(replay_state: i8, pid: i64) -> Vec<u8> {
+        let mut buf = vec![2, 1, replay_state as u8];
+        buf.extend_from_slice(&pid.to_le_bytes());
+        buf
+    }
+
+    fn txn_info(tgid: i64, destination: u8, status: u8) -> Vec<u8> {
+        let mut buf = vec![1];
+        buf.extend_from_slice(&tgid.to_le_bytes());
+        buf.extend_from_slice(&[destination, status]);
+        buf
+    }
+
+    fn tg_data(tgid: i64) -> Vec<u8> {
+        let mut buf = vec![0];
+        buf.extend_from_slice(&group(tgid).encode_framed().unwrap());
+        buf
+    }
+
+    /// 与服务器写入顺序一致：PREPARING、TG数据、COMMITCOMPLETE，之后检查点完成TG 1
+    fn sample_wal() -> Vec<u8> {
+        let mut buf = status(1, 4242);
+        for tgid in [1, 2] {
+            buf.extend(txn_info(tgid, 0, 0));
+            buf.extend(tg_data(tgid));
+            buf.extend(txn_info(tgid, 0, 2));
+        }
+        buf.extend(txn_info(1, 1, 0));
+        buf.extend(txn_info(1, 1, 2));
+        buf
+    }
+
+    #[test]
+    fn test_reads_header_groups_and_pending_state() {
+        let path = std::env::temp_dir().join(format!("WALFile.{}.walfile", std::process::id()));
+        std::fs::write(&path, sample_wal()).unwrap();
+
+        let wal = WalFile::open(&path).unwrap();
+        assert_eq!(wal.header.file_status, WalFileStatus::Open);
+        assert_eq!(wal.header.replay_state, ReplayState::NotReplayed);
+        assert_eq!(wal.header.owning_instance_id, 4242);
+        assert!(wal.header.needs_replay());
+        assert!(wal.issues.is_empty());
+
+        let ids: Vec<i64> = wal.transaction_groups().map(|g| g.transaction_group_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(wal.transaction_infos().count(), 6);
+        assert_eq!(wal.transaction_infos().last().unwrap().status, TxnStatus::CommitComplete);
+
+        let pending: Vec<i64> = wal.pending_transaction_groups().iter().map(|g| g.transaction_group_id).collect();
+        assert_eq!(pending, vec![2]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_group_is_skipped_and_truncated_tail_reported() {
+        let mut buf = sample_wal();
+        let corrupted_at = buf.len();
+        let mut corrupted = tg_data(3);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        buf.extend(corrupted);
+        buf.extend(tg_data(4));
+        let truncated_at = buf.len();
+        buf.extend(&txn_info(5, 0, 0)[..4]);
+
+        let wal = WalFile::parse(&buf).unwrap();
+        let ids: Vec<i64> = wal.transaction_groups().map(|g| g.transaction_group_id).collect();
+        assert_eq!(ids, vec![1, 2, 4]);
+
+        assert_eq!(wal.issues.len(), 2);
+        assert_eq!(wal.issues[0].offset, corrupted_at);
+        assert!(wal.issues[0].message.contains("Checksum mismatch"));
+        assert_eq!(wal.issues[1].offset, truncated_at);
+    }
+
+    #[test]
+    fn test_rejects_file_without_status_header() {
+        assert!(WalFile::parse(&tg_data(1)).is_err());
+        assert!(WalFile::parse(&[]).is_err());
+    }
+}